@@ -0,0 +1,388 @@
+//! A command-line interface to Sotto, for transcribing without the app's window (e.g. in scripts
+//! and pipelines). This uses the same models, recording and transcription as the app, and tidies
+//! up transcripts according to the user's settings, unless asked not to.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use sotto_core::errors::Error;
+use sotto_core::export::ExportFormat;
+use sotto_core::model::{self, Model};
+use sotto_core::settings::{Settings, SettingsExt};
+use sotto_core::transcribe::{Transcript, WhisperContext};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use tokio::sync::oneshot;
+use tracing_subscriber::EnvFilter;
+
+mod server;
+
+#[derive(Parser)]
+#[command(
+    name = "sotto",
+    version,
+    about = "Private, local dictation and transcription"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Transcribes WAV files
+    Transcribe {
+        /// The files to transcribe
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// A directory to write each transcript to (named after its file), rather than printing
+        /// them all
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+        #[command(flatten)]
+        options: TranscribeOptions,
+    },
+    /// Records from the microphone until Enter or Ctrl-C is pressed, and then prints the transcript
+    Record {
+        #[command(flatten)]
+        options: TranscribeOptions,
+    },
+    /// Runs a server on localhost that other programs can transcribe with, through an
+    /// OpenAI-compatible HTTP API or a WebSocket stream
+    Serve {
+        /// The port to listen on
+        #[arg(short, long, default_value_t = 8178)]
+        port: u16,
+        /// The model to transcribe with, which will be downloaded if necessary (by default, the
+        /// one from whichever profile is active in the app)
+        #[arg(short, long, value_enum)]
+        model: Option<ModelArg>,
+        /// How many transcriptions can run at once (any more wait their turn)
+        #[arg(long, default_value_t = 1)]
+        max_concurrent: usize,
+        /// A key clients have to give (as a bearer token) to use the server
+        #[arg(long, env = "SOTTO_API_KEY")]
+        api_key: Option<String>,
+        /// Leave transcripts exactly as they were transcribed, ignoring the spoken punctuation and
+        /// normalization settings
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Lists or downloads speech recognition models
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModelsCommand {
+    /// Lists the available models, and where they are if they've been downloaded
+    List,
+    /// Downloads a model, replacing it if it's already been downloaded
+    Download {
+        #[arg(value_enum)]
+        model: ModelArg,
+    },
+}
+
+#[derive(Args)]
+struct TranscribeOptions {
+    /// The model to transcribe with, which will be downloaded if necessary (by default, the one
+    /// from the profile being used)
+    #[arg(short, long, value_enum)]
+    model: Option<ModelArg>,
+    /// The name of the app profile to transcribe with, which decides the model, language and how
+    /// transcripts are tidied up (by default, the one that's active in the app)
+    #[arg(short, long)]
+    profile: Option<String>,
+    /// The format to write transcripts in
+    #[arg(short, long, value_enum, default_value_t = Format::Txt)]
+    format: Format,
+    /// Leave transcripts exactly as they were transcribed, ignoring the spoken punctuation and
+    /// normalization settings
+    #[arg(long)]
+    raw: bool,
+}
+
+/// The models that can be chosen on the command line.
+#[derive(ValueEnum, Clone, Copy)]
+enum ModelArg {
+    Tiny,
+    Base,
+    Small,
+    Medium,
+    Large,
+}
+impl From<ModelArg> for Model {
+    fn from(model: ModelArg) -> Self {
+        match model {
+            ModelArg::Tiny => Self::WhisperTiny,
+            ModelArg::Base => Self::WhisperBase,
+            ModelArg::Small => Self::WhisperSmall,
+            ModelArg::Medium => Self::WhisperMedium,
+            ModelArg::Large => Self::WhisperLarge,
+        }
+    }
+}
+
+/// The formats transcripts can be written in. These are the same as for exporting from the app,
+/// plus the full transcript as JSON.
+#[derive(ValueEnum, Clone, Copy)]
+enum Format {
+    Txt,
+    Markdown,
+    Docx,
+    Srt,
+    Vtt,
+    Json,
+}
+impl Format {
+    /// Gets the file extension for this format.
+    fn extension(&self) -> &'static str {
+        match self.export_format() {
+            Some(format) => format.extension(),
+            None => "json",
+        }
+    }
+    /// Gets the export format this corresponds to, if it isn't JSON.
+    fn export_format(&self) -> Option<ExportFormat> {
+        match self {
+            Self::Txt => Some(ExportFormat::Txt),
+            Self::Markdown => Some(ExportFormat::Markdown),
+            Self::Docx => Some(ExportFormat::Docx),
+            Self::Srt => Some(ExportFormat::Srt),
+            Self::Vtt => Some(ExportFormat::Vtt),
+            Self::Json => None,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // Only problems by default, stdout is for transcripts and stderr shouldn't be too noisy
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_env("SOTTO_LOG").unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let res = match cli.command {
+        Command::Transcribe {
+            files,
+            output_dir,
+            options,
+        } => transcribe_files(&files, output_dir.as_deref(), &options).await,
+        Command::Record { options } => record(&options).await,
+        Command::Serve {
+            port,
+            model,
+            max_concurrent,
+            api_key,
+            raw,
+        } => server::serve(model.map(Model::from), port, max_concurrent, api_key, !raw).await,
+        Command::Models {
+            command: ModelsCommand::List,
+        } => list_models(),
+        Command::Models {
+            command: ModelsCommand::Download { model },
+        } => download_model(model).await,
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            report(&err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Transcribes each of the given files, writing the transcripts to the given directory (or
+/// printing them, if there isn't one).
+async fn transcribe_files(
+    files: &[PathBuf],
+    output_dir: Option<&Path>,
+    options: &TranscribeOptions,
+) -> Result<(), Error> {
+    let settings = load_settings(options.profile.as_deref())?;
+    let ctx = load_model(choose_model(options.model, &settings)).await?;
+    if let Some(output_dir) = output_dir {
+        std::fs::create_dir_all(output_dir)
+            .map_err(|err| Error::WriteExportFailed { source: err })?;
+    }
+    for file in files {
+        eprintln!("Transcribing {}...", file.display());
+        let audio = sotto_core::transcribe::read_wav(file)?;
+        let transcript = transcribe(&audio, &ctx, &settings, options)?;
+        let contents = render(&transcript, options.format)?;
+
+        match output_dir {
+            Some(output_dir) => {
+                let name = file
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "transcript".to_string());
+                let path = output_dir.join(format!("{}.{}", name, options.format.extension()));
+                std::fs::write(&path, contents)
+                    .map_err(|err| Error::WriteExportFailed { source: err })?;
+            }
+            None => write_stdout(&contents)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Records from the microphone until the user presses Enter or Ctrl-C, and then prints the
+/// transcript.
+async fn record(options: &TranscribeOptions) -> Result<(), Error> {
+    // Load the model first, so the user isn't kept waiting once they've finished speaking
+    let settings = load_settings(options.profile.as_deref())?;
+    let ctx = load_model(choose_model(options.model, &settings)).await?;
+
+    let (tx, rx) = oneshot::channel();
+    let device = settings.input_device.clone();
+    let recording = tokio::task::spawn_blocking(move || {
+        sotto_core::record::record_to_memory(device.as_deref(), rx, None)
+    });
+    eprintln!("Recording, press Enter or Ctrl-C to finish...");
+
+    // This has its own thread (rather than a blocking task), since it might never finish, and the
+    // runtime would wait for it
+    let (enter_tx, enter_rx) = oneshot::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        // If there's nothing to read from (e.g. in a pipeline), only Ctrl-C will do
+        if matches!(std::io::stdin().read_line(&mut line), Ok(read) if read > 0) {
+            let _ = enter_tx.send(());
+        }
+    });
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        Ok(()) = enter_rx => {},
+    }
+    // If the recording's already failed, we'll find out below
+    let _ = tx.send(());
+    let audio = recording
+        .await
+        .map_err(|err| Error::DictationTaskPanicked { source: err })??;
+
+    eprintln!("Transcribing...");
+    let transcript = transcribe(&audio, &ctx, &settings, options)?;
+    write_stdout(&render(&transcript, options.format)?)
+}
+
+/// Lists every model, along with where it is if it's been downloaded.
+fn list_models() -> Result<(), Error> {
+    for model in ModelArg::value_variants() {
+        // This can't fail, every variant has a name
+        let name = model.to_possible_value().unwrap();
+        match model::find(Model::from(*model))? {
+            Some(path) => println!("{:<8}{}", name.get_name(), path.display()),
+            None => println!("{:<8}(not downloaded)", name.get_name()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads the given model, printing where it ended up.
+async fn download_model(model: ModelArg) -> Result<(), Error> {
+    let model = Model::from(model);
+    // This makes sure the directory models go in exists
+    model::find(model)?;
+    let path = model::download(model).await?;
+    println!("{}", path.display());
+
+    Ok(())
+}
+
+/// Loads the app's settings, with the profile of the given name made active (just for this run)
+/// if there is one.
+fn load_settings(profile: Option<&str>) -> Result<Settings, Error> {
+    let mut settings = Settings::load()?;
+    if let Some(profile) = profile {
+        if settings.find_profile(profile).is_none() {
+            return Err(Error::ProfileNotFound);
+        }
+        settings.active_profile = profile.to_string();
+    }
+
+    Ok(settings)
+}
+
+/// Gets the model the user asked for, or the one from the active profile in the given settings if
+/// they didn't ask for one.
+fn choose_model(model: Option<ModelArg>, settings: &Settings) -> Model {
+    match model {
+        Some(model) => model.into(),
+        None => settings.profile().model,
+    }
+}
+
+/// Loads the given model, downloading it first if necessary.
+async fn load_model(model: Model) -> Result<WhisperContext, Error> {
+    let path = match model::find(model)? {
+        Some(path) => path,
+        None => {
+            eprintln!("Downloading model...");
+            model::download(model).await?
+        }
+    };
+
+    sotto_core::transcribe::load_context(&path)
+}
+
+/// Transcribes the given audio, tidying up the transcript like the app would (unless the user
+/// wants it raw).
+fn transcribe(
+    audio: &[f32],
+    ctx: &WhisperContext,
+    settings: &Settings,
+    options: &TranscribeOptions,
+) -> Result<Transcript, Error> {
+    // Nobody's going to check uncertain words here, so there's no point looking for alternatives
+    let mut transcript =
+        sotto_core::transcribe::transcribe(audio, ctx, &settings.whisper_options(), false)?;
+    if !options.raw {
+        settings.tidy(&mut transcript);
+    }
+
+    Ok(transcript)
+}
+
+/// Converts the given transcript into the given format.
+fn render(transcript: &Transcript, format: Format) -> Result<Vec<u8>, Error> {
+    match format.export_format() {
+        Some(format) => {
+            sotto_core::export::render(format, &transcript.text(), Some(&transcript.segments))
+        }
+        None => {
+            // This can't fail, we have no maps with non-string keys
+            let mut json = serde_json::to_vec_pretty(transcript).unwrap();
+            json.push(b'\n');
+            Ok(json)
+        }
+    }
+}
+
+/// Writes the given output to stdout.
+fn write_stdout(contents: &[u8]) -> Result<(), Error> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(contents)
+        .and_then(|_| stdout.flush())
+        .map_err(|err| Error::WriteExportFailed { source: err })
+}
+
+/// Prints the given error, along with everything that caused it.
+fn report(err: &Error) {
+    eprintln!("Error: {err}");
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        eprintln!("  caused by: {cause}");
+        source = cause.source();
+    }
+}
@@ -0,0 +1,443 @@
+//! Sotto's server mode, which lets other programs on this machine transcribe with Sotto's engine,
+//! without their audio ever leaving it.
+//!
+//! There are two endpoints:
+//!
+//! - `POST /v1/audio/transcriptions`, which works like OpenAI's endpoint of the same name (so
+//!   existing clients can just be pointed at Sotto). This takes a WAV file, and supports the
+//!   `json`, `text`, `srt`, `verbose_json` and `vtt` response formats. The `model`, `prompt` and
+//!   `temperature` fields are accepted, but ignored: we always use the model the server was
+//!   started with.
+//! - `GET /v1/audio/stream`, a WebSocket that takes binary messages of 16-bit little-endian PCM
+//!   audio (mono, at 16kHz), and sends back JSON messages with each segment as it's finalized
+//!   (`{"type": "segment", "text", "start_ms", "end_ms"}`). Sending the text message `end`
+//!   transcribes whatever's left, and then sends `{"type": "done", "text"}` with the whole
+//!   transcript before closing. Errors are sent as `{"type": "error", "message"}`.
+//!
+//! If the server has an API key, clients have to give it as a bearer token, like with OpenAI, or
+//! (since browsers can't set headers on WebSockets) in the `api_key` query parameter.
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, Multipart, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sotto_core::export::ExportFormat;
+use sotto_core::transcribe::SAMPLE_RATE;
+use sotto_core::{AppState, Error, Model, Transcript};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use zeroize::Zeroizing;
+
+/// The largest file that can be uploaded for transcription, which is the same as OpenAI's limit.
+const MAX_UPLOAD_BYTES: usize = 25 * 1024 * 1024;
+/// How much audio a streaming client has to send before we transcribe it, in samples.
+const STREAM_CHUNK_SAMPLES: usize = SAMPLE_RATE as usize * 5;
+/// The most audio we'll hold back from a streaming client while waiting for Whisper to finish a
+/// segment, in samples. Whisper only looks at 30 seconds at a time, so we have to stay under that.
+const MAX_PENDING_SAMPLES: usize = SAMPLE_RATE as usize * 25;
+
+/// Everything the server's handlers share.
+struct Server {
+    state: AppState,
+    /// Limits how many transcriptions run at once, since each one uses every core it can get.
+    permits: Semaphore,
+    /// The key clients have to give to use the server, if there is one.
+    api_key: Option<String>,
+    /// Whether or not transcripts should be tidied up according to the user's settings.
+    tidy: bool,
+}
+impl Server {
+    /// Checks the API key the client gave us, if we need one, either in the `Authorization` header
+    /// or as a query parameter.
+    fn authorize(&self, headers: &HeaderMap, query_key: Option<&str>) -> Result<(), ApiError> {
+        let expected = match &self.api_key {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or(query_key);
+
+        match given {
+            Some(given) if keys_match(given, expected) => Ok(()),
+            _ => Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "invalid_api_key",
+                "Incorrect API key provided.",
+            )),
+        }
+    }
+    /// Transcribes the given audio once there's room to, without blocking the runtime.
+    async fn transcribe(self: &Arc<Self>, audio: Zeroizing<Vec<f32>>) -> Result<Transcript, Error> {
+        // This can't fail, we never close the semaphore
+        let _permit = self.permits.acquire().await.unwrap();
+        let server = self.clone();
+        tokio::task::spawn_blocking(move || server.state.transcribe(&audio, server.tidy))
+            .await
+            .map_err(|err| Error::DictationTaskPanicked { source: err })?
+    }
+}
+
+/// Runs the server on the given port (only on localhost) until it's stopped with Ctrl-C. If no
+/// model is given, this follows the app's active profile.
+pub async fn serve(
+    model: Option<Model>,
+    port: u16,
+    max_concurrent: usize,
+    api_key: Option<String>,
+    tidy: bool,
+) -> Result<(), Error> {
+    let server = Arc::new(Server {
+        state: match model {
+            Some(model) => AppState::new(model).await?,
+            None => AppState::from_settings().await?,
+        },
+        permits: Semaphore::new(max_concurrent.max(1)),
+        api_key: api_key.filter(|key| !key.is_empty()),
+        tidy,
+    });
+    let router = Router::new()
+        .route("/v1/audio/transcriptions", post(transcriptions))
+        .route("/v1/audio/stream", get(stream))
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+        .with_state(server);
+
+    // Only programs on this machine can reach us
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|err| Error::StartServerFailed { source: err })?;
+    eprintln!("Listening on http://{addr}");
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .map_err(|err| Error::ServerFailed { source: err })
+}
+
+/// The response formats the transcription endpoint supports, which are the same as OpenAI's.
+#[derive(Clone, Copy)]
+enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    VerboseJson,
+    Vtt,
+}
+impl ResponseFormat {
+    fn parse(format: &str) -> Result<Self, ApiError> {
+        match format {
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            "srt" => Ok(Self::Srt),
+            "verbose_json" => Ok(Self::VerboseJson),
+            "vtt" => Ok(Self::Vtt),
+            _ => Err(ApiError::bad_request(
+                "invalid_response_format",
+                "The response format must be one of json, text, srt, verbose_json or vtt.",
+            )),
+        }
+    }
+    /// Writes the given transcript of audio of the given length, in the given language, in this
+    /// format.
+    fn render(
+        &self,
+        transcript: &Transcript,
+        duration_ms: u64,
+        language: &str,
+    ) -> Result<Response, Error> {
+        let text = transcript.text().trim().to_string();
+        let response = match self {
+            Self::Json => Json(json!({ "text": text })).into_response(),
+            Self::Text => text.into_response(),
+            Self::Srt | Self::Vtt => {
+                let (format, content_type) = match self {
+                    Self::Srt => (ExportFormat::Srt, "application/x-subrip"),
+                    _ => (ExportFormat::Vtt, "text/vtt"),
+                };
+                let contents =
+                    sotto_core::export::render(format, &text, Some(&transcript.segments))?;
+                ([(header::CONTENT_TYPE, content_type)], contents).into_response()
+            }
+            Self::VerboseJson => {
+                let segments = transcript
+                    .segments
+                    .iter()
+                    .enumerate()
+                    .map(|(id, segment)| {
+                        json!({
+                            "id": id,
+                            "start": segment.start_ms as f64 / 1000.0,
+                            "end": segment.end_ms as f64 / 1000.0,
+                            "text": segment.text,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Json(json!({
+                    "task": "transcribe",
+                    "language": language,
+                    "duration": duration_ms as f64 / 1000.0,
+                    "text": text,
+                    "segments": segments,
+                }))
+                .into_response()
+            }
+        };
+
+        Ok(response)
+    }
+}
+
+/// Transcribes an uploaded file, like OpenAI's `/v1/audio/transcriptions`.
+async fn transcriptions(
+    State(server): State<Arc<Server>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    server.authorize(&headers, None)?;
+
+    let mut file = None;
+    let mut format = ResponseFormat::Json;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(ApiError::from_multipart)?
+    {
+        let name = field.name().map(|name| name.to_string());
+        match name.as_deref() {
+            Some("file") => {
+                file = Some(field.bytes().await.map_err(ApiError::from_multipart)?);
+            }
+            Some("response_format") => {
+                let value = field.text().await.map_err(ApiError::from_multipart)?;
+                format = ResponseFormat::parse(value.trim())?;
+            }
+            Some("language") => {
+                let value = field.text().await.map_err(ApiError::from_multipart)?;
+                // Whisper is only ever run in the language from the user's active profile
+                let language = server.state.settings().profile().language.clone();
+                if !value.trim().is_empty() && value.trim() != language {
+                    return Err(ApiError::bad_request(
+                        "unsupported_language",
+                        &format!("Only the configured language ({language}) is supported."),
+                    ));
+                }
+            }
+            // Everything else (e.g. `model`) is ignored
+            _ => {}
+        }
+    }
+
+    let file = file.ok_or_else(|| {
+        ApiError::bad_request(
+            "missing_file",
+            "No audio file was uploaded in the `file` field.",
+        )
+    })?;
+    let audio = sotto_core::transcribe::decode_wav(&file).map_err(|_| {
+        ApiError::bad_request(
+            "invalid_file",
+            "The audio file couldn't be read. Only WAV files are supported.",
+        )
+    })?;
+    let duration_ms = audio.len() as u64 * 1000 / SAMPLE_RATE as u64;
+    let transcript = server.transcribe(audio).await?;
+
+    Ok(format.render(
+        &transcript,
+        duration_ms,
+        &server.state.settings().profile().language,
+    )?)
+}
+
+/// The query parameters the streaming endpoint takes.
+#[derive(Deserialize)]
+struct StreamQuery {
+    api_key: Option<String>,
+}
+
+/// Upgrades a request to a WebSocket for streaming transcription.
+async fn stream(
+    State(server): State<Arc<Server>>,
+    headers: HeaderMap,
+    Query(query): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    server.authorize(&headers, query.api_key.as_deref())?;
+    Ok(ws.on_upgrade(move |socket| stream_transcription(server, socket)))
+}
+
+/// A message sent to a streaming client.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage<'a> {
+    /// A segment that won't change anymore.
+    Segment {
+        text: &'a str,
+        start_ms: u64,
+        end_ms: u64,
+    },
+    /// The whole transcript, once the client's finished.
+    Done {
+        text: &'a str,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Transcribes audio from a streaming client as it comes in.
+///
+/// Every few seconds, we transcribe whatever audio hasn't been finalized yet. All but the last
+/// segment Whisper gives us are sent to the client, but the last one might have been cut off
+/// mid-word, so its audio is kept and transcribed again with the next chunk.
+async fn stream_transcription(server: Arc<Server>, mut socket: WebSocket) {
+    let mut pending = Zeroizing::new(Vec::new());
+    // Where the pending audio starts in the stream
+    let mut offset_ms = 0;
+    let mut text = String::new();
+    loop {
+        let finished = match socket.recv().await {
+            Some(Ok(Message::Binary(data))) => {
+                for sample in data.chunks_exact(2) {
+                    let sample = i16::from_le_bytes([sample[0], sample[1]]);
+                    pending.push(sample as f32 / i16::MAX as f32);
+                }
+                false
+            }
+            Some(Ok(Message::Text(message))) if message.trim() == "end" => true,
+            // If the client's gone, there's nobody to send the rest to
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+            Some(Ok(_)) => continue,
+        };
+        if !finished && pending.len() < STREAM_CHUNK_SAMPLES {
+            continue;
+        }
+
+        let mut audio = pending.clone();
+        // Whisper won't transcribe less than a second of audio
+        audio.resize(audio.len().max(SAMPLE_RATE as usize), 0.0);
+        let transcript = match server.transcribe(audio).await {
+            Ok(transcript) => transcript,
+            Err(err) => {
+                let _ = send(
+                    &mut socket,
+                    &StreamMessage::Error {
+                        message: err.to_string(),
+                    },
+                )
+                .await;
+                return;
+            }
+        };
+
+        let segments = &transcript.segments;
+        let final_segments = if finished || pending.len() >= MAX_PENDING_SAMPLES {
+            segments.len()
+        } else {
+            segments.len().saturating_sub(1)
+        };
+        for segment in &segments[..final_segments] {
+            text.push_str(&segment.text);
+            let message = StreamMessage::Segment {
+                text: segment.text.trim(),
+                start_ms: offset_ms + segment.start_ms,
+                end_ms: offset_ms + segment.end_ms,
+            };
+            if send(&mut socket, &message).await.is_err() {
+                return;
+            }
+        }
+        if finished {
+            let _ = send(&mut socket, &StreamMessage::Done { text: text.trim() }).await;
+            let _ = socket.close().await;
+            return;
+        }
+
+        // Keep the audio from the start of the segment we held back (if we didn't hold one back,
+        // everything's been sent)
+        let kept_ms = match segments.get(final_segments) {
+            Some(segment) => segment.start_ms,
+            None => pending.len() as u64 * 1000 / SAMPLE_RATE as u64,
+        };
+        let kept_from = ((kept_ms * SAMPLE_RATE as u64 / 1000) as usize).min(pending.len());
+        pending = Zeroizing::new(pending[kept_from..].to_vec());
+        offset_ms += kept_ms;
+    }
+}
+
+/// Sends the given message to a streaming client.
+async fn send(socket: &mut WebSocket, message: &StreamMessage<'_>) -> Result<(), axum::Error> {
+    // This can't fail, it's plain data
+    let message = serde_json::to_string(message).unwrap();
+    socket.send(Message::Text(message)).await
+}
+
+/// Compares an API key the client gave us with the one we expect, taking the same time however
+/// much of it is right.
+fn keys_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// An error response, in the shape OpenAI's API uses so existing clients can show it.
+struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            code,
+            message: message.to_string(),
+        }
+    }
+    fn bad_request(code: &'static str, message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+    fn from_multipart(err: axum::extract::multipart::MultipartError) -> Self {
+        Self::new(err.status(), "invalid_request", &err.body_text())
+    }
+}
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "transcription_failed",
+            &err.to_string(),
+        )
+    }
+}
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let kind = if self.status.is_server_error() {
+            "server_error"
+        } else {
+            "invalid_request_error"
+        };
+        let body = json!({
+            "error": {
+                "message": self.message,
+                "type": kind,
+                "param": null,
+                "code": self.code,
+            }
+        });
+
+        (self.status, Json(body)).into_response()
+    }
+}
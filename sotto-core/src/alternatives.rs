@@ -0,0 +1,176 @@
+use crate::punctuation::normalize_word;
+use crate::transcribe::{
+    run_whisper, segment_audio, Segment, Transcript, WhisperOptions, Word, SAMPLE_RATE,
+};
+use whisper_rs::{SamplingStrategy, WhisperContext};
+use zeroize::Zeroizing;
+
+/// The most segments we'll look for alternatives in for each transcript, since each one means
+/// running Whisper again.
+const MAX_SEGMENTS: usize = 3;
+/// The most alternatives we'll suggest for each word.
+const MAX_ALTERNATIVES: usize = 3;
+/// The most words an alternative can be, since anything longer probably means the two
+/// transcriptions didn't line up properly.
+const MAX_ALTERNATIVE_WORDS: usize = 3;
+/// The temperature used when sampling a different transcription.
+const SAMPLING_TEMPERATURE: f32 = 0.6;
+/// How much audio either side of a segment to include when transcribing it again, so the first
+/// and last words aren't cut off.
+const MARGIN_MS: u64 = 200;
+
+/// Looks for alternatives to the words Whisper wasn't sure about in the given transcript of the
+/// given audio, running Whisper with the same options it was transcribed with.
+///
+/// Whisper only ever tells us what it thinks is most likely, so we get alternatives by
+/// transcribing the segments with uncertain words in them again (once with beam search, and once
+/// sampling at a higher temperature), and seeing what those transcriptions have in place of each
+/// uncertain word.
+pub fn find(
+    transcript: &mut Transcript,
+    audio: &[f32],
+    ctx: &WhisperContext,
+    options: &WhisperOptions,
+) {
+    let uncertain_segments = transcript
+        .segments
+        .iter_mut()
+        .filter(|segment| segment.words.iter().any(Word::is_uncertain))
+        .take(MAX_SEGMENTS);
+    for segment in uncertain_segments {
+        let audio = padded_segment_audio(audio, segment);
+        let passes = [
+            (
+                SamplingStrategy::BeamSearch {
+                    beam_size: 5,
+                    patience: -1.0,
+                },
+                0.0,
+            ),
+            (
+                SamplingStrategy::Greedy { best_of: 1 },
+                SAMPLING_TEMPERATURE,
+            ),
+        ];
+        for (strategy, temperature) in passes {
+            // These are only suggestions, so if Whisper can't come up with any, that's fine
+            let candidates = match run_whisper(&audio, ctx, options, strategy, temperature) {
+                Ok(candidates) => candidates,
+                Err(_) => continue,
+            };
+            let other = candidates
+                .iter()
+                .map(|candidate| candidate.segment.text.as_str())
+                .collect::<String>();
+            suggest(segment, &other);
+        }
+    }
+}
+
+/// Suggests alternatives for the uncertain words in the given segment from another transcription
+/// of the same audio, by lining the two up and seeing what the other one has in place of each
+/// uncertain word.
+fn suggest(segment: &mut Segment, other: &str) {
+    let other_words = other
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\''))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let ours = segment
+        .words
+        .iter()
+        .map(|word| normalize_word(&word.text))
+        .collect::<Vec<_>>();
+    let theirs = other_words
+        .iter()
+        .map(|word| normalize_word(word))
+        .collect::<Vec<_>>();
+    let matches = align(&ours, &theirs);
+
+    for (i, word) in segment.words.iter_mut().enumerate() {
+        if !word.is_uncertain() || matches.iter().any(|(ours, _)| *ours == i) {
+            continue;
+        }
+        // Find the words on either side that both transcriptions agree on
+        let before = matches.iter().rev().find(|(ours, _)| *ours < i);
+        let after = matches.iter().find(|(ours, _)| *ours > i);
+        let our_start = before.map_or(0, |(ours, _)| ours + 1);
+        let our_end = after.map_or(ours.len(), |(ours, _)| *ours);
+        let their_start = before.map_or(0, |(_, theirs)| theirs + 1);
+        let their_end = after.map_or(theirs.len(), |(_, theirs)| *theirs);
+        // If more than just this word is different, we can't tell what goes where
+        if our_start != i || our_end != i + 1 {
+            continue;
+        }
+        if their_end <= their_start || their_end - their_start > MAX_ALTERNATIVE_WORDS {
+            continue;
+        }
+
+        let alternative = other_words[their_start..their_end].join(" ");
+        let normalized = alternative
+            .split_whitespace()
+            .map(normalize_word)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let is_new = normalized != ours[i]
+            && !word
+                .alternatives
+                .iter()
+                .any(|existing| normalize_word(existing) == normalized);
+        if is_new && word.alternatives.len() < MAX_ALTERNATIVES {
+            word.alternatives.push(alternative);
+        }
+    }
+}
+
+/// Lines up two lists of words, returning the indices of the words they have in common (as a
+/// longest common subsequence), in order.
+fn align(a: &[String], b: &[String]) -> Vec<(usize, usize)> {
+    let same = |i: usize, j: usize| !a[i].is_empty() && a[i] == b[j];
+
+    // `lengths[i][j]` is the length of the longest common subsequence of `a[i..]` and `b[j..]`
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(i, j) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(i, j) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    pairs
+}
+
+/// Gets the audio under the given segment, with a little either side, and padded with silence to
+/// at least a second (Whisper won't transcribe anything shorter). The returned buffer will be
+/// zeroed when it's dropped.
+fn padded_segment_audio(audio: &[f32], segment: &Segment) -> Zeroizing<Vec<f32>> {
+    let widened = Segment {
+        text: String::new(),
+        start_ms: segment.start_ms.saturating_sub(MARGIN_MS),
+        end_ms: segment.end_ms + MARGIN_MS,
+        words: Vec::new(),
+    };
+    let slice = segment_audio(audio, &widened);
+
+    let mut padded = Zeroizing::new(Vec::with_capacity(slice.len().max(SAMPLE_RATE as usize)));
+    padded.extend_from_slice(slice);
+    padded.resize(padded.len().max(SAMPLE_RATE as usize), 0.0);
+    padded
+}
@@ -0,0 +1,190 @@
+use crate::errors::Error;
+use crate::vault::VaultKey;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Loads the document the user last autosaved before Sotto supported several documents, if
+/// there is one. Documents are now kept in the [`crate::workspace::Workspace`].
+pub fn load_document(key: &VaultKey) -> Result<Option<String>, Error> {
+    let path = autosave_dir()?.join("document.enc");
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let text = key.read(&path)?;
+    // We only ever write valid UTF-8, so anything else is corruption
+    let text = String::from_utf8(text.to_vec()).map_err(|_| Error::DecryptFailed)?;
+    Ok(Some(text))
+}
+
+/// Deletes the document the user last autosaved before Sotto supported several documents, once
+/// it's been moved into the workspace.
+pub fn delete_document() -> Result<(), Error> {
+    let path = autosave_dir()?.join("document.enc");
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|err| Error::WriteWorkspaceFailed { source: err })?;
+    }
+
+    Ok(())
+}
+
+/// A recording that's periodically checkpointed to disk while it's in progress, so that it can be
+/// recovered if Sotto dies halfway through. Once the recording has been transcribed successfully,
+/// this should be [`Self::finish`]ed, which deletes it.
+///
+/// The file is a sequence of frames, each of which is a little-endian `u32` length followed by
+/// that many bytes of encrypted little-endian f32 samples. Encrypting each checkpoint
+/// separately means we never need to rewrite what's already there.
+pub struct RecoveryRecording {
+    file: File,
+    path: PathBuf,
+    key: VaultKey,
+}
+impl RecoveryRecording {
+    /// Starts a new recovery recording. If there's one left over from a previous session that
+    /// hasn't been recovered yet, that's set aside (see [`set_aside_recovery_recording`]) rather
+    /// than overwritten, so it can still be recovered later.
+    pub fn create(key: VaultKey) -> Result<Self, Error> {
+        set_aside_recovery_recording()?;
+        let path = autosave_dir()?.join("recording.enc");
+        let file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)
+            .map_err(|err| Error::WriteRecoveryRecordingFailed { source: err })?;
+
+        Ok(Self { file, path, key })
+    }
+    /// Appends the given samples to the recovery recording, making sure they've actually hit
+    /// the disk before returning.
+    pub fn append(&mut self, samples: &[f32]) -> Result<(), Error> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = Zeroizing::new(
+            samples
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        );
+        let frame = self.key.encrypt(&bytes)?;
+        self.file
+            .write_all(&(frame.len() as u32).to_le_bytes())
+            .and_then(|_| self.file.write_all(&frame))
+            .and_then(|_| self.file.sync_data())
+            .map_err(|err| Error::WriteRecoveryRecordingFailed { source: err })
+    }
+    /// Deletes the recovery recording, since it's no longer needed.
+    pub fn finish(self) -> Result<(), Error> {
+        drop(self.file);
+        delete_recovery_recording(&self.path)
+    }
+}
+
+/// Moves the recording that was last in progress (`recording.enc`), if there is one, aside to
+/// `recording-<timestamp>.enc`, where it waits to be recovered. Nothing writes to those, so they
+/// can be recovered without anything changing underneath us.
+///
+/// This must only be done when there's no dictation going, since otherwise the recording is still
+/// being written to. If nothing was ever written to it (e.g. because the microphone couldn't be
+/// opened), there's nothing to recover, so it's just deleted.
+pub fn set_aside_recovery_recording() -> Result<(), Error> {
+    let dir = autosave_dir()?;
+    let path = dir.join("recording.enc");
+    match std::fs::metadata(&path) {
+        Err(_) => return Ok(()),
+        Ok(metadata) if metadata.len() == 0 => return delete_recovery_recording(&path),
+        Ok(_) => {}
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_millis() as u64)
+        .unwrap_or(0);
+    std::fs::rename(&path, dir.join(format!("recording-{timestamp}.enc")))
+        .map_err(|err| Error::WriteRecoveryRecordingFailed { source: err })?;
+    tracing::info!("set aside unrecovered recording from previous session");
+
+    Ok(())
+}
+
+/// Lists the recordings from previous sessions that have been set aside to be recovered (see
+/// [`set_aside_recovery_recording`]), oldest first.
+pub fn recovery_recordings() -> Result<Vec<PathBuf>, Error> {
+    let entries = std::fs::read_dir(autosave_dir()?)
+        .map_err(|err| Error::ReadRecoveryRecordingFailed { source: err })?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| Error::ReadRecoveryRecordingFailed { source: err })?
+            .path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if !name.starts_with("recording-") || !name.ends_with(".enc") {
+            continue;
+        }
+        // A recording with nothing in it has nothing to recover
+        let empty = std::fs::metadata(&path).map_or(true, |metadata| metadata.len() == 0);
+        if empty {
+            delete_recovery_recording(&path)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    // The timestamps all have the same number of digits (until the year 2286, anyway)
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Reads back the recording at the given path, which was left over from a previous session. If
+/// Sotto died partway through writing a checkpoint, that last checkpoint will be ignored.
+pub fn read_recovery_recording(key: &VaultKey, path: &Path) -> Result<Zeroizing<Vec<f32>>, Error> {
+    let contents = Zeroizing::new(
+        std::fs::read(path).map_err(|err| Error::ReadRecoveryRecordingFailed { source: err })?,
+    );
+    // Allocate everything up front so we don't leave copies of the audio lying around in memory
+    let mut audio = Zeroizing::new(Vec::with_capacity(contents.len() / 4));
+    let mut rest = &contents[..];
+    while rest.len() >= 4 {
+        let (len, after_len) = rest.split_at(4);
+        // This can't fail, we just split off four bytes
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if after_len.len() < len {
+            break;
+        }
+        let (frame, after_frame) = after_len.split_at(len);
+        let bytes = key.decrypt(frame)?;
+        for sample in bytes.chunks_exact(4) {
+            audio.push(f32::from_le_bytes(sample.try_into().unwrap()));
+        }
+        rest = after_frame;
+    }
+
+    Ok(audio)
+}
+
+/// Deletes the recording at the given path, once it's been recovered.
+pub fn delete_recovery_recording(path: &Path) -> Result<(), Error> {
+    if path.exists() {
+        std::fs::remove_file(path)
+            .map_err(|err| Error::WriteRecoveryRecordingFailed { source: err })?;
+    }
+
+    Ok(())
+}
+
+/// Gets the directory autosaved data is stored in, creating it if necessary.
+fn autosave_dir() -> Result<PathBuf, Error> {
+    let autosave_dir = crate::paths::data_dir()?.join("autosave");
+    std::fs::create_dir_all(&autosave_dir)
+        .map_err(|err| Error::CreateAutosaveDirFailed { source: err })?;
+
+    Ok(autosave_dir)
+}
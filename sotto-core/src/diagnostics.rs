@@ -0,0 +1,181 @@
+use crate::errors::Error;
+use crate::settings::Settings;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Gets the directory logs are written to (`logs` in Sotto's cache directory, see
+/// [`crate::paths`]), creating it if necessary. The app keeps a rotating set of log files here,
+/// which end up in diagnostic reports.
+///
+/// Nothing that's logged should ever contain what the user said, only what Sotto was doing.
+pub fn log_dir() -> Result<PathBuf, Error> {
+    let log_dir = crate::paths::cache_dir()?.join("logs");
+    std::fs::create_dir_all(&log_dir).map_err(|err| Error::CreateLogDirFailed { source: err })?;
+
+    Ok(log_dir)
+}
+
+/// Creates a diagnostic report at the given path, which the user can attach to an issue. This is
+/// a zip file containing:
+///
+/// - `system.json`: the versions of Sotto and the OS it's running on, and the models that have
+///   been downloaded;
+/// - `devices.txt`: the audio devices Sotto can see;
+/// - `settings.json`: the given settings, with any custom spoken commands and glossaries left out
+///   (since they're the user's own words);
+/// - `logs/`: everything in [`log_dir`].
+///
+/// No recordings, transcripts or documents are ever included. The given version is that of
+/// whatever's using this (e.g. the app), which might differ from `sotto-core`'s.
+pub fn create_report(path: &Path, version: &str, settings: &Settings) -> Result<(), Error> {
+    let file = File::create(path).map_err(|err| Error::CreateReportFailed { source: err })?;
+    let mut zip = ZipWriter::new(file);
+
+    // These can't fail, they're plain data
+    let system = serde_json::to_string_pretty(&SystemInfo::collect(version)?).unwrap();
+    add_file(&mut zip, "system.json", system.as_bytes())?;
+    add_file(&mut zip, "devices.txt", list_devices().as_bytes())?;
+    let mut settings = serde_json::to_value(settings).unwrap();
+    if let Some(commands) = settings.get_mut("custom_commands") {
+        let count = commands.as_array().map_or(0, |commands| commands.len());
+        *commands = format!("<{count} custom commands left out>").into();
+    }
+    if let Some(profiles) = settings.get_mut("profiles").and_then(|p| p.as_array_mut()) {
+        for glossary in profiles.iter_mut().filter_map(|p| p.get_mut("glossary")) {
+            let count = glossary.as_array().map_or(0, |terms| terms.len());
+            *glossary = format!("<{count} terms left out>").into();
+        }
+    }
+    let settings = serde_json::to_string_pretty(&settings).unwrap();
+    add_file(&mut zip, "settings.json", settings.as_bytes())?;
+
+    let log_dir = log_dir()?;
+    let entries =
+        std::fs::read_dir(&log_dir).map_err(|err| Error::ReadLogsFailed { source: err })?;
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::ReadLogsFailed { source: err })?;
+        if !entry.path().is_file() {
+            continue;
+        }
+        let contents =
+            std::fs::read(entry.path()).map_err(|err| Error::ReadLogsFailed { source: err })?;
+        let name = format!("logs/{}", entry.file_name().to_string_lossy());
+        add_file(&mut zip, &name, &contents)?;
+    }
+
+    zip.finish()
+        .map_err(|err| Error::WriteReportFailed { source: err })?;
+    tracing::info!(path = %path.display(), "created diagnostic report");
+
+    Ok(())
+}
+
+/// Adds a file with the given contents to a report.
+fn add_file(zip: &mut ZipWriter<File>, name: &str, contents: &[u8]) -> Result<(), Error> {
+    zip.start_file(name, FileOptions::default())
+        .map_err(|err| Error::WriteReportFailed { source: err })?;
+    zip.write_all(contents)
+        .map_err(|err| Error::WriteReportFailed { source: err.into() })
+}
+
+/// The details of the system Sotto's running on that go into a diagnostic report.
+#[derive(Serialize)]
+struct SystemInfo {
+    /// The version of whatever created the report.
+    version: String,
+    /// The version of `sotto-core`.
+    core_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    /// When the report was created, in seconds since the Unix epoch.
+    created: u64,
+    /// The models that have been downloaded, with their sizes in bytes (so broken downloads
+    /// stand out).
+    models: Vec<(String, u64)>,
+}
+impl SystemInfo {
+    fn collect(version: &str) -> Result<Self, Error> {
+        // If we can't read this, there's nothing to list
+        let models = match std::fs::read_dir(crate::paths::models_dir()?) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "bin"))
+                .map(|entry| {
+                    let size = entry.metadata().map_or(0, |metadata| metadata.len());
+                    (entry.file_name().to_string_lossy().to_string(), size)
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            version: version.to_string(),
+            core_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            models,
+        })
+    }
+}
+
+/// Lists the audio devices Sotto can see, along with their default configurations. Anything that
+/// can't be found out is noted rather than failing the report, since that's useful to know too.
+#[cfg(feature = "record")]
+fn list_devices() -> String {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let host = cpal::default_host();
+    let name_of = |device: Option<cpal::Device>| match device {
+        Some(device) => device
+            .name()
+            .unwrap_or_else(|err| format!("<unknown: {err}>")),
+        None => "<none>".to_string(),
+    };
+    let mut report = format!(
+        "Host: {}\nDefault input: {}\nDefault output: {}\n",
+        host.id().name(),
+        name_of(host.default_input_device()),
+        name_of(host.default_output_device()),
+    );
+
+    report.push_str("\nInputs:\n");
+    match host.input_devices() {
+        Ok(devices) => {
+            for device in devices {
+                let config = device.default_input_config().map_or_else(
+                    |err| format!("<unknown: {err}>"),
+                    |config| format!("{config:?}"),
+                );
+                report.push_str(&format!("- {}: {config}\n", name_of(Some(device))));
+            }
+        }
+        Err(err) => report.push_str(&format!("<failed to list: {err}>\n")),
+    }
+    report.push_str("\nOutputs:\n");
+    match host.output_devices() {
+        Ok(devices) => {
+            for device in devices {
+                let config = device.default_output_config().map_or_else(
+                    |err| format!("<unknown: {err}>"),
+                    |config| format!("{config:?}"),
+                );
+                report.push_str(&format!("- {}: {config}\n", name_of(Some(device))));
+            }
+        }
+        Err(err) => report.push_str(&format!("<failed to list: {err}>\n")),
+    }
+
+    report
+}
+#[cfg(not(feature = "record"))]
+fn list_devices() -> String {
+    "Audio devices can't be listed without the `record` feature.\n".to_string()
+}
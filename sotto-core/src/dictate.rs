@@ -0,0 +1,681 @@
+use crate::autosave::RecoveryRecording;
+use crate::document::{DocumentUpdate, UncertainWord};
+use crate::errors::Error;
+#[cfg(feature = "export")]
+use crate::export::{ExportFormat, ExportSource};
+use crate::history::{HistoryEntry, HistoryEntryExt};
+use crate::model::{LoadedModels, Model};
+use crate::settings::{Settings, SettingsExt, SETTINGS_VERSION};
+use crate::transcribe::Transcript;
+use crate::vault::{ScratchFile, Vault, VaultKey, VaultStatus};
+use crate::workspace::{Workspace, WorkspaceSummary};
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::{oneshot, watch};
+use zeroize::Zeroizing;
+
+pub use sotto_types::DictationStatus;
+
+/// The app's state on the backend.
+pub struct AppState {
+    /// A sender that can be used to terminate the dictation process if it's
+    /// ongoing. Note that this might still be `Some(_)` if recording has been
+    /// completed, but if transcription is still ongoing.
+    dictation: Arc<Mutex<DictationState>>,
+    /// A publicly observable version of the dictation state, which can be used to keep indicators
+    /// (e.g. the tray icon) up-to-date.
+    status: Arc<watch::Sender<DictationStatus>>,
+    /// Whether or not the current dictation has been cancelled, in which case whatever was
+    /// recorded will be thrown away.
+    cancelled: Arc<AtomicBool>,
+    /// The Whisper contexts for every model that's been used, cached for all uses. A new state
+    /// will be created for each use.
+    ///
+    /// These are kept for as long as one of the user's profiles uses them.
+    models: Arc<LoadedModels>,
+    /// A model to use for everything, whatever the user's profiles say, if one was asked for.
+    fixed_model: Option<Model>,
+    /// The user's settings. These are read at the start of each dictation, so changes will
+    /// take effect from the next one.
+    settings: Arc<Mutex<Settings>>,
+    /// Why the user's settings couldn't be loaded, if they couldn't be (in which case we're using
+    /// the defaults), or why we couldn't get the active profile's model ready at startup, until
+    /// the user's been told.
+    settings_error: Mutex<Option<Error>>,
+    /// The vault used to encrypt anything we persist. This starts locked.
+    vault: Mutex<Vault>,
+    /// The user's documents, the active one of which dictations are applied to.
+    workspace: Arc<Mutex<Workspace>>,
+    /// Held while recordings from previous sessions are being recovered, so two recoveries at
+    /// once don't both recover the same recordings.
+    recovering: tokio::sync::Mutex<()>,
+}
+impl AppState {
+    /// Creates a new [`AppState`] that transcribes with the model from whichever of the user's
+    /// profiles is active. That model will be loaded (and downloaded, if necessary and possible)
+    /// immediately, and the models for other profiles when they're first used.
+    pub async fn from_settings() -> Result<Self, Error> {
+        Self::with_model(None, false).await
+    }
+    /// Creates a new [`AppState`] as with [`Self::from_settings`], but never fails. If the
+    /// settings can't be read at all, the defaults are used, and if the model can't be got ready
+    /// (e.g. because we're offline and it needs downloading), we start without it, and it's tried
+    /// again when the user next saves their settings or picks a profile. Either way, the error
+    /// can be shown to the user through [`Self::take_settings_error`], rather than crashing.
+    pub async fn from_settings_or_degraded() -> Self {
+        Self::with_model(None, true)
+            .await
+            .expect("degraded startup should never fail")
+    }
+    /// Creates a new [`AppState`] using the given model for all transcriptions, whatever the
+    /// user's profiles say. This model will be loaded and cached immediately.
+    pub async fn new(model: Model) -> Result<Self, Error> {
+        Self::with_model(Some(model), false).await
+    }
+    /// Creates a new [`AppState`], preloading either the given model or the active profile's. If
+    /// `degrade` is set, failures are kept as the settings error rather than returned.
+    async fn with_model(fixed_model: Option<Model>, degrade: bool) -> Result<Self, Error> {
+        let (settings, mut settings_error) = match Settings::load_or_default() {
+            Ok(loaded) => loaded,
+            Err(err) if degrade => {
+                tracing::error!(?err, "failed to read settings, using the defaults instead");
+                (Settings::default(), Some(err))
+            }
+            Err(err) => return Err(err),
+        };
+        let models = Arc::new(LoadedModels::default());
+        if let Err(err) = models
+            .prepare(fixed_model.unwrap_or(settings.profile().model))
+            .await
+        {
+            if !degrade {
+                return Err(err);
+            }
+            tracing::error!(?err, "failed to prepare model, starting without it");
+            // If the settings failed too, that's the more fundamental problem
+            settings_error.get_or_insert(err);
+        }
+        tracing::info!(profile = %settings.active_profile, "sotto core initialized");
+
+        Ok(Self {
+            dictation: Arc::new(Mutex::new(DictationState::None)),
+            status: Arc::new(watch::channel(DictationStatus::Idle).0),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            models,
+            fixed_model,
+            settings: Arc::new(Mutex::new(settings)),
+            settings_error: Mutex::new(settings_error),
+            vault: Mutex::new(Vault::default()),
+            workspace: Arc::new(Mutex::new(Workspace::default())),
+            recovering: tokio::sync::Mutex::new(()),
+        })
+    }
+    /// Executes a dictation. This is not in itself asynchronous, but will
+    /// return a future that will resolve when transcription is complete, and the
+    /// result has been applied to the working document. Once
+    /// this function returns that future (*before* its resolution), the app
+    /// state will have been modified to be prepared for a signal to end
+    /// a recording.
+    pub fn dictate(&self) -> Result<impl Future<Output = Result<DocumentUpdate, Error>>, Error> {
+        // If something panicked while holding this, the state it left is still worth looking at
+        let mut dictation_sender = self
+            .dictation
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let DictationState::None = &*dictation_sender {
+            // If we're keeping the recording or checkpointing it for recovery, we'll need to
+            // encrypt it, so fail now if we can't (rather than making the user record everything
+            // only to have it thrown away)
+            let settings = self.settings.lock().unwrap().clone();
+            let vault_key = if settings.retain_recordings || settings.autosave {
+                Some(self.vault_key()?)
+            } else {
+                None
+            };
+            let retain_key = vault_key.clone().filter(|_| settings.retain_recordings);
+            let save_key = vault_key.clone().filter(|_| settings.autosave);
+            let mut recovery = match vault_key.filter(|_| settings.autosave) {
+                Some(key) => Some(RecoveryRecording::create(key)?),
+                None => None,
+            };
+
+            // We only touch the disk if we have to keep the recording anyway (and even then, the
+            // file will be overwritten before it's deleted)
+            let scratch_file = if settings.retain_recordings {
+                Some(ScratchFile::new()?)
+            } else {
+                None
+            };
+
+            // Create a channel for terminating the recording and starting
+            // transcription
+            let (tx, rx) = oneshot::channel::<()>();
+            *dictation_sender = DictationState::Recording(tx);
+            drop(dictation_sender);
+            self.cancelled.store(false, Ordering::SeqCst);
+            self.status.send_replace(DictationStatus::Recording);
+            tracing::info!(
+                retain = settings.retain_recordings,
+                autosave = settings.autosave,
+                "started dictation"
+            );
+
+            // And a thread to perform both in sequence
+            let dictation_sender = self.dictation.clone();
+            let status = self.status.clone();
+            let cancelled = self.cancelled.clone();
+            // Whatever the user switches to while this is going, it'll finish with the profile
+            // it started with
+            let models = self.models.clone();
+            let model = self.model_for(&settings);
+            let insertion_mode = settings.insertion_mode;
+            let language = settings.profile().language.clone();
+            let task = tokio::task::spawn_blocking(move || {
+                // However this ends (even if it's an error or a panic), we'll be ready for the
+                // next dictation afterward
+                let _reset = ResetOnDrop {
+                    dictation: dictation_sender,
+                    status,
+                };
+                // This will complete when the receiver gets a signal
+                // NOTE: We aren't responsible for the state change from `Recording` -> `Transcribing`,
+                // that has to be done by whoever sends the signal to end the recording, in order
+                // to actually access the underlying sender (Rust's ownership system enforces this!).
+                let audio = match scratch_file {
+                    Some(scratch_file) => {
+                        crate::record::start_recording(
+                            scratch_file.path(),
+                            settings.input_device.as_deref(),
+                            rx,
+                            recovery.as_mut(),
+                        )?;
+                        // Once we have the audio in memory, we don't need it lying around on disk
+                        crate::transcribe::read_audio(scratch_file.path())?
+                    }
+                    None => crate::record::record_to_memory(
+                        settings.input_device.as_deref(),
+                        rx,
+                        recovery.as_mut(),
+                    )?,
+                };
+                if cancelled.load(Ordering::SeqCst) {
+                    return Err(abandon(recovery));
+                }
+                // Never log what was said, only how much of it there was
+                tracing::info!(
+                    seconds = audio.len() as f32 / crate::transcribe::SAMPLE_RATE as f32,
+                    "finished recording, transcribing"
+                );
+                let started = std::time::Instant::now();
+                // If the profile's model hasn't been used yet, this is where it'll be loaded
+                let whisper_ctx = models.get(model)?;
+                // The audio will be zeroed when it's dropped at the end of this task
+                let mut transcript = crate::transcribe::transcribe(
+                    &audio,
+                    &whisper_ctx,
+                    &settings.whisper_options(),
+                    settings.suggest_alternatives,
+                )?;
+                settings.tidy(&mut transcript);
+                tracing::info!(
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    segments = transcript.segments.len(),
+                    flagged = transcript.flagged.len(),
+                    "finished transcription"
+                );
+                // Whisper can't be interrupted, so we might only find out now that the user
+                // didn't want this after all
+                if cancelled.load(Ordering::SeqCst) {
+                    return Err(abandon(recovery));
+                }
+                let words = transcript
+                    .segments
+                    .iter()
+                    .flat_map(|segment| segment.words.clone())
+                    .collect::<Vec<_>>();
+                let result = (transcript.text(), transcript.flagged.clone(), words);
+                if let Some(retain_key) = retain_key {
+                    HistoryEntry::create(transcript, &audio, &retain_key)?;
+                }
+                // Only now that we've got the transcript can we get rid of the recovery recording
+                if let Some(recovery) = recovery {
+                    recovery.finish()?;
+                }
+
+                Ok::<_, Error>(result)
+            });
+
+            // Morph this into a future that makes the errors neater from joining to
+            // a blocking task, and that applies the result to the document (which might have
+            // changed while we were transcribing)
+            let workspace = self.workspace.clone();
+            let task_fut = async move {
+                let (text, flagged, words) = task
+                    .await
+                    .map_err(|err| Error::DictationTaskPanicked { source: err })??;
+                let mut workspace = workspace.lock().unwrap();
+                let mut update =
+                    workspace
+                        .active()
+                        .apply_dictation(&text, &language, insertion_mode, &words);
+                update.flagged = flagged;
+                if let Some(save_key) = save_key {
+                    workspace.save_active(&save_key)?;
+                }
+
+                Ok(update)
+            };
+
+            Ok(task_fut)
+        } else {
+            Err(Error::AlreadyDictating)
+        }
+    }
+    /// Sends a signal to the dictation thread to end recording and begin transcription.
+    /// It is assumed that the caller of the original dictation will still be holding the
+    /// future that will yield the actual transcription result.
+    pub async fn end_recording(&self) -> Result<(), Error> {
+        let mut dictation_sender = self
+            .dictation
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let DictationState::Recording(_) = &*dictation_sender {
+            // We need the actual sender itself, which will be consumed by this call
+            let state = std::mem::replace(&mut *dictation_sender, DictationState::Transcribing);
+            if let DictationState::Recording(sender) = state {
+                // If the receiver has been dropped, the error will be received by the holder
+                // of the dictation thread future (so we'll ignore it here)
+                let _ = sender.send(());
+            } else {
+                unreachable!();
+            }
+            self.status.send_replace(DictationStatus::Transcribing);
+
+            Ok(())
+        } else {
+            Err(Error::NotDictating)
+        }
+    }
+    /// Cancels the current dictation, throwing away whatever's been recorded. If we're still
+    /// recording, this stops the recording, otherwise the transcription will finish but be
+    /// discarded. Either way, the future returned by [`Self::dictate`] will resolve to
+    /// [`Error::DictationCancelled`].
+    pub fn cancel(&self) -> Result<(), Error> {
+        let mut dictation_sender = self
+            .dictation
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match std::mem::replace(&mut *dictation_sender, DictationState::Transcribing) {
+            DictationState::Recording(sender) => {
+                tracing::info!("cancelled dictation while recording");
+                self.cancelled.store(true, Ordering::SeqCst);
+                // As in `end_recording`, any error here will go to the dictation future
+                let _ = sender.send(());
+                self.status.send_replace(DictationStatus::Transcribing);
+
+                Ok(())
+            }
+            DictationState::Transcribing => {
+                tracing::info!("cancelled dictation while transcribing");
+                self.cancelled.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+            DictationState::None => {
+                *dictation_sender = DictationState::None;
+                Err(Error::NotDictating)
+            }
+        }
+    }
+    /// Gets the model dictations with the given settings should be transcribed with.
+    fn model_for(&self, settings: &Settings) -> Model {
+        self.fixed_model.unwrap_or(settings.profile().model)
+    }
+    /// Gets the current dictation status.
+    pub fn status(&self) -> DictationStatus {
+        *self.status.borrow()
+    }
+    /// Transcribes the given audio (mono f32 at 16kHz) outside of any dictation, tidying up the
+    /// transcript according to the user's settings if asked to. This doesn't touch the workspace
+    /// or the history, and can be called from several threads at once (each gets its own Whisper
+    /// state). It blocks until transcription is done.
+    pub fn transcribe(&self, audio: &[f32], tidy: bool) -> Result<Transcript, Error> {
+        let settings = self.settings();
+        let mut transcript = crate::transcribe::transcribe(
+            audio,
+            &self.models.get(self.model_for(&settings))?,
+            &settings.whisper_options(),
+            false,
+        )?;
+        if tidy {
+            settings.tidy(&mut transcript);
+        }
+
+        Ok(transcript)
+    }
+    /// Subscribes to changes in the dictation status.
+    pub fn subscribe(&self) -> watch::Receiver<DictationStatus> {
+        self.status.subscribe()
+    }
+    /// Gets an overview of the user's documents.
+    pub fn workspace(&self) -> WorkspaceSummary {
+        self.workspace.lock().unwrap().summary()
+    }
+    /// Loads the user's saved documents, if they've enabled autosaving. Anything they've written
+    /// since Sotto was opened is kept.
+    pub fn restore_workspace(&self) -> Result<WorkspaceSummary, Error> {
+        if self.settings.lock().unwrap().autosave {
+            if let Some(mut saved) = Workspace::load(&self.vault_key()?)? {
+                let mut workspace = self.workspace.lock().unwrap();
+                saved.absorb(std::mem::take(&mut *workspace));
+                *workspace = saved;
+            }
+        }
+        self.save_workspace(false)?;
+
+        Ok(self.workspace())
+    }
+    /// Updates the given document with the user's manual edits, and their selection in it
+    /// (as byte offsets), if that's known. If autosaving is enabled, the document will be saved.
+    ///
+    /// Only the active document can be edited, so edits to any other document (which can happen
+    /// if the user switches documents while an edit is on its way) are ignored, and `None` is
+    /// returned. Otherwise, this returns the words in the document the user should still check,
+    /// which will have moved (or gone) with the edits.
+    pub fn set_document(
+        &self,
+        id: u64,
+        text: String,
+        selection: Option<(usize, usize)>,
+    ) -> Result<Option<Vec<UncertainWord>>, Error> {
+        let uncertain = {
+            let mut workspace = self.workspace.lock().unwrap();
+            if workspace.active_id() != id {
+                return Ok(None);
+            }
+            let document = workspace.active();
+            document.set_text(text, selection);
+            document.uncertain().to_vec()
+        };
+        self.save_workspace(true)?;
+
+        Ok(Some(uncertain))
+    }
+    /// Marks the uncertain word starting at the given byte offset in the given document as
+    /// checked, replacing it with the given correction if there is one. As with
+    /// [`Self::set_document`], only the active document can be changed.
+    pub fn resolve_word(
+        &self,
+        id: u64,
+        start: usize,
+        correction: Option<String>,
+    ) -> Result<DocumentUpdate, Error> {
+        let update = {
+            let mut workspace = self.workspace.lock().unwrap();
+            if workspace.active_id() != id {
+                return Err(Error::DocumentNotFound);
+            }
+            workspace
+                .active()
+                .resolve_uncertain(start, correction.as_deref())
+        };
+        self.save_workspace(true)?;
+
+        Ok(update)
+    }
+    /// Creates a new document with the given name, and makes it active.
+    pub fn create_document(&self, name: &str) -> Result<WorkspaceSummary, Error> {
+        self.workspace.lock().unwrap().create(name);
+        self.save_workspace(false)?;
+        Ok(self.workspace())
+    }
+    /// Renames the given document.
+    pub fn rename_document(&self, id: u64, name: &str) -> Result<WorkspaceSummary, Error> {
+        self.workspace.lock().unwrap().rename(id, name)?;
+        self.save_workspace(false)?;
+        Ok(self.workspace())
+    }
+    /// Makes the given document active, so dictations will go into it.
+    pub fn switch_document(&self, id: u64) -> Result<WorkspaceSummary, Error> {
+        self.workspace.lock().unwrap().switch(id)?;
+        self.save_workspace(false)?;
+        Ok(self.workspace())
+    }
+    /// Deletes the given document.
+    pub fn delete_document(&self, id: u64) -> Result<WorkspaceSummary, Error> {
+        self.workspace.lock().unwrap().delete(id)?;
+        self.save_workspace(false)?;
+        Ok(self.workspace())
+    }
+    /// Saves the workspace (or just the active document), if autosaving is enabled and the vault
+    /// is unlocked. If the vault is locked, documents will only be kept in memory until it's
+    /// unlocked again.
+    fn save_workspace(&self, active_only: bool) -> Result<(), Error> {
+        if !self.settings.lock().unwrap().autosave {
+            return Ok(());
+        }
+        let key = match self.vault_key() {
+            Ok(key) => key,
+            Err(Error::VaultLocked) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let workspace = self.workspace.lock().unwrap();
+        if active_only {
+            workspace.save_active(&key)
+        } else {
+            workspace.save(&key)
+        }
+    }
+    /// Transcribes any recordings left over from previous sessions that never finished (e.g.
+    /// because Sotto crashed), giving back their text as one paragraph each. Once that's done, the
+    /// leftover recordings will be deleted.
+    pub async fn recover_recording(&self) -> Result<Option<String>, Error> {
+        let _recovering = self.recovering.lock().await;
+        {
+            // If the user's dictating right now, the latest recording is still being written (and
+            // anything from before it was set aside when it started). No dictation can start while
+            // we're holding this, since that's when the latest recording is created.
+            let dictation = self
+                .dictation
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let DictationState::None = *dictation {
+                crate::autosave::set_aside_recovery_recording()?;
+            }
+        }
+        let paths = crate::autosave::recovery_recordings()?;
+        if paths.is_empty() {
+            return Ok(None);
+        }
+
+        let vault_key = self.vault_key()?;
+        let settings = self.settings();
+        let models = self.models.clone();
+        let model = self.model_for(&settings);
+        tokio::task::spawn_blocking(move || {
+            let mut texts = Vec::new();
+            for path in &paths {
+                let audio = crate::autosave::read_recovery_recording(&vault_key, path)?;
+                tracing::info!("recovering recording from previous session");
+                // Nobody's around to check these words, so there's no point looking for
+                // alternatives
+                let mut transcript = crate::transcribe::transcribe(
+                    &audio,
+                    &models.get(model)?,
+                    &settings.whisper_options(),
+                    false,
+                )?;
+                settings.tidy(&mut transcript);
+                texts.push(transcript.text().trim().to_string());
+            }
+            // Only once they've all been transcribed, so a failure doesn't lose the ones before it
+            for path in &paths {
+                crate::autosave::delete_recovery_recording(path)?;
+            }
+
+            Ok(Some(texts.join("\n\n")))
+        })
+        .await
+        .map_err(|err| Error::DictationTaskPanicked { source: err })?
+    }
+    /// Lists the entries in the user's history, newest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, Error> {
+        HistoryEntry::list(&self.vault_key()?)
+    }
+    /// Exports the given source to the given path in the given format. History entries are
+    /// exported exactly as they were transcribed, with their timestamps.
+    #[cfg(feature = "export")]
+    pub fn export(
+        &self,
+        source: ExportSource,
+        format: ExportFormat,
+        path: &Path,
+    ) -> Result<(), Error> {
+        match source {
+            ExportSource::Document(text) => crate::export::export(path, format, &text, None),
+            ExportSource::History(id) => {
+                let entry = self
+                    .history()?
+                    .into_iter()
+                    .find(|entry| entry.id == id)
+                    .ok_or(Error::HistoryEntryNotFound)?;
+                crate::export::export(
+                    path,
+                    format,
+                    &entry.transcript.text(),
+                    Some(&entry.transcript.segments),
+                )
+            }
+        }
+    }
+    /// Gets the status of the vault.
+    pub fn vault_status(&self) -> Result<VaultStatus, Error> {
+        Ok(VaultStatus {
+            set_up: Vault::is_set_up()?,
+            unlocked: self.vault.lock().unwrap().is_unlocked(),
+        })
+    }
+    /// Unlocks the vault with the given passphrase, setting it up if this is the first time.
+    ///
+    /// Deriving the key from the passphrase is deliberately slow, so that's done on a blocking
+    /// thread, and without holding on to the vault, so anything that needs the key in the meantime
+    /// (like a dictation started with the global shortcut) finds it locked rather than waiting.
+    pub async fn unlock_vault(&self, passphrase: String) -> Result<(), Error> {
+        let passphrase = Zeroizing::new(passphrase);
+        let key = tokio::task::spawn_blocking(move || Vault::open(&passphrase))
+            .await
+            .map_err(|err| Error::DictationTaskPanicked { source: err })??;
+        self.vault.lock().unwrap().unlock(key);
+
+        Ok(())
+    }
+    /// Locks the vault.
+    pub fn lock_vault(&self) {
+        self.vault.lock().unwrap().lock()
+    }
+    /// Gets the vault key, which can be used to read and write encrypted data. This will fail
+    /// if the vault is locked.
+    pub fn vault_key(&self) -> Result<VaultKey, Error> {
+        self.vault.lock().unwrap().key()
+    }
+    /// Gets the user's current settings.
+    pub fn settings(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+    /// Takes the error the user's settings couldn't be loaded with, if there was one (in which
+    /// case the defaults are being used), or the one the model failed to load with at startup.
+    /// This only gives the error back once, so the user isn't told about it over and over.
+    pub fn take_settings_error(&self) -> Option<Error> {
+        self.settings_error.lock().unwrap().take()
+    }
+    /// Updates the user's settings, saving them to disk.
+    ///
+    /// If this turns on autosaving while the vault is unlocked, any saved documents will be
+    /// restored straight away, so they aren't overwritten by the next edit. If the active profile
+    /// uses a model that hasn't been loaded, it'll be loaded by the next dictation, unless
+    /// [`Self::prepare_model`] is called first.
+    pub fn update_settings(&self, mut settings: Settings) -> Result<(), Error> {
+        settings.validate()?;
+        settings.version = SETTINGS_VERSION;
+        // If the active profile's been renamed or deleted, fall back to the first one
+        if settings.find_profile(&settings.active_profile).is_none() {
+            settings.active_profile = settings.profiles[0].name.clone();
+        }
+        let enabling_autosave = settings.autosave && !self.settings.lock().unwrap().autosave;
+        settings.save()?;
+        tracing::debug!("updated settings");
+        // Models no profile uses any more are just taking up memory
+        self.models.retain(|model| match self.fixed_model {
+            Some(fixed_model) => model == fixed_model,
+            None => settings
+                .profiles
+                .iter()
+                .any(|profile| profile.model == model),
+        });
+        *self.settings.lock().unwrap() = settings;
+
+        if enabling_autosave && self.vault.lock().unwrap().is_unlocked() {
+            self.restore_workspace()?;
+        }
+
+        Ok(())
+    }
+    /// Makes the profile with the given name the active one, so the next dictation will use it,
+    /// and gets its model ready (downloading it if necessary), so that dictation doesn't have to
+    /// wait for it. The choice is saved, so it'll still be active next time Sotto starts.
+    pub async fn select_profile(&self, name: &str) -> Result<(), Error> {
+        {
+            let mut settings = self.settings.lock().unwrap();
+            if settings.find_profile(name).is_none() {
+                return Err(Error::ProfileNotFound);
+            }
+            settings.active_profile = name.to_string();
+            settings.save()?;
+        }
+        tracing::info!(profile = name, "switched profile");
+
+        self.prepare_model().await
+    }
+    /// Gets the active profile's model ready to transcribe with, downloading it if necessary.
+    /// This can take a while, and dictations can carry on in the meantime.
+    pub async fn prepare_model(&self) -> Result<(), Error> {
+        let model = self.model_for(&self.settings());
+        self.models.prepare(model).await
+    }
+}
+
+/// Cleans up after a cancelled dictation, returning the error the dictation should fail with.
+fn abandon(recovery: Option<RecoveryRecording>) -> Error {
+    // The user doesn't want this recording, so there's nothing to recover
+    if let Some(recovery) = recovery {
+        if let Err(err) = recovery.finish() {
+            return err;
+        }
+    }
+
+    Error::DictationCancelled
+}
+
+/// Gets the dictation state ready for the next dictation when it's dropped, so a dictation that
+/// fails partway through (or panics) doesn't stop the user from starting another one.
+struct ResetOnDrop {
+    dictation: Arc<Mutex<DictationState>>,
+    status: Arc<watch::Sender<DictationStatus>>,
+}
+impl Drop for ResetOnDrop {
+    fn drop(&mut self) {
+        *self
+            .dictation
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = DictationState::None;
+        self.status.send_replace(DictationStatus::Idle);
+    }
+}
+
+enum DictationState {
+    Recording(oneshot::Sender<()>),
+    Transcribing,
+    None,
+}
@@ -0,0 +1,799 @@
+use crate::punctuation::{capitalize, normalize_word};
+use crate::settings::InsertionMode;
+use crate::transcribe::Word;
+
+pub use sotto_types::{DocumentUpdate, UncertainWord};
+
+/// The maximum number of changes that can be undone.
+const MAX_UNDO: usize = 100;
+/// How many words ahead we'll look for each transcribed word when finding them in a dictation.
+/// Rules like number normalization can merge or remove words, but they shouldn't throw us off
+/// for long.
+const WORD_LOOKAHEAD: usize = 5;
+
+/// The user's working document, as the backend sees it. The frontend keeps us up-to-date with
+/// the user's manual edits, and we apply dictations to it, which lets dictations contain voice
+/// editing commands (like "scratch that") that change what's already there.
+#[derive(Default)]
+pub struct Document {
+    /// The full text of the document.
+    text: String,
+    /// The most recent dictation, as its byte offset in the text and what it inserted (including
+    /// any whitespace added to separate it from what was already there), if it's still there.
+    last_dictation: Option<(usize, String)>,
+    /// The user's selection in the document, as start and end byte offsets. If nothing's
+    /// selected, these will be the same, and mark the cursor.
+    selection: (usize, usize),
+    /// Changes made to the document by voice, most recent last, which can be undone by voice.
+    undo_stack: Vec<Edit>,
+    /// Dictated words Whisper wasn't sure about, in order, which the user hasn't checked yet.
+    /// These are kept where they are as the document's edited, and dropped if they're edited
+    /// themselves.
+    uncertain: Vec<UncertainWord>,
+}
+
+/// A change made to a [`Document`] by voice, which can be reversed with "undo". This only records
+/// the part of the text that changed, so undoing it leaves any edits the user has made elsewhere
+/// alone.
+struct Edit {
+    /// Where the change starts in the text.
+    start: usize,
+    /// What was there before the change.
+    old: String,
+    /// What's there now.
+    new: String,
+    /// The last dictation before the change, in the text as it was then.
+    last_dictation: Option<(usize, String)>,
+    /// The selection before the change, in the text as it was then.
+    selection: (usize, usize),
+    /// The uncertain words before the change, in the text as it was then.
+    uncertain: Vec<UncertainWord>,
+}
+
+/// A voice command that edits the document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum EditCommand {
+    /// Removes the last dictation.
+    ScratchThat,
+    /// Undoes the last change made by dictation.
+    Undo,
+    /// Capitalizes every word in the last dictation.
+    CapitalizeThat,
+    /// Replaces the last occurrence of some text with something else.
+    Replace { find: String, replacement: String },
+}
+
+impl Document {
+    /// Gets the full text of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Gets the words in the document the user should check.
+    pub fn uncertain(&self) -> &[UncertainWord] {
+        &self.uncertain
+    }
+    /// Updates the document with the user's manual edits, along with their selection, if it's
+    /// known. These can't be undone by voice, since the frontend handles undoing them itself, and
+    /// any voice edit the user has since changed by hand can't be either.
+    pub fn set_text(&mut self, text: String, selection: Option<(usize, usize)>) {
+        if let Some(selection) = selection {
+            self.selection = selection;
+        }
+        if text == self.text {
+            self.clamp_selection();
+            return;
+        }
+
+        let (start, old_end, new_end) = changed_range(&self.text, &text);
+        self.shift_uncertain(start, old_end, new_end - start);
+        self.shift_undo(start, old_end, new_end - start);
+        // Keep track of the last dictation if it's survived the user's edits, even if it's moved
+        self.last_dictation =
+            shift_span(self.last_dictation.take(), start, old_end, new_end - start);
+        self.text = text;
+        self.clamp_selection();
+    }
+    /// Applies the given dictation to the document. Voice editing commands at the start or end of
+    /// the dictation are carried out (in the order they were spoken), and whatever else was
+    /// dictated is inserted according to the given mode.
+    ///
+    /// Voice commands are currently only understood in English, anything else will be appended
+    /// as-is.
+    ///
+    /// The given words are the ones Whisper transcribed, which are used to find any it wasn't sure
+    /// about in what's inserted.
+    pub fn apply_dictation(
+        &mut self,
+        dictation: &str,
+        language: &str,
+        mode: InsertionMode,
+        words: &[Word],
+    ) -> DocumentUpdate {
+        let (leading, rest, trailing) = if language == "en" {
+            parse_commands(dictation)
+        } else {
+            (None, dictation, None)
+        };
+
+        if let Some(command) = leading {
+            self.run(command);
+        }
+        let inserted = rest.trim().to_string();
+        if !inserted.is_empty() {
+            match mode {
+                InsertionMode::Append => self.append(&inserted),
+                InsertionMode::Cursor => self.insert_at_selection(&inserted),
+            }
+            self.mark_uncertain(words);
+        }
+        if let Some(command) = trailing {
+            self.run(command);
+        }
+
+        DocumentUpdate {
+            text: self.text.clone(),
+            inserted,
+            cursor: self.selection.1,
+            flagged: Vec::new(),
+            uncertain: self.uncertain.clone(),
+            output_error: None,
+        }
+    }
+    /// Marks the uncertain word starting at the given offset as checked, replacing it with the
+    /// given correction if there is one.
+    pub fn resolve_uncertain(&mut self, start: usize, correction: Option<&str>) -> DocumentUpdate {
+        if let Some(idx) = self.uncertain.iter().position(|word| word.start == start) {
+            let word = self.uncertain.remove(idx);
+            if let Some(correction) = correction {
+                self.replace(word.start, word.end, correction);
+                self.shift_uncertain(word.start, word.end, correction.len());
+                let cursor = word.start + correction.len();
+                self.selection = (cursor, cursor);
+                // The last dictation's text might not be what it was anymore
+                self.last_dictation = self.last_dictation.take().filter(|(start, dictated)| {
+                    self.text.get(*start..*start + dictated.len()) == Some(dictated.as_str())
+                });
+            }
+        }
+
+        DocumentUpdate {
+            text: self.text.clone(),
+            inserted: String::new(),
+            cursor: self.selection.1,
+            flagged: Vec::new(),
+            uncertain: self.uncertain.clone(),
+            output_error: None,
+        }
+    }
+    /// Appends the given text as a new paragraph, making it the last dictation.
+    fn append(&mut self, new_text: &str) {
+        // It's much easier for a human to remove paragraphing than to add it!
+        let inserted = if self.text.is_empty() {
+            new_text.to_string()
+        } else {
+            format!("\n\n{new_text}")
+        };
+        let start = self.text.len();
+        self.replace(start, start, &inserted);
+        self.last_dictation = Some((start, inserted));
+        self.selection = (self.text.len(), self.text.len());
+    }
+    /// Inserts the given text at the cursor, or in place of the selection if there is one, making
+    /// it the last dictation. Spacing and capitalization are adjusted so the new text fits into
+    /// the sentence around it.
+    fn insert_at_selection(&mut self, new_text: &str) {
+        let (start, end) = self.selection;
+        let before = &self.text[..start];
+        let after = &self.text[end..];
+
+        let mut inserted = String::new();
+        if !before.is_empty() && !before.ends_with(char::is_whitespace) {
+            inserted.push(' ');
+        }
+        // Whisper treats every dictation as its own sentence, but it might be going into the
+        // middle of one
+        let starts_sentence = before.trim_end().ends_with(['.', '?', '!'])
+            || before.trim_end().is_empty()
+            || before.ends_with('\n');
+        if starts_sentence {
+            inserted.push_str(&capitalize(new_text));
+        } else {
+            inserted.push_str(&decapitalize(new_text, &self.text));
+        }
+        let continues_sentence = after
+            .trim_start()
+            .starts_with(|c: char| c.is_lowercase() || matches!(c, ',' | ';' | ':'));
+        if continues_sentence && inserted.ends_with('.') {
+            inserted.pop();
+        }
+        if !after.is_empty()
+            && !after.starts_with(|c: char| {
+                c.is_whitespace() || matches!(c, '.' | ',' | '?' | '!' | ';' | ':' | ')')
+            })
+        {
+            inserted.push(' ');
+        }
+
+        self.replace(start, end, &inserted);
+        self.shift_uncertain(start, end, inserted.len());
+        self.last_dictation = Some((start, inserted.clone()));
+        let cursor = start + inserted.trim_end().len();
+        self.selection = (cursor, cursor);
+    }
+    /// Carries out the given editing command.
+    fn run(&mut self, command: EditCommand) {
+        match command {
+            EditCommand::ScratchThat => {
+                if let Some((start, dictated)) = self.last_dictation.clone() {
+                    self.replace(start, start + dictated.len(), "");
+                    self.shift_uncertain(start, start + dictated.len(), 0);
+                    self.last_dictation = None;
+                    self.selection = (start, start);
+                }
+            }
+            EditCommand::Undo => {
+                if let Some(edit) = self.undo_stack.pop() {
+                    let end = edit.start + edit.new.len();
+                    // We keep track of every change, so this should always be where we left it,
+                    // but if it isn't, it's better to do nothing than to mangle the user's text
+                    if self.text.get(edit.start..end) != Some(edit.new.as_str()) {
+                        tracing::warn!("lost track of voice edit, clearing undo history");
+                        self.undo_stack.clear();
+                        return;
+                    }
+                    self.text.replace_range(edit.start..end, &edit.old);
+                    self.last_dictation = edit.last_dictation;
+                    self.selection = edit.selection;
+                    self.uncertain = edit.uncertain;
+                    self.clamp_selection();
+                }
+            }
+            EditCommand::CapitalizeThat => {
+                if let Some((start, dictated)) = self.last_dictation.clone() {
+                    let capitalized = dictated
+                        .split_inclusive(char::is_whitespace)
+                        .map(capitalize)
+                        .collect::<String>();
+                    self.replace(start, start + dictated.len(), &capitalized);
+                    // Capitalizing doesn't usually move anything, but it can for some characters
+                    if capitalized.len() != dictated.len() {
+                        self.shift_uncertain(start, start + dictated.len(), capitalized.len());
+                    }
+                    self.last_dictation = Some((start, capitalized));
+                    self.clamp_selection();
+                }
+            }
+            EditCommand::Replace { find, replacement } => {
+                if let Some((start, end)) = find_words(&self.text, &find) {
+                    self.replace(start, end, &replacement);
+                    self.shift_uncertain(start, end, replacement.len());
+                    // The last dictation might have moved or changed
+                    self.last_dictation =
+                        self.last_dictation.take().and_then(|(d_start, dictated)| {
+                            let d_end = d_start + dictated.len();
+                            if end <= d_start {
+                                Some((d_start - (end - start) + replacement.len(), dictated))
+                            } else if start >= d_end {
+                                Some((d_start, dictated))
+                            } else if start >= d_start && end <= d_end {
+                                let new_end = d_end - (end - start) + replacement.len();
+                                Some((d_start, self.text[d_start..new_end].to_string()))
+                            } else {
+                                None
+                            }
+                        });
+                    if self.selection.0 >= end {
+                        let shift = |pos: usize| pos - (end - start) + replacement.len();
+                        self.selection = (shift(self.selection.0), shift(self.selection.1));
+                    }
+                    self.clamp_selection();
+                }
+            }
+        }
+    }
+    /// Makes sure the selection is actually within the text, and doesn't split any characters.
+    fn clamp_selection(&mut self) {
+        let clamp = |mut pos: usize| {
+            pos = pos.min(self.text.len());
+            while !self.text.is_char_boundary(pos) {
+                pos -= 1;
+            }
+            pos
+        };
+        let start = clamp(self.selection.0);
+        let end = clamp(self.selection.1).max(start);
+        self.selection = (start, end);
+    }
+    /// Finds the words Whisper wasn't sure about in the last dictation, and marks them for the user
+    /// to check.
+    fn mark_uncertain(&mut self, words: &[Word]) {
+        let (start, dictated) = match &self.last_dictation {
+            Some((start, dictated)) => (*start, dictated.clone()),
+            None => return,
+        };
+        let dictated_words = words_with_offsets(&dictated);
+
+        let mut next = 0;
+        let mut marked = Vec::new();
+        for word in words {
+            let normalized = normalize_word(&word.text);
+            if normalized.is_empty() {
+                continue;
+            }
+            // The dictation's words are in the same order, but some might have been changed by
+            // punctuation and normalization rules, so we only look a little way ahead
+            let found = dictated_words[next..]
+                .iter()
+                .take(WORD_LOOKAHEAD)
+                .position(|(_, candidate)| normalize_word(candidate) == normalized);
+            let (offset, found_word) = match found {
+                Some(idx) => {
+                    next += idx + 1;
+                    dictated_words[next - 1]
+                }
+                None => continue,
+            };
+            if !word.is_uncertain() {
+                continue;
+            }
+
+            // Punctuation around the word isn't part of it
+            let leading = found_word.len()
+                - found_word
+                    .trim_start_matches(|c: char| !c.is_alphanumeric())
+                    .len();
+            let trimmed = trim_non_alphanumeric(found_word);
+            marked.push(UncertainWord {
+                start: start + offset + leading,
+                end: start + offset + leading + trimmed.len(),
+                confidence: word.confidence,
+                alternatives: word.alternatives.clone(),
+            });
+        }
+
+        // Keep everything in order
+        let idx = self
+            .uncertain
+            .iter()
+            .position(|word| word.start >= start)
+            .unwrap_or(self.uncertain.len());
+        self.uncertain.splice(idx..idx, marked);
+    }
+    /// Updates the positions of the uncertain words after the given range of the text has been
+    /// replaced with something of the given length. Any words in that range are forgotten.
+    fn shift_uncertain(&mut self, start: usize, end: usize, new_len: usize) {
+        shift_words(&mut self.uncertain, start, end, new_len);
+    }
+    /// Replaces the given range of the text with the given new text, remembering how to undo it.
+    /// Anything else that depends on the text (like the uncertain words) is left for the caller to
+    /// update.
+    fn replace(&mut self, start: usize, end: usize, new: &str) {
+        self.undo_stack.push(Edit {
+            start,
+            old: self.text[start..end].to_string(),
+            new: new.to_string(),
+            last_dictation: self.last_dictation.clone(),
+            selection: self.selection,
+            uncertain: self.uncertain.clone(),
+        });
+        if self.undo_stack.len() > MAX_UNDO {
+            self.undo_stack.remove(0);
+        }
+        self.text.replace_range(start..end, new);
+    }
+    /// Updates the voice edits that can be undone after the user has replaced the given range of
+    /// the text with something of the given length. Undoing an edit the user has since changed
+    /// would throw away what they did, so that edit (and everything before it) is forgotten.
+    fn shift_undo(&mut self, mut start: usize, mut end: usize, new_len: usize) {
+        // Each edit is in the text as it'll be once everything after it has been undone, so we
+        // work back through them, carrying the user's change back with us
+        for idx in (0..self.undo_stack.len()).rev() {
+            let edit = &mut self.undo_stack[idx];
+            let edit_end = edit.start + edit.new.len();
+            if start < edit_end && end > edit.start {
+                self.undo_stack.drain(..=idx);
+                return;
+            }
+            if end <= edit.start {
+                edit.start = edit.start - (end - start) + new_len;
+            } else {
+                let before_edit = |pos: usize| pos - edit.new.len() + edit.old.len();
+                start = before_edit(start);
+                end = before_edit(end);
+            }
+
+            edit.last_dictation = shift_span(edit.last_dictation.take(), start, end, new_len);
+            shift_words(&mut edit.uncertain, start, end, new_len);
+            let shift = |pos: usize| {
+                if pos >= end {
+                    pos - (end - start) + new_len
+                } else {
+                    pos.min(start)
+                }
+            };
+            edit.selection = (shift(edit.selection.0), shift(edit.selection.1));
+        }
+    }
+}
+
+/// Updates the positions of the given words after the given range of their text has been replaced
+/// with something of the given length. Any words in that range are forgotten.
+fn shift_words(words: &mut Vec<UncertainWord>, start: usize, end: usize, new_len: usize) {
+    words.retain_mut(|word| {
+        if word.end <= start {
+            true
+        } else if word.start >= end {
+            word.start = word.start - end + start + new_len;
+            word.end = word.end - end + start + new_len;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Updates the position of the given span of text (as its start and contents) after the given
+/// range of the text it's in has been replaced with something of the given length. If the span
+/// itself was changed, it's gone.
+fn shift_span(
+    span: Option<(usize, String)>,
+    start: usize,
+    end: usize,
+    new_len: usize,
+) -> Option<(usize, String)> {
+    let (span_start, text) = span?;
+    if end <= span_start {
+        Some((span_start - (end - start) + new_len, text))
+    } else if start >= span_start + text.len() {
+        Some((span_start, text))
+    } else {
+        None
+    }
+}
+
+/// Splits the given dictation into a command at the start, the text to insert, and a command at
+/// the end. Replacements have to be the whole dictation, since there'd be no telling where they
+/// end otherwise.
+fn parse_commands(dictation: &str) -> (Option<EditCommand>, &str, Option<EditCommand>) {
+    let words = words_with_offsets(dictation);
+    let normalized = words
+        .iter()
+        .map(|(_, word)| normalize_word(word))
+        .collect::<Vec<_>>();
+
+    // "replace <find> with <replacement>"
+    if normalized.first().map(String::as_str) == Some("replace") {
+        if let Some(with) = normalized.iter().rposition(|word| word == "with") {
+            if with > 1 && with < words.len() - 1 {
+                let find_start = words[1].0;
+                let find_end = words[with - 1].0 + words[with - 1].1.len();
+                let replacement_start = words[with + 1].0;
+                return (
+                    Some(EditCommand::Replace {
+                        find: dictation[find_start..find_end].to_string(),
+                        replacement: trim_punctuation(&dictation[replacement_start..]).to_string(),
+                    }),
+                    "",
+                    None,
+                );
+            }
+        }
+    }
+
+    // "undo" on its own is too common a word to look for anywhere else
+    if normalized == ["undo"] {
+        return (Some(EditCommand::Undo), "", None);
+    }
+
+    let commands: &[(&[&str], EditCommand)] = &[
+        (&["scratch", "that"], EditCommand::ScratchThat),
+        (&["delete", "that"], EditCommand::ScratchThat),
+        (&["undo", "that"], EditCommand::Undo),
+        (&["capitalize", "that"], EditCommand::CapitalizeThat),
+        (&["capitalise", "that"], EditCommand::CapitalizeThat),
+    ];
+    let mut leading = None;
+    let mut rest_start = 0;
+    let mut rest_end = dictation.len();
+    let mut first_word = 0;
+    for (phrase, command) in commands {
+        if normalized.len() >= phrase.len() && normalized[..phrase.len()] == **phrase {
+            let (offset, word) = words[phrase.len() - 1];
+            leading = Some(command.clone());
+            rest_start = offset + word.len();
+            first_word = phrase.len();
+            break;
+        }
+    }
+    let mut trailing = None;
+    for (phrase, command) in commands {
+        if normalized.len() >= first_word + phrase.len()
+            && normalized[normalized.len() - phrase.len()..] == **phrase
+        {
+            trailing = Some(command.clone());
+            rest_end = words[normalized.len() - phrase.len()].0;
+            break;
+        }
+    }
+
+    // Only trim the punctuation separating the rest from a command, so a dictation without
+    // any keeps its full stop
+    let mut rest = &dictation[rest_start..rest_end.max(rest_start)];
+    if leading.is_some() {
+        rest = rest.trim_start_matches(is_command_separator);
+    }
+    if trailing.is_some() {
+        rest = rest.trim_end_matches(is_command_separator);
+    }
+    (leading, rest.trim(), trailing)
+}
+
+/// Finds the last occurrence of the given words in the given text, ignoring case and
+/// punctuation, and returns the byte range they cover (not including any punctuation around
+/// them).
+fn find_words(text: &str, needle: &str) -> Option<(usize, usize)> {
+    let needle = needle
+        .split_whitespace()
+        .map(normalize_word)
+        .collect::<Vec<_>>();
+    if needle.is_empty() {
+        return None;
+    }
+    let words = words_with_offsets(text);
+    let normalized = words
+        .iter()
+        .map(|(_, word)| normalize_word(word))
+        .collect::<Vec<_>>();
+
+    let first = normalized
+        .windows(needle.len())
+        .rposition(|window| window == needle.as_slice())?;
+    let (first_offset, first_word) = words[first];
+    let (last_offset, last_word) = words[first + needle.len() - 1];
+    let start = first_offset
+        + (first_word.len()
+            - first_word
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .len());
+    let end = last_offset
+        + last_word
+            .trim_end_matches(|c: char| !c.is_alphanumeric())
+            .len();
+
+    Some((start, end.max(start)))
+}
+
+/// Lowercases the first letter of the given text, but only if its first word is written in
+/// lowercase somewhere else in it or in the given context (i.e. the rest of the document). Whisper
+/// capitalizes the start of every dictation, but there's no telling whether a word we've never
+/// seen in lowercase is a name (like "Smith" or "Paris"), an acronym or "I", so those are left as
+/// Whisper wrote them.
+fn decapitalize(text: &str, context: &str) -> String {
+    let first_word = trim_non_alphanumeric(text.split_whitespace().next().unwrap_or(""));
+    let lowercase = first_word.to_lowercase();
+    let seen_lowercase = text
+        .split_whitespace()
+        .skip(1)
+        .chain(context.split_whitespace())
+        .any(|word| trim_non_alphanumeric(word) == lowercase);
+    // "I" is always capitalised, even if someone typed it in lowercase elsewhere
+    let pronoun = first_word == "I" || first_word.starts_with("I'");
+    if first_word == lowercase || pronoun || !seen_lowercase {
+        return text.to_string();
+    }
+
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Splits the given text into words, along with the byte offset each starts at.
+fn words_with_offsets(text: &str) -> Vec<(usize, &str)> {
+    text.split_whitespace()
+        // This is always a subslice of `text`
+        .map(|word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+        .collect()
+}
+
+/// Trims anything that isn't part of a word (like punctuation) from around the given text.
+fn trim_non_alphanumeric(text: &str) -> &str {
+    text.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+/// Works out which part of the given old text was changed to make the given new text, as the
+/// start of the change and where it ends in each (as byte offsets). Several separate changes are
+/// treated as one big one.
+fn changed_range(old: &str, new: &str) -> (usize, usize, usize) {
+    let prefix = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+    let suffix = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+
+    (prefix, old.len() - suffix, new.len() - suffix)
+}
+
+/// Trims punctuation Whisper might have added around a command from the given text.
+fn trim_punctuation(text: &str) -> &str {
+    text.trim_matches(is_command_separator)
+}
+
+/// Whether the given character can separate a voice command from the rest of a dictation.
+fn is_command_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '.' | ',' | '?' | '!' | ';' | ':')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dictates the given text into the given document, appending it.
+    fn dictate(doc: &mut Document, text: &str) {
+        doc.apply_dictation(text, "en", InsertionMode::Append, &[]);
+    }
+
+    #[test]
+    fn changed_range_finds_the_edit() {
+        assert_eq!(
+            changed_range("hello world", "hello there world"),
+            (6, 6, 12)
+        );
+        assert_eq!(changed_range("hello world", "hello"), (5, 11, 5));
+        assert_eq!(changed_range("same", "same"), (4, 4, 4));
+    }
+
+    #[test]
+    fn changed_range_respects_multibyte_characters() {
+        // "é" and "à" are two bytes each
+        assert_eq!(changed_range("café", "café au lait"), (5, 5, 13));
+        assert_eq!(changed_range("voilà", "voilé"), (4, 6, 6));
+    }
+
+    #[test]
+    fn commands_are_parsed_from_either_end() {
+        assert_eq!(
+            parse_commands("Scratch that. Hello there."),
+            (Some(EditCommand::ScratchThat), "Hello there.", None)
+        );
+        assert_eq!(
+            parse_commands("Hello there, undo that."),
+            (None, "Hello there", Some(EditCommand::Undo))
+        );
+        assert_eq!(
+            parse_commands("Capitalise that."),
+            (Some(EditCommand::CapitalizeThat), "", None)
+        );
+        assert_eq!(parse_commands("Undo."), (Some(EditCommand::Undo), "", None));
+    }
+
+    #[test]
+    fn replacements_are_the_whole_dictation() {
+        assert_eq!(
+            parse_commands("Replace the cat with a dog."),
+            (
+                Some(EditCommand::Replace {
+                    find: "the cat".to_string(),
+                    replacement: "a dog".to_string(),
+                }),
+                "",
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn ordinary_words_are_not_commands() {
+        assert_eq!(
+            parse_commands("I need to undo the change."),
+            (None, "I need to undo the change.", None)
+        );
+    }
+
+    #[test]
+    fn dictations_are_appended_as_paragraphs() {
+        let mut doc = Document::default();
+        dictate(&mut doc, "First.");
+        dictate(&mut doc, "Second.");
+        assert_eq!(doc.text(), "First.\n\nSecond.");
+    }
+
+    #[test]
+    fn scratch_that_removes_the_last_dictation() {
+        let mut doc = Document::default();
+        dictate(&mut doc, "First.");
+        dictate(&mut doc, "Second.");
+        dictate(&mut doc, "Scratch that.");
+        assert_eq!(doc.text(), "First.");
+        // A command at the end applies to what came before it in the same dictation
+        dictate(&mut doc, "Oops, scratch that.");
+        assert_eq!(doc.text(), "First.");
+    }
+
+    #[test]
+    fn scratch_that_follows_the_dictation_when_the_selection_moves() {
+        let mut doc = Document::default();
+        doc.set_text("I saw the cat.".to_string(), Some((9, 9)));
+        doc.apply_dictation("Black.", "en", InsertionMode::Cursor, &[]);
+        assert_eq!(doc.text(), "I saw the Black cat.");
+
+        doc.set_text(doc.text().to_string(), Some((0, 0)));
+        doc.apply_dictation("Scratch that.", "en", InsertionMode::Cursor, &[]);
+        assert_eq!(doc.text(), "I saw the cat.");
+        assert_eq!(doc.selection, (9, 9));
+    }
+
+    #[test]
+    fn undo_reverses_voice_edits() {
+        let mut doc = Document::default();
+        dictate(&mut doc, "hello world");
+        dictate(&mut doc, "Capitalize that.");
+        assert_eq!(doc.text(), "Hello World");
+        dictate(&mut doc, "Undo.");
+        assert_eq!(doc.text(), "hello world");
+    }
+
+    #[test]
+    fn undo_keeps_manual_edits_elsewhere() {
+        let mut doc = Document::default();
+        dictate(&mut doc, "First.");
+        dictate(&mut doc, "Second.");
+        dictate(&mut doc, "Replace second with third.");
+        assert_eq!(doc.text(), "First.\n\nthird.");
+
+        doc.set_text("Intro. First.\n\nthird.".to_string(), None);
+        dictate(&mut doc, "Undo.");
+        assert_eq!(doc.text(), "Intro. First.\n\nSecond.");
+    }
+
+    #[test]
+    fn undo_forgets_voice_edits_changed_by_hand() {
+        let mut doc = Document::default();
+        dictate(&mut doc, "First.");
+        dictate(&mut doc, "Replace first with third.");
+        doc.set_text("tired.".to_string(), None);
+        dictate(&mut doc, "Undo.");
+        assert_eq!(doc.text(), "tired.");
+    }
+
+    #[test]
+    fn edits_work_around_multibyte_text() {
+        let mut doc = Document::default();
+        dictate(&mut doc, "Café au lait.");
+        dictate(&mut doc, "Très bien.");
+        doc.set_text(format!("Déjà: {}", doc.text()), None);
+
+        dictate(&mut doc, "Scratch that.");
+        assert_eq!(doc.text(), "Déjà: Café au lait.");
+        dictate(&mut doc, "Undo.");
+        assert_eq!(doc.text(), "Déjà: Café au lait.\n\nTrès bien.");
+    }
+
+    #[test]
+    fn spans_move_with_edits_before_them() {
+        let span = Some((10, "dictated".to_string()));
+        assert_eq!(
+            shift_span(span.clone(), 0, 4, 1),
+            Some((7, "dictated".to_string()))
+        );
+        assert_eq!(shift_span(span.clone(), 20, 25, 0), span);
+        assert_eq!(shift_span(span, 12, 14, 3), None);
+    }
+
+    #[test]
+    fn decapitalize_only_lowercases_known_words() {
+        assert_eq!(decapitalize("The dog", "saw the cat"), "the dog");
+        assert_eq!(decapitalize("Smith said so", "we met"), "Smith said so");
+        assert_eq!(decapitalize("Paris is nice", "paris"), "paris is nice");
+        assert_eq!(decapitalize("I think", "i"), "I think");
+    }
+}
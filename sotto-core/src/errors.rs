@@ -0,0 +1,466 @@
+use sotto_types::{CommandError, ErrorCode};
+use thiserror::Error;
+
+/// Errors that can occur in Sotto.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot begin dictation, we're already dictating")]
+    AlreadyDictating,
+    #[error("cannot end recording, we aren't dictating")]
+    NotDictating,
+    #[error("dictation was cancelled")]
+    DictationCancelled,
+    #[error("failed to create temporary file to record to")]
+    TmpFileCreationFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("dictation task panicked")]
+    DictationTaskPanicked { source: tokio::task::JoinError },
+    #[error("no audio input device found (do you have a microphone connected?)")]
+    NoInputDevice,
+    #[cfg(feature = "record")]
+    #[error("failed to get configuration for default audio input device")]
+    GetDefaultInputConfigFailed {
+        #[source]
+        source: cpal::DefaultStreamConfigError,
+    },
+    #[error("failed to create a wav file writer to record audio")]
+    CreateWavWriterFailed {
+        #[source]
+        source: hound::Error,
+    },
+    #[cfg(feature = "record")]
+    #[error("failed to build input stream for recording audio")]
+    BuildInputStreamFailed {
+        #[source]
+        source: cpal::BuildStreamError,
+    },
+    #[cfg(feature = "record")]
+    #[error("failed to start audio input stream for recording")]
+    StartStreamFailed {
+        #[source]
+        source: cpal::PlayStreamError,
+    },
+    #[error("failed to instantiate context for speech recognition")]
+    LoadWhisperCtxFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error("failed to create state for speech recognition")]
+    CreateWhisperStateFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error("failed to create a reader for recorded audio")]
+    CreateWavReaderFailed {
+        #[source]
+        source: hound::Error,
+    },
+    #[error("failed to parse recorded audio sample (try again, but your microphone may not be compatible with Sotto)")]
+    ParseSampleFailed {
+        #[source]
+        source: hound::Error,
+    },
+    #[error("failed to run speech recognition model")]
+    WhisperRunFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error("failed to get number of segments in speech recognition output")]
+    GetNumSegmentsFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error("failed to extract text from speech recognition output")]
+    GetSegmentTextFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error("failed to extract timestamps from speech recognition output")]
+    GetSegmentTimestampFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error("failed to extract tokens from speech recognition output")]
+    GetSegmentTokensFailed {
+        #[source]
+        source: whisper_rs::WhisperError,
+    },
+    #[error(
+        "couldn't find a home directory (please execute this program on a sane operating system)"
+    )]
+    NoHomeDir,
+    #[error("the speech recognition model hasn't been downloaded yet")]
+    ModelNotDownloaded,
+    #[cfg(feature = "download")]
+    #[error("failed to download model (are you connected to the internet?)")]
+    DownloadModelFailed {
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("downloading model failed with http status code {status}")]
+    DownloadModelBadStatus { status: u16 },
+    #[error("failed to create a file for the downloaded model")]
+    CreateModelFileFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[cfg(feature = "download")]
+    #[error("found bad chunk in downloaded model response")]
+    BadChunk {
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("failed to write chunk of downloaded model to file")]
+    WriteChunkFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[cfg(feature = "download")]
+    #[error("failed to get model index to download model (are you connected to the internet?)")]
+    GetModelIndexFailed { source: reqwest::Error },
+    #[error("getting model index failed with http status code {status}")]
+    GetModelIndexBadStatus { status: u16 },
+    #[error(transparent)]
+    ModelIndexError(#[from] ModelIndexError),
+    #[error("failed to create sotto's data directory")]
+    CreateDataDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to create sotto's cache directory")]
+    CreateCacheDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to read settings file")]
+    ReadSettingsFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to create directory for settings")]
+    CreateConfigDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to move sotto's files out of `~/.sotto`")]
+    MigrateDataFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(
+        "settings file is from a newer version of sotto (version {version}), please update sotto"
+    )]
+    SettingsTooNew { version: u32 },
+    #[error("there must be at least one profile")]
+    NoProfiles,
+    #[error("every profile must have a name")]
+    UnnamedProfile,
+    #[error("there's already a profile called '{name}'")]
+    DuplicateProfile { name: String },
+    #[error("couldn't find that profile")]
+    ProfileNotFound,
+    #[error("failed to parse settings file")]
+    ParseSettingsFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("failed to write settings file")]
+    WriteSettingsFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to create directory for storing past recordings")]
+    CreateHistoryDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to compress recording for storage")]
+    EncodeRecordingFailed {
+        #[source]
+        source: flacenc::error::EncodeError,
+    },
+    #[error("failed to decompress stored recording")]
+    DecodeRecordingFailed {
+        #[source]
+        source: claxon::Error,
+    },
+    #[error("failed to read past recordings")]
+    ReadHistoryEntryFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to parse transcript of past recording")]
+    ParseHistoryEntryFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("couldn't find a recording of that text (was it recorded before you turned on keeping recordings?)")]
+    RecordingNotFound,
+    #[error("no audio output device found (do you have speakers or headphones connected?)")]
+    NoOutputDevice,
+    #[cfg(feature = "record")]
+    #[error("failed to get configuration for default audio output device")]
+    GetDefaultOutputConfigFailed {
+        #[source]
+        source: cpal::DefaultStreamConfigError,
+    },
+    #[cfg(feature = "record")]
+    #[error("failed to build output stream for playing audio")]
+    BuildOutputStreamFailed {
+        #[source]
+        source: cpal::BuildStreamError,
+    },
+    #[cfg(feature = "record")]
+    #[error("failed to start audio output stream for playback")]
+    StartPlaybackFailed {
+        #[source]
+        source: cpal::PlayStreamError,
+    },
+    #[cfg(feature = "record")]
+    #[error("failed to list microphones")]
+    ListInputDevicesFailed {
+        #[source]
+        source: cpal::DevicesError,
+    },
+    #[error("your recordings are locked, please unlock them with your passphrase first")]
+    VaultLocked,
+    #[error("incorrect passphrase")]
+    IncorrectPassphrase,
+    #[error("the file storing your passphrase details is corrupted (your saved recordings can't be recovered without it)")]
+    VaultCorrupted,
+    #[error("failed to read the file storing your passphrase details")]
+    ReadVaultFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to write the file storing your passphrase details")]
+    WriteVaultFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to derive encryption key from passphrase")]
+    DeriveKeyFailed,
+    #[error("failed to encrypt data")]
+    EncryptFailed,
+    #[error("failed to decrypt data (it may have been corrupted)")]
+    DecryptFailed,
+    #[error("failed to write encrypted file")]
+    WriteEncryptedFileFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to read encrypted file")]
+    ReadEncryptedFileFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to create directory for autosaving")]
+    CreateAutosaveDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to save recording in progress for recovery")]
+    WriteRecoveryRecordingFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to read unfinished recording from last time")]
+    ReadRecoveryRecordingFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[cfg(feature = "output")]
+    #[error("failed to connect to the keyboard to type your text into other apps")]
+    ConnectKeyboardFailed {
+        #[source]
+        source: enigo::NewConError,
+    },
+    #[cfg(feature = "output")]
+    #[error("failed to type your text into the focused app")]
+    TypeTextFailed {
+        #[source]
+        source: enigo::InputError,
+    },
+    #[cfg(feature = "output")]
+    #[error("failed to access the clipboard")]
+    AccessClipboardFailed {
+        #[source]
+        source: arboard::Error,
+    },
+    #[error("subtitles can only be exported from your history, since the working document has no timestamps")]
+    NoTimestamps,
+    #[error("couldn't find that dictation in your history")]
+    HistoryEntryNotFound,
+    #[error("failed to create directory for documents")]
+    CreateDocumentsDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to read saved documents")]
+    ReadWorkspaceFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to parse list of saved documents")]
+    ParseWorkspaceFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("failed to save documents")]
+    WriteWorkspaceFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't find that document")]
+    DocumentNotFound,
+    #[error("failed to write exported file")]
+    WriteExportFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to create directory for logs")]
+    CreateLogDirFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to read logs for diagnostic report")]
+    ReadLogsFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to create diagnostic report file")]
+    CreateReportFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[cfg(feature = "diagnostics")]
+    #[error("failed to write diagnostic report")]
+    WriteReportFailed {
+        #[source]
+        source: zip::result::ZipError,
+    },
+    #[error("failed to start transcription server (is something else using that port?)")]
+    StartServerFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("transcription server stopped unexpectedly")]
+    ServerFailed {
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl Error {
+    /// Gets the kind of error this is, as far as the frontend's concerned.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::AlreadyDictating => ErrorCode::AlreadyDictating,
+            Self::NotDictating => ErrorCode::NotDictating,
+            Self::DictationCancelled => ErrorCode::DictationCancelled,
+            Self::NoInputDevice => ErrorCode::NoInputDevice,
+            Self::NoOutputDevice => ErrorCode::NoOutputDevice,
+            #[cfg(feature = "record")]
+            Self::GetDefaultInputConfigFailed { .. }
+            | Self::BuildInputStreamFailed { .. }
+            | Self::StartStreamFailed { .. }
+            | Self::GetDefaultOutputConfigFailed { .. }
+            | Self::BuildOutputStreamFailed { .. }
+            | Self::StartPlaybackFailed { .. }
+            | Self::ListInputDevicesFailed { .. } => ErrorCode::AudioDevice,
+            Self::TmpFileCreationFailed { .. }
+            | Self::CreateWavWriterFailed { .. }
+            | Self::CreateWavReaderFailed { .. }
+            | Self::ParseSampleFailed { .. }
+            | Self::EncodeRecordingFailed { .. }
+            | Self::DecodeRecordingFailed { .. }
+            | Self::WriteRecoveryRecordingFailed { .. }
+            | Self::ReadRecoveryRecordingFailed { .. } => ErrorCode::Recording,
+            Self::CreateWhisperStateFailed { .. }
+            | Self::WhisperRunFailed { .. }
+            | Self::GetNumSegmentsFailed { .. }
+            | Self::GetSegmentTextFailed { .. }
+            | Self::GetSegmentTimestampFailed { .. }
+            | Self::GetSegmentTokensFailed { .. } => ErrorCode::Transcription,
+            Self::LoadWhisperCtxFailed { .. } | Self::ModelNotDownloaded => ErrorCode::Model,
+            #[cfg(feature = "download")]
+            Self::DownloadModelFailed { .. }
+            | Self::BadChunk { .. }
+            | Self::GetModelIndexFailed { .. } => ErrorCode::ModelDownload,
+            Self::DownloadModelBadStatus { .. }
+            | Self::CreateModelFileFailed { .. }
+            | Self::WriteChunkFailed { .. }
+            | Self::GetModelIndexBadStatus { .. } => ErrorCode::ModelDownload,
+            Self::VaultLocked => ErrorCode::VaultLocked,
+            Self::IncorrectPassphrase => ErrorCode::IncorrectPassphrase,
+            Self::VaultCorrupted | Self::DecryptFailed => ErrorCode::VaultCorrupted,
+            Self::NoHomeDir
+            | Self::CreateDataDirFailed { .. }
+            | Self::CreateCacheDirFailed { .. }
+            | Self::ReadSettingsFailed { .. }
+            | Self::WriteSettingsFailed { .. }
+            | Self::CreateHistoryDirFailed { .. }
+            | Self::ReadHistoryEntryFailed { .. }
+            | Self::ParseHistoryEntryFailed { .. }
+            | Self::ReadVaultFailed { .. }
+            | Self::WriteVaultFailed { .. }
+            | Self::WriteEncryptedFileFailed { .. }
+            | Self::ReadEncryptedFileFailed { .. }
+            | Self::CreateAutosaveDirFailed { .. }
+            | Self::CreateDocumentsDirFailed { .. }
+            | Self::ReadWorkspaceFailed { .. }
+            | Self::ParseWorkspaceFailed { .. }
+            | Self::WriteWorkspaceFailed { .. }
+            | Self::CreateLogDirFailed { .. }
+            | Self::CreateConfigDirFailed { .. }
+            | Self::MigrateDataFailed { .. } => ErrorCode::Storage,
+            Self::ParseSettingsFailed { .. } | Self::SettingsTooNew { .. } => {
+                ErrorCode::InvalidSettings
+            }
+            Self::NoProfiles | Self::UnnamedProfile | Self::DuplicateProfile { .. } => {
+                ErrorCode::InvalidProfile
+            }
+            #[cfg(feature = "output")]
+            Self::ConnectKeyboardFailed { .. }
+            | Self::TypeTextFailed { .. }
+            | Self::AccessClipboardFailed { .. } => ErrorCode::Output,
+            Self::NoTimestamps
+            | Self::WriteExportFailed { .. }
+            | Self::ReadLogsFailed { .. }
+            | Self::CreateReportFailed { .. } => ErrorCode::Export,
+            #[cfg(feature = "diagnostics")]
+            Self::WriteReportFailed { .. } => ErrorCode::Export,
+            Self::RecordingNotFound
+            | Self::HistoryEntryNotFound
+            | Self::DocumentNotFound
+            | Self::ProfileNotFound => ErrorCode::NotFound,
+            Self::DictationTaskPanicked { .. }
+            | Self::DeriveKeyFailed
+            | Self::EncryptFailed
+            | Self::ModelIndexError(_)
+            | Self::StartServerFailed { .. }
+            | Self::ServerFailed { .. } => ErrorCode::Internal,
+        }
+    }
+}
+impl From<Error> for CommandError {
+    fn from(err: Error) -> Self {
+        CommandError::new(err.code(), err.to_string(), format!("{err:?}"))
+    }
+}
+
+/// Errors that can occur with the model index. These are specifically errors that herald a severe
+/// problem with the model index which should be immediately reported to prevent issues for
+/// all users of Sotto worldwide.
+#[derive(Debug, Error)]
+pub enum ModelIndexError {
+    #[error("failed to parse global model index (this is a bug in Sotto, and will be fixed as soon as possible)")]
+    ParseFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("the global model index is missing a key (this is a bug in Sotto, and will be fixed as soon as possible)")]
+    Incomplete { missing_key: &'static str },
+}
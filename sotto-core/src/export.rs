@@ -0,0 +1,168 @@
+use crate::errors::Error;
+use crate::transcribe::Segment;
+use docx_rs::{Docx, Paragraph, Run};
+use std::io::Cursor;
+use std::path::Path;
+
+pub use sotto_types::{ExportFormat, ExportSource};
+
+/// Writes the given text to the given path in the given format. Subtitle formats need the
+/// segments the text came from, and will fail if they aren't given.
+pub fn export(
+    path: &Path,
+    format: ExportFormat,
+    text: &str,
+    segments: Option<&[Segment]>,
+) -> Result<(), Error> {
+    let contents = render(format, text, segments)?;
+    std::fs::write(path, contents).map_err(|err| Error::WriteExportFailed { source: err })
+}
+
+/// Converts the given text into the given format, without writing it anywhere. Like with
+/// [`export`], subtitle formats need the segments the text came from.
+pub fn render(
+    format: ExportFormat,
+    text: &str,
+    segments: Option<&[Segment]>,
+) -> Result<Vec<u8>, Error> {
+    let contents = match format {
+        ExportFormat::Txt => format!("{}\n", text.trim()).into_bytes(),
+        ExportFormat::Markdown => to_markdown(text).into_bytes(),
+        ExportFormat::Docx => to_docx(text)?,
+        ExportFormat::Srt => to_srt(segments.ok_or(Error::NoTimestamps)?).into_bytes(),
+        ExportFormat::Vtt => to_vtt(segments.ok_or(Error::NoTimestamps)?).into_bytes(),
+    };
+
+    Ok(contents)
+}
+
+/// Converts the given text into Markdown, escaping anything that would otherwise be treated as
+/// formatting.
+fn to_markdown(text: &str) -> String {
+    let mut markdown = paragraphs(text)
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(escape_markdown)
+                // Hard line breaks, so single newlines survive rendering
+                .collect::<Vec<_>>()
+                .join("  \n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    markdown.push('\n');
+
+    markdown
+}
+
+/// Escapes characters that have special meaning in Markdown.
+fn escape_markdown(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    for c in line.trim().chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    // Things like `1.` and `-` only mean something at the start of a line
+    if escaped.starts_with(['-', '+']) {
+        escaped.insert(0, '\\');
+    } else if let Some(dot) = escaped.find('.') {
+        if dot > 0 && escaped[..dot].chars().all(|c| c.is_ascii_digit()) {
+            escaped.insert(dot, '\\');
+        }
+    }
+
+    escaped
+}
+
+/// Converts the given text into a Word document, with a paragraph for each paragraph of text.
+fn to_docx(text: &str) -> Result<Vec<u8>, Error> {
+    let docx = paragraphs(text).fold(Docx::new(), |docx, paragraph| {
+        let run = paragraph
+            .lines()
+            .enumerate()
+            .fold(Run::new(), |run, (i, line)| {
+                let run = if i > 0 {
+                    run.add_break(docx_rs::BreakType::TextWrapping)
+                } else {
+                    run
+                };
+                run.add_text(line.trim())
+            });
+        docx.add_paragraph(Paragraph::new().add_run(run))
+    });
+
+    let mut buf = Cursor::new(Vec::new());
+    docx.build()
+        .pack(&mut buf)
+        .map_err(|err| Error::WriteExportFailed {
+            source: std::io::Error::new(std::io::ErrorKind::Other, err),
+        })?;
+
+    Ok(buf.into_inner())
+}
+
+/// Converts the given segments into SubRip subtitles.
+fn to_srt(segments: &[Segment]) -> String {
+    subtitle_cues(segments)
+        .enumerate()
+        .map(|(i, (segment, text))| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                format_timestamp(segment.start_ms, ','),
+                format_timestamp(segment.end_ms, ','),
+                text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Converts the given segments into WebVTT subtitles.
+fn to_vtt(segments: &[Segment]) -> String {
+    let mut vtt = "WEBVTT\n".to_string();
+    for (segment, text) in subtitle_cues(segments) {
+        vtt.push_str(&format!(
+            "\n{} --> {}\n{}\n",
+            format_timestamp(segment.start_ms, '.'),
+            format_timestamp(segment.end_ms, '.'),
+            text
+        ));
+    }
+
+    vtt
+}
+
+/// Gets the segments that should become subtitle cues, along with their trimmed text. Whisper
+/// sometimes produces empty segments, which would be invalid cues.
+fn subtitle_cues(segments: &[Segment]) -> impl Iterator<Item = (&Segment, &str)> {
+    segments
+        .iter()
+        .map(|segment| (segment, segment.text.trim()))
+        .filter(|(_, text)| !text.is_empty())
+}
+
+/// Formats the given time as `HH:MM:SS<sep>mmm`, which is what both SRT (with a comma) and
+/// WebVTT (with a full stop) use.
+fn format_timestamp(ms: u64, sep: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1000) % 60,
+        sep,
+        ms % 1000
+    )
+}
+
+/// Splits the given text into its non-empty paragraphs.
+fn paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    text.split("\n\n")
+        .map(|paragraph| paragraph.trim())
+        .filter(|paragraph| !paragraph.is_empty())
+}
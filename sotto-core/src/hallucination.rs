@@ -0,0 +1,219 @@
+use crate::punctuation::normalize_word;
+use crate::transcribe::{segment_audio, FlagReason, FlaggedSegment, Segment, Transcript};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// Segments quieter than this (as an RMS level) are treated as silence, however loud the rest of
+/// the recording is.
+const ABSOLUTE_SILENCE_RMS: f32 = 0.005;
+/// Segments quieter than this fraction of the loudest segment's level are treated as silence.
+const RELATIVE_SILENCE: f32 = 0.1;
+/// If Whisper is less confident than this in a segment over silence, we assume it made the segment
+/// up. This stands in for Whisper's own no-speech probability, which we can't get at.
+const NO_SPEECH_MAX_CONFIDENCE: f32 = 0.6;
+/// Phrases Whisper is known to make up are only believed if it's at least this confident in them
+/// (and they weren't spoken over silence).
+const KNOWN_PHRASE_MIN_CONFIDENCE: f32 = 0.8;
+/// Text that compresses better than this is so repetitive that it's almost certainly Whisper
+/// stuck in a loop. This is the same threshold Whisper itself uses.
+const MAX_COMPRESSION_RATIO: f32 = 2.4;
+/// A segment that repeats the one before it is only treated as a loop if it's at least this many
+/// words long, since people do say "yes. Yes." sometimes.
+const MIN_REPEATED_WORDS: usize = 3;
+/// The longest phrase (in words) we look for loops of within a segment.
+const MAX_LOOP_WORDS: usize = 8;
+
+/// Phrases Whisper commonly produces from silence or noise, because they turn up at the end of so
+/// many of the videos it was trained on. These are compared against whole segments, lowercased
+/// and without punctuation.
+const KNOWN_PHRASES: &[&str] = &[
+    "you",
+    "bye",
+    "thanks",
+    "thank you",
+    "thank you so much",
+    "thank you very much",
+    "thanks for watching",
+    "thank you for watching",
+    "thank you for watching and see you next time",
+    "thank you so much for watching",
+    "please subscribe",
+    "like and subscribe",
+    "please like and subscribe",
+    "don't forget to like and subscribe",
+    "subscribe to my channel",
+    "see you next time",
+    "see you in the next video",
+];
+/// Like [`KNOWN_PHRASES`], but these are matched against the start of segments, since whatever
+/// comes after them varies.
+const KNOWN_PREFIXES: &[&str] = &[
+    "subtitles by",
+    "transcribed by",
+    "translated by",
+    "captions by",
+];
+
+/// A segment as Whisper produced it, before we've decided whether or not to believe it.
+pub struct Candidate {
+    pub segment: Segment,
+    /// The mean probability Whisper gave the tokens in this segment (ignoring special tokens).
+    pub confidence: f32,
+}
+
+/// Builds a transcript from the given candidate segments, leaving out any that Whisper seems to
+/// have made up from silence or noise and collapsing any loops it got stuck in. Anything left out
+/// is flagged in the transcript instead, so the user can decide for themselves.
+///
+/// Whisper's own no-speech probability isn't exposed to us, so we approximate it with the level of
+/// the audio under each segment and how confident Whisper was in its tokens.
+pub fn filter(candidates: Vec<Candidate>, audio: &[f32]) -> Transcript {
+    let levels = candidates
+        .iter()
+        .map(|candidate| rms(segment_audio(audio, &candidate.segment)))
+        .collect::<Vec<_>>();
+    let loudest = levels.iter().copied().fold(0.0, f32::max);
+    let silence = (loudest * RELATIVE_SILENCE).max(ABSOLUTE_SILENCE_RMS);
+
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut flagged = Vec::new();
+    for (candidate, level) in candidates.into_iter().zip(levels) {
+        let Candidate {
+            mut segment,
+            confidence,
+        } = candidate;
+        let words = normalized_words(&segment.text);
+        if words.is_empty() {
+            segments.push(segment);
+            continue;
+        }
+        let quiet = level < silence;
+        let phrase = words.join(" ");
+
+        let reason = if quiet && confidence < NO_SPEECH_MAX_CONFIDENCE {
+            Some(FlagReason::NoSpeech)
+        } else if is_known_phrase(&phrase) && (quiet || confidence < KNOWN_PHRASE_MIN_CONFIDENCE) {
+            Some(FlagReason::KnownHallucination)
+        } else if words.len() >= MIN_REPEATED_WORDS
+            && segments
+                .last()
+                .map_or(false, |last| normalized_words(&last.text) == words)
+        {
+            Some(FlagReason::Repeated)
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            flagged.push(FlaggedSegment { segment, reason });
+            continue;
+        }
+
+        // A loop within the segment can be collapsed to one copy of what was looped, keeping the
+        // rest of the segment, but if it's still repetitive after that, we can't save it
+        let collapsed = collapse_loops(&segment.text);
+        let text = collapsed.as_deref().unwrap_or(&segment.text);
+        if compression_ratio(text) > MAX_COMPRESSION_RATIO {
+            flagged.push(FlaggedSegment {
+                segment,
+                reason: FlagReason::Repetitive,
+            });
+            continue;
+        }
+        if let Some(collapsed) = collapsed {
+            flagged.push(FlaggedSegment {
+                segment: segment.clone(),
+                reason: FlagReason::Looped,
+            });
+            segment.text = collapsed;
+        }
+
+        segments.push(segment);
+    }
+
+    Transcript { segments, flagged }
+}
+
+/// Collapses any phrases that are repeated over and over in a row in the given text down to a
+/// single copy, returning the new text if there were any.
+fn collapse_loops(text: &str) -> Option<String> {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let normalized = words
+        .iter()
+        .map(|word| normalize_word(word))
+        .collect::<Vec<_>>();
+
+    let mut kept = Vec::with_capacity(words.len());
+    let mut collapsed = false;
+    let mut i = 0;
+    'words: while i < words.len() {
+        for len in 1..=MAX_LOOP_WORDS {
+            let pattern = match normalized.get(i..i + len) {
+                Some(pattern) if pattern.iter().any(|word| !word.is_empty()) => pattern,
+                _ => break,
+            };
+            let mut repeats = 1;
+            while normalized.get(i + repeats * len..i + (repeats + 1) * len) == Some(pattern) {
+                repeats += 1;
+            }
+            // Single words get repeated for emphasis ("no, no, no"), so they need to go on for
+            // longer before we believe it's a loop
+            let min_repeats = if len == 1 { 5 } else { 3 };
+            if repeats >= min_repeats {
+                // The last copy is the one with any punctuation that ends the loop
+                let last = i + (repeats - 1) * len;
+                kept.extend_from_slice(&words[last..last + len]);
+                i += repeats * len;
+                collapsed = true;
+                continue 'words;
+            }
+        }
+
+        kept.push(words[i]);
+        i += 1;
+    }
+
+    // Whisper prefixes its segments with a space, so we keep that
+    let prefix = if text.starts_with(' ') { " " } else { "" };
+    collapsed.then(|| format!("{}{}", prefix, kept.join(" ")))
+}
+
+/// Whether or not the given (normalized) phrase is one Whisper commonly makes up.
+fn is_known_phrase(phrase: &str) -> bool {
+    KNOWN_PHRASES.contains(&phrase)
+        || KNOWN_PREFIXES
+            .iter()
+            .any(|prefix| phrase.starts_with(prefix))
+}
+
+/// Splits the given text into words, normalized for comparison.
+fn normalized_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(normalize_word)
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Works out how well the given text compresses, which is high for repetitive text.
+fn compression_ratio(text: &str) -> f32 {
+    if text.is_empty() {
+        return 0.0;
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // This can't fail, we're writing to memory
+    encoder.write_all(text.as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    text.len() as f32 / compressed.len() as f32
+}
+
+/// Gets the RMS level of the given audio.
+fn rms(audio: &[f32]) -> f32 {
+    if audio.is_empty() {
+        return 0.0;
+    }
+
+    let sum = audio.iter().map(|sample| sample * sample).sum::<f32>();
+    (sum / audio.len() as f32).sqrt()
+}
@@ -0,0 +1,176 @@
+use crate::errors::Error;
+use crate::transcribe::{Transcript, SAMPLE_RATE};
+use crate::vault::VaultKey;
+use flacenc::component::BitRepr;
+use flacenc::error::Verify;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+pub use sotto_types::HistoryEntry;
+
+/// The parts of [`HistoryEntry`] only the backend can do, like reading and writing entries on
+/// disk.
+///
+/// Entries are only created if the user has opted into retaining their recordings. Each one is
+/// stored as a pair of files in `history` in Sotto's data directory (see [`crate::paths`]):
+/// `<id>.json.enc` for the transcript, and `<id>.flac.enc` for the audio (compressed, because raw
+/// f32 audio gets big very quickly). Both are encrypted with the vault key, so the vault has to be
+/// unlocked to do anything with them.
+pub trait HistoryEntryExt: Sized {
+    /// Creates a new history entry from the given transcript and the audio it came from, saving
+    /// both to disk.
+    fn create(transcript: Transcript, audio: &[f32], key: &VaultKey) -> Result<Self, Error>;
+    /// Lists all the entries in the user's history, newest first.
+    fn list(key: &VaultKey) -> Result<Vec<Self>, Error>;
+    /// Reads the audio for this entry back into memory.
+    fn audio(&self, key: &VaultKey) -> Result<Vec<f32>, Error>;
+    /// Finds the span of audio (as start and end times in milliseconds) behind the given text,
+    /// if it comes from this entry's transcript. This will cover every segment the text touches.
+    ///
+    /// Matching ignores differences in whitespace, since Whisper pads its segments, and the user
+    /// may well have reflowed things a little.
+    fn find_span(&self, text: &str) -> Option<(u64, u64)>;
+}
+impl HistoryEntryExt for HistoryEntry {
+    fn create(transcript: Transcript, audio: &[f32], key: &VaultKey) -> Result<Self, Error> {
+        let dir = history_dir()?;
+        let id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|dur| dur.as_millis() as u64)
+            .unwrap_or(0);
+        let entry = Self { id, transcript };
+
+        let flac = encode_flac(audio)?;
+        key.write(&dir.join(format!("{id}.flac.enc")), &flac)?;
+        // This can't fail, we have no maps with non-string keys
+        let json = serde_json::to_vec(&entry).unwrap();
+        key.write(&dir.join(format!("{id}.json.enc")), &json)?;
+
+        Ok(entry)
+    }
+    fn list(key: &VaultKey) -> Result<Vec<Self>, Error> {
+        let dir = history_dir()?;
+        let mut entries = Vec::new();
+        for file in
+            std::fs::read_dir(&dir).map_err(|err| Error::ReadHistoryEntryFailed { source: err })?
+        {
+            let path = file
+                .map_err(|err| Error::ReadHistoryEntryFailed { source: err })?
+                .path();
+            if !path.to_string_lossy().ends_with(".json.enc") {
+                continue;
+            }
+
+            let json = key.read(&path)?;
+            let entry: Self = serde_json::from_slice(&json)
+                .map_err(|err| Error::ParseHistoryEntryFailed { source: err })?;
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| b.id.cmp(&a.id));
+
+        Ok(entries)
+    }
+    fn audio(&self, key: &VaultKey) -> Result<Vec<f32>, Error> {
+        let path = history_dir()?.join(format!("{}.flac.enc", self.id));
+        let flac = key.read(&path)?;
+        let mut reader = claxon::FlacReader::new(Cursor::new(&flac[..]))
+            .map_err(|err| Error::DecodeRecordingFailed { source: err })?;
+        let mut audio = Vec::new();
+        for sample in reader.samples() {
+            let sample = sample.map_err(|err| Error::DecodeRecordingFailed { source: err })?;
+            audio.push(sample as f32 / i16::MAX as f32);
+        }
+
+        Ok(audio)
+    }
+    fn find_span(&self, text: &str) -> Option<(u64, u64)> {
+        let needle = normalize_whitespace(text);
+        if needle.is_empty() {
+            return None;
+        }
+
+        // Build up the normalised text of the whole transcript, keeping track of where each
+        // segment ends in it
+        let mut haystack = String::new();
+        let mut segment_ends = Vec::new();
+        for segment in &self.transcript.segments {
+            let segment_text = normalize_whitespace(&segment.text);
+            if !haystack.is_empty() && !segment_text.is_empty() {
+                haystack.push(' ');
+            }
+            haystack.push_str(&segment_text);
+            segment_ends.push(haystack.len());
+        }
+
+        let start = haystack.find(&needle)?;
+        let end = start + needle.len();
+        let first = segment_ends.iter().position(|seg_end| *seg_end > start)?;
+        let last = segment_ends.iter().position(|seg_end| *seg_end >= end)?;
+
+        Some((
+            self.transcript.segments[first].start_ms,
+            self.transcript.segments[last].end_ms,
+        ))
+    }
+}
+
+/// Plays back the retained recording behind the given text, which should be (part of) the
+/// result of some previous dictation. This will play every segment the text touches, and blocks
+/// until playback is complete.
+#[cfg(feature = "record")]
+pub fn play_recording_of(text: &str, key: &VaultKey) -> Result<(), Error> {
+    // Prefer more recent recordings if the same text appears in several
+    for entry in HistoryEntry::list(key)? {
+        if let Some((start_ms, end_ms)) = entry.find_span(text) {
+            let audio = entry.audio(key)?;
+            let ms_to_sample =
+                |ms: u64| ((ms * SAMPLE_RATE as u64 / 1000) as usize).min(audio.len());
+            return crate::playback::play(&audio[ms_to_sample(start_ms)..ms_to_sample(end_ms)]);
+        }
+    }
+
+    Err(Error::RecordingNotFound)
+}
+
+/// Gets the directory history entries are stored in, creating it if necessary.
+fn history_dir() -> Result<PathBuf, Error> {
+    let history_dir = crate::paths::data_dir()?.join("history");
+    std::fs::create_dir_all(&history_dir)
+        .map_err(|err| Error::CreateHistoryDirFailed { source: err })?;
+
+    Ok(history_dir)
+}
+
+/// Encodes the given mono f32 audio as 16-bit FLAC. That's more than enough fidelity to
+/// listen back to speech, and roughly a quarter of the size of what we record.
+fn encode_flac(audio: &[f32]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let samples = Zeroizing::new(
+        audio
+            .iter()
+            .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i32)
+            .collect::<Vec<_>>(),
+    );
+
+    // The default configuration is always valid
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .expect("default flac encoder configuration was invalid");
+    let source = flacenc::source::MemSource::from_samples(&samples, 1, 16, SAMPLE_RATE as usize);
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|err| Error::EncodeRecordingFailed { source: err })?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    // We're writing to memory, this can't fail
+    stream
+        .write(&mut sink)
+        .expect("failed to write flac stream to memory");
+
+    Ok(Zeroizing::new(sink.as_slice().to_vec()))
+}
+
+/// Collapses all runs of whitespace in the given text into single spaces, and trims it.
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,59 @@
+//! The engine behind Sotto: recording, transcription, and everything that's done with transcripts
+//! afterwards. None of this depends on Tauri, so the app, the CLI, and anything else that wants to
+//! transcribe speech locally can all build on it.
+//!
+//! The main entry points are:
+//!
+//! - [`model`], for finding (and, with the `download` feature, downloading) speech recognition
+//!   models;
+//! - [`transcribe::transcribe`], for turning audio into a [`Transcript`];
+//! - [`AppState`] (with the `record` feature), which manages dictations into a workspace of
+//!   documents, along with the user's settings and encrypted storage, the way the app does.
+//!
+//! Everything that can go wrong is an [`Error`].
+//!
+//! # Features
+//!
+//! - `record` (default): recording from and playing back to audio devices with `cpal`. Without
+//!   this, audio has to come from elsewhere (e.g. WAV files), and there's no [`AppState`].
+//! - `download` (default): downloading models from Sotto's model index with `reqwest`. Without
+//!   this, models have to be downloaded some other way first.
+//! - `output`: typing and pasting text into other apps, and copying it to the clipboard, with
+//!   `enigo` and `arboard` (the [`output`] module).
+//! - `export`: exporting text to files, including Word documents with `docx-rs` (the [`export`]
+//!   module, and [`AppState::export`]).
+//! - `diagnostics`: creating zipped diagnostic reports, and finding the logs that go in them (the
+//!   [`diagnostics`] module).
+
+pub mod alternatives;
+pub mod autosave;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+#[cfg(feature = "record")]
+pub mod dictate;
+pub mod document;
+pub mod errors;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod hallucination;
+pub mod history;
+pub mod model;
+pub mod normalize;
+#[cfg(feature = "output")]
+pub mod output;
+pub mod paths;
+#[cfg(feature = "record")]
+pub mod playback;
+pub mod punctuation;
+#[cfg(feature = "record")]
+pub mod record;
+pub mod settings;
+pub mod transcribe;
+pub mod vault;
+pub mod workspace;
+
+#[cfg(feature = "record")]
+pub use dictate::AppState;
+pub use errors::Error;
+pub use model::Model;
+pub use transcribe::Transcript;
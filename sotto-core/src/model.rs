@@ -0,0 +1,217 @@
+use crate::errors::Error;
+#[cfg(feature = "download")]
+use crate::errors::ModelIndexError;
+#[cfg(feature = "download")]
+use futures::stream::StreamExt;
+#[cfg(feature = "download")]
+use reqwest::Client;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "download")]
+use tokio::fs::File;
+#[cfg(feature = "download")]
+use tokio::io::AsyncWriteExt;
+use whisper_rs::WhisperContext;
+
+pub use sotto_types::Model;
+
+/// The URL of the public models index. This is maintained on a separate locked branch to prevent
+/// changes to model locations from breaking every Sotto installation.
+///
+/// Note to contributors: only @arctic-hen7 has the authority to unlock the `prod-index` branch
+/// to modify this index! Requests for changes should be made in issues, *not* PRs.
+#[cfg(feature = "download")]
+const INDEX_URL: &str =
+    "https://raw.githubusercontent.com/arctic-hen7/sotto/prod-index/models.json";
+
+/// A convenience function for core models that either gets them or downloads them without a
+/// prompt. This is intended for use with models whose presence is checked at startup.
+#[cfg(feature = "download")]
+pub async fn get_or_download(model: Model) -> Result<PathBuf, Error> {
+    if let Some(path) = find(model)? {
+        Ok(path)
+    } else {
+        download(model).await
+    }
+}
+
+/// Gets the path to the given model, or returns `Ok(None)` if it hasn't been downloaded yet.
+pub fn find(model: Model) -> Result<Option<PathBuf>, Error> {
+    let download_path = path(model)?;
+    if download_path.exists() {
+        Ok(Some(download_path))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Downloads the given model. This will *not* check for the model's existence first, and should
+/// only be called if you're sure the desired model doesn't exist!
+///
+/// The model is downloaded into a `.partial` file next to where it goes, and only moved into place
+/// once it's complete, so a download that's cut off is never mistaken for the model. That partial
+/// file is overwritten by the next attempt, so two downloads of the same model mustn't run at once
+/// ([`LoadedModels::prepare`] makes sure of this).
+#[cfg(feature = "download")]
+pub async fn download(model: Model) -> Result<PathBuf, Error> {
+    let client = Client::new();
+    // Get the model index first and resolve the URL for the model
+    let res = client
+        .get(INDEX_URL)
+        .send()
+        .await
+        .map_err(|err| Error::GetModelIndexFailed { source: err })?;
+    if !res.status().is_success() {
+        return Err(Error::GetModelIndexBadStatus {
+            status: res.status().into(),
+        });
+    }
+    let model_idx = res
+        .text()
+        .await
+        .map_err(|err| Error::GetModelIndexFailed { source: err })?;
+    // Represented in general terms to make future format changes eassy without needing to update
+    // every Sotto installation on the planet
+    let model_idx: HashMap<String, String> = serde_json::from_str(&model_idx)
+        .map_err(|err| ModelIndexError::ParseFailed { source: err })?;
+    // This is an error with the index, because it should support what's in a production app!
+    let model_key = identifier(model);
+    let model_url = model_idx
+        .get(model_key)
+        .ok_or(ModelIndexError::Incomplete {
+            missing_key: model_key,
+        })?;
+
+    let download_path = path(model)?;
+
+    // Download the file
+    tracing::info!(model = model_key, "downloading model");
+    let res = client
+        .get(model_url)
+        .send()
+        .await
+        .map_err(|err| Error::DownloadModelFailed { source: err })?;
+    if !res.status().is_success() {
+        return Err(Error::DownloadModelBadStatus {
+            status: res.status().into(),
+        });
+    }
+
+    // Stream the response into the partial file (it's a model, it will be big)
+    let partial_path = download_path.with_file_name(format!(".{model_key}.bin.partial"));
+    let mut file = File::create(&partial_path)
+        .await
+        .map_err(|err| Error::CreateModelFileFailed { source: err })?;
+    let mut body = res.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|err| Error::BadChunk { source: err })?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|err| Error::WriteChunkFailed { source: err })?;
+    }
+    // Make sure it's all on disk before it looks like the model, in case we're cut off now
+    file.sync_all()
+        .await
+        .map_err(|err| Error::WriteChunkFailed { source: err })?;
+    drop(file);
+    tokio::fs::rename(&partial_path, &download_path)
+        .await
+        .map_err(|err| Error::CreateModelFileFailed { source: err })?;
+    tracing::info!(model = model_key, path = %download_path.display(), "downloaded model");
+
+    Ok(download_path)
+}
+
+/// Gets the path the given model is (or would be) downloaded to, making sure the directory it's in
+/// exists.
+fn path(model: Model) -> Result<PathBuf, Error> {
+    let model_key = identifier(model);
+    Ok(crate::paths::models_dir()?.join(format!("{model_key}.bin")))
+}
+
+/// Gets the name this model goes by in the model index (and on disk).
+fn identifier(model: Model) -> &'static str {
+    match model {
+        Model::WhisperTiny => "whisper_tiny",
+        Model::WhisperBase => "whisper_base",
+        Model::WhisperSmall => "whisper_small",
+        Model::WhisperMedium => "whisper_medium",
+        Model::WhisperLarge => "whisper_large",
+    }
+}
+
+/// The models that have been loaded so far, each of which is loaded at most once. Loading a model
+/// is slow, so they're only loaded when they're first needed, and then kept until no profile uses
+/// them any more (see [`Self::retain`]).
+#[derive(Default)]
+pub struct LoadedModels {
+    /// A slot for each model that's been asked for, which holds its context once it's loaded.
+    /// Each slot has its own lock, so loading one model doesn't hold up getting any other.
+    contexts: Mutex<HashMap<Model, Arc<Mutex<Option<Arc<WhisperContext>>>>>>,
+    /// A lock for downloading each model, so that two things getting the same model ready at once
+    /// (e.g. startup and the user switching profiles) don't both download it.
+    #[cfg(feature = "download")]
+    downloads: Mutex<HashMap<Model, Arc<tokio::sync::Mutex<()>>>>,
+}
+impl LoadedModels {
+    /// Gets the Whisper context for the given model, loading it if it hasn't been already. This
+    /// blocks while the model is loaded, and will fail if it hasn't been downloaded.
+    pub fn get(&self, model: Model) -> Result<Arc<WhisperContext>, Error> {
+        let slot = self
+            .contexts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(model)
+            .or_default()
+            .clone();
+        // Holding this while loading means two dictations can't load the same model at once
+        let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ctx) = &*slot {
+            return Ok(ctx.clone());
+        }
+
+        let model_path = find(model)?.ok_or(Error::ModelNotDownloaded)?;
+        let ctx = Arc::new(crate::transcribe::load_context(&model_path)?);
+        *slot = Some(ctx.clone());
+        tracing::info!(?model, "loaded model");
+
+        Ok(ctx)
+    }
+    /// Forgets every model the given function says isn't needed any more, freeing the memory
+    /// they take up (which can be gigabytes for the bigger ones). Any dictation that's still using
+    /// one of them keeps it until it's done.
+    pub fn retain(&self, mut in_use: impl FnMut(Model) -> bool) {
+        self.contexts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|model, _| {
+                let keep = in_use(*model);
+                if !keep {
+                    tracing::info!(?model, "unloaded model");
+                }
+                keep
+            });
+    }
+    /// Gets the given model ready to transcribe with, downloading it first if necessary (and
+    /// possible), so the next dictation that uses it doesn't have to wait.
+    pub async fn prepare(self: &Arc<Self>, model: Model) -> Result<(), Error> {
+        #[cfg(feature = "download")]
+        {
+            let download = self
+                .downloads
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(model)
+                .or_default()
+                .clone();
+            // Whoever's waiting on someone else's download will find the model once it's done
+            let _download = download.lock().await;
+            get_or_download(model).await?;
+        }
+        let models = self.clone();
+        tokio::task::spawn_blocking(move || models.get(model).map(|_| ()))
+            .await
+            .map_err(|err| Error::DictationTaskPanicked { source: err })?
+    }
+}
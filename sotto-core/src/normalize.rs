@@ -0,0 +1,1179 @@
+use crate::settings::{Normalization, Spelling};
+use crate::transcribe::Transcript;
+
+/// Tidies up the text of the given transcript according to the user's normalization settings:
+/// writing numbers, currency, dates, times and units as figures, masking profanity, using
+/// consistent spelling, and fixing up capitalization. These rules only know English for now, so
+/// transcripts in any other language are left alone.
+///
+/// Each segment is normalized on its own, so the transcript will still line up with its audio
+/// (the cost of this is that a number split across two segments will stay split).
+pub fn apply(transcript: &mut Transcript, language: &str, settings: &Normalization) {
+    if language != "en" {
+        return;
+    }
+
+    // The first segment's left alone, since where it goes decides whether it starts a sentence
+    let mut after_sentence = false;
+    for segment in &mut transcript.segments {
+        segment.text = normalize(&segment.text, settings, after_sentence);
+        after_sentence = tokenize(&segment.text)
+            .0
+            .last()
+            .map_or(after_sentence, ends_sentence);
+    }
+}
+
+/// Runs all the rules the user has turned on over the given text, in the order that keeps them
+/// from stepping on each other (e.g. times before dates, so "eleven thirty pm" isn't taken for a
+/// year, and dates before plain numbers, so "twenty twenty four" doesn't become "20 24"). If the
+/// text carries on from the end of a sentence, `after_sentence` should be set, so its first word
+/// gets capitalized too.
+pub fn normalize(text: &str, settings: &Normalization, after_sentence: bool) -> String {
+    let mut text = text.to_string();
+    if settings.numbers {
+        text = times(&text);
+        text = dates(&text);
+        text = currency(&text);
+        text = units(&text);
+        text = numbers(&text);
+    }
+    if settings.mask_profanity {
+        text = mask_profanity(&text);
+    }
+    text = spelling(&text, settings.spelling);
+    if settings.sentence_case {
+        text = sentence_case(&text, after_sentence);
+    }
+    // The user's own casing always wins, even at the start of a sentence
+    casing(&text, &settings.casing)
+}
+
+/// Writes spoken dates with figures: "March fifth twenty twenty four" becomes "March 5, 2024",
+/// "the fifth of March" becomes "5 March", and years on their own like "nineteen ninety nine"
+/// become "1999".
+///
+/// A year on its own sounds just like a time ("eleven fifteen"), so we only believe one if it's in
+/// the last century or this one, or it comes straight after "in" or "year".
+pub fn dates(text: &str) -> String {
+    rewrite_after(text, |previous, words| {
+        // "March fifth", "March twenty twenty four", "March fifth twenty twenty four"
+        if let Some(month) = month_name(words[0]) {
+            if let Some((len, year)) = parse_year(&words[1..]) {
+                return Some((1 + len, format!("{} {}", month, year)));
+            }
+            // "May" and "march" are also common words, so we only believe a plain number after
+            // them if it's an ordinal
+            let allow_cardinal = !matches!(words[0], "may" | "march");
+            let (day_len, day) = parse_day(&words[1..], allow_cardinal)?;
+            let len = 1 + day_len;
+            return Some(match parse_year(&words[len..]) {
+                Some((year_len, year)) => (len + year_len, format!("{} {}, {}", month, day, year)),
+                None => (len, format!("{} {}", month, day)),
+            });
+        }
+        // "the fifth of March"
+        if words[0] == "the" {
+            let (day_len, day) = parse_day(&words[1..], false)?;
+            let len = 1 + day_len;
+            if words.get(len) != Some(&"of") {
+                return None;
+            }
+            let month = month_name(words.get(len + 1)?)?;
+            let len = len + 2;
+            return Some(match parse_year(&words[len..]) {
+                Some((year_len, year)) => (len + year_len, format!("{} {} {}", day, month, year)),
+                None => (len, format!("{} {}", day, month)),
+            });
+        }
+
+        let (len, year) = parse_year(words)?;
+        let century = year / 100;
+        if century != 19 && century != 20 && !matches!(previous, Some("in" | "year")) {
+            return None;
+        }
+        Some((len, year.to_string()))
+    })
+}
+
+/// Writes spoken times with figures: "three thirty pm" becomes "3:30 pm", "ten oh five a.m."
+/// becomes "10:05 am", and "three o'clock" becomes "3:00".
+pub fn times(text: &str) -> String {
+    rewrite(text, |words| {
+        let (len, hour) =
+            parse_integer(words).or_else(|| parse_digits(words[0]).map(|n| (1, n)))?;
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        if words.get(len) == Some(&"o'clock") {
+            return Some((len + 1, format!("{}:00", hour)));
+        }
+
+        let minutes = parse_minutes(&words[len..]);
+        let len = len + minutes.map_or(0, |(minutes_len, _)| minutes_len);
+        let meridiem = match *words.get(len)? {
+            "am" | "a.m" => "am",
+            "pm" | "p.m" => "pm",
+            _ => return None,
+        };
+        Some(match minutes {
+            Some((_, minutes)) => (len + 1, format!("{}:{:02} {}", hour, minutes, meridiem)),
+            None => (len + 1, format!("{} {}", hour, meridiem)),
+        })
+    })
+}
+
+/// Writes spoken amounts of money with symbols: "twenty five dollars" becomes "$25", and "three
+/// euros and fifty cents" becomes "€3.50".
+///
+/// "Pounds" are only treated as money if pence come after them, since they might well be a
+/// weight otherwise.
+pub fn currency(text: &str) -> String {
+    rewrite(text, |words| {
+        let amount = parse_number(words)?;
+        let (symbol, minor_units): (_, &[&str]) = match *words.get(amount.len)? {
+            "dollar" | "dollars" => ("$", &["cent", "cents"]),
+            "euro" | "euros" => ("€", &["cent", "cents"]),
+            "pound" | "pounds" => ("£", &["penny", "pence", "p"]),
+            _ => return None,
+        };
+        let mut len = amount.len + 1;
+        let mut text = amount.text;
+
+        // "...and fifty cents"
+        let minor = words
+            .get(len)
+            .filter(|word| **word == "and")
+            .and_then(|_| parse_number(&words[len + 1..]))
+            .filter(|minor| minor.value < 100 && !minor.text.contains('.'))
+            .filter(|minor| {
+                words
+                    .get(len + 1 + minor.len)
+                    .map_or(false, |word| minor_units.contains(word))
+            });
+        match minor {
+            Some(minor) if !text.contains('.') => {
+                text = format!("{}.{:02}", text, minor.value);
+                len += 2 + minor.len;
+            }
+            _ if symbol == "£" => return None,
+            _ => {}
+        }
+
+        Some((len, format!("{}{}", symbol, text)))
+    })
+}
+
+/// Writes spoken measurements with figures and standard abbreviations: "twenty five milligrams"
+/// becomes "25 mg", and "five percent" becomes "5%".
+pub fn units(text: &str) -> String {
+    rewrite(text, |words| {
+        let amount = parse_number(words)?;
+        let rest = &words[amount.len..];
+        let (unit_len, symbol) = UNITS.iter().find_map(|(phrase, symbol)| {
+            let phrase = phrase.split(' ').collect::<Vec<_>>();
+            rest.starts_with(&phrase).then_some((phrase.len(), *symbol))
+        })?;
+
+        let len = amount.len + unit_len;
+        // Percentages and temperatures are written right up against the number
+        if symbol.starts_with(['%', '°']) {
+            Some((len, format!("{}{}", amount.text, symbol)))
+        } else {
+            Some((len, format!("{} {}", amount.text, symbol)))
+        }
+    })
+}
+
+/// Writes spoken numbers with figures: "twenty five" becomes "25", "two point five" becomes
+/// "2.5", and "fifty thousand" becomes "50,000". Whole numbers under ten are left as words (as
+/// most style guides have it), since "one of them" reads better than "1 of them".
+pub fn numbers(text: &str) -> String {
+    rewrite(text, |words| {
+        // Numbers Whisper has already written as figures are fine as they are
+        if parse_digits(words[0]).is_some() {
+            return None;
+        }
+        let number = parse_number(words)?;
+        if number.value < 10 && !number.text.contains('.') {
+            return None;
+        }
+        Some((number.len, number.text))
+    })
+}
+
+/// Masks common profanity, keeping the first letter so the meaning is still clear (e.g. "s***").
+pub fn mask_profanity(text: &str) -> String {
+    map_words(text, |word| {
+        let lower = word.to_lowercase();
+        if !PROFANITY.contains(&lower.as_str()) {
+            return None;
+        }
+        let mut chars = word.chars();
+        let first = chars.next()?;
+        Some(std::iter::once(first).chain(chars.map(|_| '*')).collect())
+    })
+}
+
+/// Converts words with different British and American spellings to the given variety (e.g.
+/// "colour" to "color", or "organize" to "organise"). Any capitalization is kept.
+pub fn spelling(text: &str, spelling: Spelling) -> String {
+    if spelling == Spelling::Unchanged {
+        return text.to_string();
+    }
+
+    map_words(text, |word| {
+        let lower = word.to_lowercase();
+        let converted = WORD_SPELLINGS
+            .iter()
+            .find_map(|(american, british)| match spelling {
+                Spelling::British if lower == *american => Some(british.to_string()),
+                Spelling::American if lower == *british => Some(american.to_string()),
+                _ => None,
+            })
+            .or_else(|| convert_stem(&lower, OR_STEMS, OR_SUFFIXES, spelling))
+            .or_else(|| convert_stem(&lower, IZE_STEMS, IZE_SUFFIXES, spelling))
+            .or_else(|| convert_stem(&lower, YZE_STEMS, YZE_SUFFIXES, spelling))?;
+
+        Some(match_case(word, &converted))
+    })
+}
+
+/// Capitalizes the first word of every sentence in the given text after the first, and the first
+/// one too if `after_sentence` is set. Words are only ever capitalized here, never lowercased,
+/// and abbreviations like "e.g." aren't taken for the end of a sentence.
+pub fn sentence_case(text: &str, after_sentence: bool) -> String {
+    let (tokens, trailing) = tokenize(text);
+    let mut out = String::with_capacity(text.len());
+    let mut sentence_start = after_sentence;
+    for token in tokens {
+        out.push_str(token.space);
+        out.push_str(token.prefix);
+        if sentence_start && token.word.starts_with(char::is_lowercase) {
+            out.push_str(&crate::punctuation::capitalize(token.word));
+        } else {
+            out.push_str(token.word);
+        }
+        out.push_str(token.suffix);
+        // Punctuation on its own (like a dash) carries on whatever came before it
+        sentence_start = ends_sentence(&token) || (token.word.is_empty() && sentence_start);
+    }
+    out.push_str(trailing);
+
+    out
+}
+
+/// Writes each of the given terms exactly as it's given (e.g. "iPhone" or "GitHub Actions")
+/// wherever it appears, however it's been capitalized. Longer terms are matched first, so
+/// "GitHub Actions" wins over "GitHub".
+pub fn casing(text: &str, terms: &[String]) -> String {
+    let mut terms = terms
+        .iter()
+        .map(|term| trim_non_alphanumeric(term))
+        .filter(|term| !term.is_empty())
+        .map(|term| {
+            let words = term
+                .split_whitespace()
+                .map(|word| word.to_lowercase().replace('’', "'"))
+                .collect::<Vec<_>>();
+            (term, words)
+        })
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        return text.to_string();
+    }
+    terms.sort_by_key(|(_, words)| std::cmp::Reverse(words.len()));
+
+    rewrite(text, |words| {
+        terms.iter().find_map(|(term, term_words)| {
+            (words.len() >= term_words.len()
+                && words
+                    .iter()
+                    .zip(term_words)
+                    .all(|(word, term)| word == term))
+            .then(|| (term_words.len(), term.to_string()))
+        })
+    })
+}
+
+/// Whether the given token ends a sentence: whether it ends with a full stop, question mark or
+/// exclamation mark (maybe inside quotes or brackets), but isn't an abbreviation or an ellipsis.
+fn ends_sentence(token: &Token<'_>) -> bool {
+    let punctuation = if token.word.is_empty() {
+        token.prefix
+    } else {
+        token.suffix
+    };
+    let punctuation = punctuation.trim_end_matches(['"', '\'', ')', '”', '’']);
+    let abbreviation = ABBREVIATIONS.contains(&token.word.to_lowercase().as_str());
+    punctuation.ends_with(['.', '?', '!']) && !punctuation.ends_with("..") && !abbreviation
+}
+
+/// Trims anything that isn't part of a word (like punctuation) from around the given text.
+fn trim_non_alphanumeric(text: &str) -> &str {
+    text.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+/// Converts a word built from one of the given stems (American first, then British) and one of
+/// the given suffixes to the given spelling.
+fn convert_stem(
+    word: &str,
+    stems: &[(&str, &str)],
+    suffixes: &[&str],
+    spelling: Spelling,
+) -> Option<String> {
+    stems.iter().find_map(|(american, british)| {
+        let (from, to) = match spelling {
+            Spelling::British => (american, british),
+            Spelling::American => (british, american),
+            Spelling::Unchanged => return None,
+        };
+        let suffix = word.strip_prefix(from)?;
+        suffixes
+            .contains(&suffix)
+            .then(|| format!("{}{}", to, suffix))
+    })
+}
+
+/// Gives the replacement the same capitalization as the original word.
+fn match_case(original: &str, replacement: &str) -> String {
+    let letters = original
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect::<Vec<_>>();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        replacement.to_uppercase()
+    } else if original.starts_with(char::is_uppercase) {
+        crate::punctuation::capitalize(replacement)
+    } else {
+        replacement.to_string()
+    }
+}
+
+/// A word in some text, along with everything around it.
+struct Token<'a> {
+    /// The whitespace before this token.
+    space: &'a str,
+    /// Any punctuation before the word (e.g. an opening quote).
+    prefix: &'a str,
+    /// The word itself, as written.
+    word: &'a str,
+    /// Any punctuation after the word (e.g. a comma).
+    suffix: &'a str,
+}
+
+/// Splits the given text into tokens, also returning any whitespace at the end, so that the
+/// text can be put back together exactly as it was.
+fn tokenize(text: &str) -> (Vec<Token<'_>>, &str) {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        let start = match rest.find(|c: char| !c.is_whitespace()) {
+            Some(start) => start,
+            None => return (tokens, rest),
+        };
+        let space = &rest[..start];
+        rest = &rest[start..];
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let chunk = &rest[..end];
+        rest = &rest[end..];
+
+        let word_start = chunk.find(char::is_alphanumeric).unwrap_or(chunk.len());
+        let word_end = chunk
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_alphanumeric())
+            .map_or(word_start, |(i, c)| i + c.len_utf8());
+        tokens.push(Token {
+            space,
+            prefix: &chunk[..word_start],
+            word: &chunk[word_start..word_end],
+            suffix: &chunk[word_end..],
+        });
+    }
+}
+
+/// Rewrites the given text with the given rule, which is shown the (lowercased) words from each
+/// position onwards and can replace some number of them with something else. Rules only ever see
+/// runs of words that aren't broken up by punctuation or line breaks, and any punctuation around
+/// what they replace is kept.
+fn rewrite(text: &str, rule: impl Fn(&[&str]) -> Option<(usize, String)>) -> String {
+    rewrite_after(text, |_, words| rule(words))
+}
+
+/// Like [`rewrite`], but the rule is also shown the (lowercased) word just before each position,
+/// if it's part of the same run of words.
+fn rewrite_after(
+    text: &str,
+    rule: impl Fn(Option<&str>, &[&str]) -> Option<(usize, String)>,
+) -> String {
+    let (tokens, trailing) = tokenize(text);
+    // Curly apostrophes would stop us matching things like "o'clock"
+    let lower = tokens
+        .iter()
+        .map(|token| token.word.to_lowercase().replace('’', "'"))
+        .collect::<Vec<_>>();
+    let lower = lower.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let joined = |end: usize| {
+            tokens[end - 1].suffix.is_empty()
+                && tokens[end].prefix.is_empty()
+                && !tokens[end].word.is_empty()
+                && !tokens[end].space.contains('\n')
+        };
+        let mut end = i + 1;
+        while end < tokens.len() && joined(end) {
+            end += 1;
+        }
+        let previous = (i > 0 && joined(i)).then(|| lower[i - 1]);
+
+        out.push_str(token.space);
+        out.push_str(token.prefix);
+        let replacement = if token.word.is_empty() {
+            None
+        } else {
+            rule(previous, &lower[i..end]).filter(|(len, _)| *len > 0 && i + len <= end)
+        };
+        match replacement {
+            Some((len, replacement)) => {
+                out.push_str(&replacement);
+                out.push_str(tokens[i + len - 1].suffix);
+                i += len;
+            }
+            None => {
+                out.push_str(token.word);
+                out.push_str(token.suffix);
+                i += 1;
+            }
+        }
+    }
+    out.push_str(trailing);
+
+    out
+}
+
+/// Rewrites each word in the given text on its own with the given function, which is given the
+/// word as written (without any punctuation around it).
+fn map_words(text: &str, f: impl Fn(&str) -> Option<String>) -> String {
+    let (tokens, trailing) = tokenize(text);
+    let mut out = String::with_capacity(text.len());
+    for token in tokens {
+        out.push_str(token.space);
+        out.push_str(token.prefix);
+        match f(token.word) {
+            Some(word) => out.push_str(&word),
+            None => out.push_str(token.word),
+        }
+        out.push_str(token.suffix);
+    }
+    out.push_str(trailing);
+
+    out
+}
+
+/// A number we've found in some words.
+struct Number {
+    /// How many words it took up.
+    len: usize,
+    /// The number written with figures.
+    text: String,
+    /// The whole part of the number.
+    value: u64,
+}
+
+/// Parses a number from the start of the given words, whether it's spoken ("twenty five point
+/// five") or already written with figures.
+fn parse_number(words: &[&str]) -> Option<Number> {
+    let first = *words.first()?;
+    if let Some(value) = parse_digits(first) {
+        return Some(Number {
+            len: 1,
+            text: first.to_string(),
+            value,
+        });
+    }
+
+    // "point five" is a number too
+    let (len, value) = match parse_integer(words) {
+        Some((len, value)) => (len, value),
+        None if first == "point" => (0, 0),
+        None => return None,
+    };
+    let mut text = format_integer(value);
+    if words.get(len) == Some(&"point") {
+        let digits = words[len + 1..]
+            .iter()
+            .map_while(|word| match *word {
+                "oh" => Some(0),
+                word => unit_value(word),
+            })
+            .map(|digit| digit.to_string())
+            .collect::<String>();
+        if !digits.is_empty() {
+            text = format!("{}.{}", text, digits);
+            return Some(Number {
+                len: len + 1 + digits.len(),
+                text,
+                value,
+            });
+        }
+    }
+
+    (len > 0).then_some(Number { len, text, value })
+}
+
+/// Parses a whole number written with figures (possibly with commas or a decimal point), giving
+/// its whole part.
+fn parse_digits(word: &str) -> Option<u64> {
+    if !word.starts_with(|c: char| c.is_ascii_digit())
+        || !word
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+    {
+        return None;
+    }
+    let whole = word.split('.').next()?.replace(',', "");
+    whole.parse().ok()
+}
+
+/// The kinds of words spoken numbers are made of.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NumberWord {
+    Zero,
+    /// One to nine.
+    Unit(u64),
+    /// Ten to nineteen.
+    Teen(u64),
+    /// Twenty, thirty, etc.
+    Tens(u64),
+    /// Something like "twenty-five", written as one word.
+    Compound(u64),
+    Hundred,
+    /// Thousand, million or billion.
+    Scale(u64),
+    /// The "and" in "one hundred and five".
+    And,
+}
+
+/// Classifies a word as part of a spoken number, if it is one.
+fn number_word(word: &str) -> Option<NumberWord> {
+    Some(match word {
+        "zero" => NumberWord::Zero,
+        "hundred" => NumberWord::Hundred,
+        "thousand" => NumberWord::Scale(1_000),
+        "million" => NumberWord::Scale(1_000_000),
+        "billion" => NumberWord::Scale(1_000_000_000),
+        "and" => NumberWord::And,
+        word => {
+            if let Some(value) = unit_value(word).filter(|value| *value > 0) {
+                NumberWord::Unit(value)
+            } else if let Some(value) = teen_value(word) {
+                NumberWord::Teen(value)
+            } else if let Some(value) = tens_value(word) {
+                NumberWord::Tens(value)
+            } else {
+                let (tens, unit) = word.split_once('-')?;
+                let value = tens_value(tens)? + unit_value(unit).filter(|value| *value > 0)?;
+                NumberWord::Compound(value)
+            }
+        }
+    })
+}
+
+/// Parses a spoken whole number from the start of the given words, returning how many words it
+/// took up and its value. This stops as soon as the words stop making sense as one number, so
+/// "five six" is just five, and "twenty twenty" is just twenty.
+fn parse_integer(words: &[&str]) -> Option<(usize, u64)> {
+    use NumberWord::*;
+
+    let mut total = 0;
+    let mut current = 0;
+    let mut last = None;
+    let mut last_scale = u64::MAX;
+    let mut group_has_hundred = false;
+    let mut parsed = None;
+    for (i, word) in words.iter().enumerate() {
+        let next = match number_word(word) {
+            Some(next) => next,
+            None => break,
+        };
+        match (last, next) {
+            (None, Zero) => return Some((1, 0)),
+            (
+                None | Some(Hundred | Scale(_) | And),
+                Unit(value) | Teen(value) | Tens(value) | Compound(value),
+            ) => current += value,
+            (Some(Tens(_)), Unit(value)) => current += value,
+            (Some(Unit(_) | Teen(_) | Compound(_)), Hundred)
+                if current > 0 && current < 100 && !group_has_hundred =>
+            {
+                current *= 100;
+                group_has_hundred = true;
+            }
+            (Some(Unit(_) | Teen(_) | Tens(_) | Compound(_) | Hundred), Scale(scale))
+                if scale < last_scale && current > 0 && current < 1000 =>
+            {
+                total += current * scale;
+                current = 0;
+                last_scale = scale;
+                group_has_hundred = false;
+            }
+            (Some(Hundred | Scale(_)), And) => {}
+            _ => break,
+        }
+
+        last = Some(next);
+        // A trailing "and" isn't part of the number
+        if next != And {
+            parsed = Some((i + 1, total + current));
+        }
+    }
+
+    parsed
+}
+
+/// Parses the minutes of a spoken time ("thirty", "forty five", "oh five").
+fn parse_minutes(words: &[&str]) -> Option<(usize, u64)> {
+    if words.first() == Some(&"oh") {
+        let minutes = unit_value(words.get(1)?).filter(|value| *value > 0)?;
+        return Some((2, minutes));
+    }
+    if let Some(minutes) = words.first().and_then(|word| parse_digits(word)) {
+        return (words[0].len() == 2 && minutes < 60).then_some((1, minutes));
+    }
+    parse_integer(words).filter(|(_, minutes)| (10..60).contains(minutes))
+}
+
+/// Parses a spoken day of the month, which is either an ordinal ("fifth", "twenty first") or,
+/// if allowed, a plain number ("five").
+fn parse_day(words: &[&str], allow_cardinal: bool) -> Option<(usize, u64)> {
+    let first = *words.first()?;
+    let hyphenated = first.split_once('-').and_then(|(tens, unit)| {
+        Some(tens_value(tens)? + ordinal_value(unit).filter(|day| *day < 10)?)
+    });
+    let day = if let Some(day) = ordinal_value(first).or(hyphenated) {
+        Some((1, day))
+    } else if let (Some(tens), Some(unit)) = (
+        tens_value(first),
+        words.get(1).and_then(|word| ordinal_value(word)),
+    ) {
+        (unit < 10).then_some((2, tens + unit))
+    } else if allow_cardinal {
+        parse_integer(words)
+    } else {
+        None
+    };
+
+    day.filter(|(_, day)| (1..=31).contains(day))
+}
+
+/// Parses a year spoken the usual way, as two pairs of digits ("nineteen ninety nine",
+/// "twenty oh five", "nineteen hundred").
+fn parse_year(words: &[&str]) -> Option<(usize, u64)> {
+    let century = match number_word(words.first()?)? {
+        NumberWord::Teen(value) if value > 10 => value,
+        NumberWord::Tens(20) => 20,
+        _ => return None,
+    };
+    let rest = &words[1..];
+    if rest.first() == Some(&"oh") {
+        let year = unit_value(rest.get(1)?).filter(|value| *value > 0)?;
+        return Some((3, century * 100 + year));
+    }
+    let (len, year) = match number_word(rest.first()?)? {
+        NumberWord::Hundred => (1, 0),
+        NumberWord::Teen(_) | NumberWord::Tens(_) | NumberWord::Compound(_) => {
+            parse_integer(rest).filter(|(_, year)| *year < 100)?
+        }
+        _ => return None,
+    };
+
+    Some((1 + len, century * 100 + year))
+}
+
+/// Writes a whole number with figures, with commas between the thousands once it's big enough
+/// for that to help (but not before, so years come out right).
+fn format_integer(value: u64) -> String {
+    let digits = value.to_string();
+    if value < 10_000 {
+        return digits;
+    }
+
+    let mut out = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+fn unit_value(word: &str) -> Option<u64> {
+    Some(match word {
+        "zero" => 0,
+        "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        _ => return None,
+    })
+}
+fn teen_value(word: &str) -> Option<u64> {
+    Some(match word {
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        "thirteen" => 13,
+        "fourteen" => 14,
+        "fifteen" => 15,
+        "sixteen" => 16,
+        "seventeen" => 17,
+        "eighteen" => 18,
+        "nineteen" => 19,
+        _ => return None,
+    })
+}
+fn tens_value(word: &str) -> Option<u64> {
+    Some(match word {
+        "twenty" => 20,
+        "thirty" => 30,
+        "forty" => 40,
+        "fifty" => 50,
+        "sixty" => 60,
+        "seventy" => 70,
+        "eighty" => 80,
+        "ninety" => 90,
+        _ => return None,
+    })
+}
+fn ordinal_value(word: &str) -> Option<u64> {
+    Some(match word {
+        "first" => 1,
+        "second" => 2,
+        "third" => 3,
+        "fourth" => 4,
+        "fifth" => 5,
+        "sixth" => 6,
+        "seventh" => 7,
+        "eighth" => 8,
+        "ninth" => 9,
+        "tenth" => 10,
+        "eleventh" => 11,
+        "twelfth" => 12,
+        "thirteenth" => 13,
+        "fourteenth" => 14,
+        "fifteenth" => 15,
+        "sixteenth" => 16,
+        "seventeenth" => 17,
+        "eighteenth" => 18,
+        "nineteenth" => 19,
+        "twentieth" => 20,
+        "thirtieth" => 30,
+        _ => return None,
+    })
+}
+
+/// Gets the properly capitalized name of the given (lowercase) month.
+fn month_name(word: &str) -> Option<&'static str> {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    MONTHS
+        .iter()
+        .find(|month| month.to_lowercase() == word)
+        .copied()
+}
+
+/// Spoken units (which may be several words) and their abbreviations. Phrases that start with
+/// other phrases come first, so they win.
+const UNITS: &[(&str, &str)] = &[
+    ("per cent", "%"),
+    ("percent", "%"),
+    ("degrees celsius", "°C"),
+    ("degrees centigrade", "°C"),
+    ("degrees fahrenheit", "°F"),
+    ("kilometres per hour", "km/h"),
+    ("kilometers per hour", "km/h"),
+    ("miles per hour", "mph"),
+    ("micrograms", "mcg"),
+    ("microgram", "mcg"),
+    ("milligrams", "mg"),
+    ("milligram", "mg"),
+    ("mg", "mg"),
+    ("grams", "g"),
+    ("gram", "g"),
+    ("kilograms", "kg"),
+    ("kilogram", "kg"),
+    ("kilos", "kg"),
+    ("kilo", "kg"),
+    ("kg", "kg"),
+    ("millilitres", "mL"),
+    ("millilitre", "mL"),
+    ("milliliters", "mL"),
+    ("milliliter", "mL"),
+    ("ml", "mL"),
+    ("litres", "L"),
+    ("litre", "L"),
+    ("liters", "L"),
+    ("liter", "L"),
+    ("millimetres", "mm"),
+    ("millimetre", "mm"),
+    ("millimeters", "mm"),
+    ("millimeter", "mm"),
+    ("mm", "mm"),
+    ("centimetres", "cm"),
+    ("centimetre", "cm"),
+    ("centimeters", "cm"),
+    ("centimeter", "cm"),
+    ("cm", "cm"),
+    ("metres", "m"),
+    ("metre", "m"),
+    ("meters", "m"),
+    ("meter", "m"),
+    ("kilometres", "km"),
+    ("kilometre", "km"),
+    ("kilometers", "km"),
+    ("kilometer", "km"),
+    ("km", "km"),
+    ("kilobytes", "KB"),
+    ("megabytes", "MB"),
+    ("gigabytes", "GB"),
+    ("terabytes", "TB"),
+];
+
+/// Abbreviations that end in a full stop without ending the sentence (as they're tokenized,
+/// without their last full stop).
+const ABBREVIATIONS: &[&str] = &["e.g", "i.e", "vs", "cf", "mr", "mrs", "ms", "dr", "st"];
+
+/// Words that get masked by [`mask_profanity`].
+const PROFANITY: &[&str] = &[
+    "fuck",
+    "fucks",
+    "fucked",
+    "fucker",
+    "fuckers",
+    "fucking",
+    "motherfucker",
+    "motherfuckers",
+    "motherfucking",
+    "shit",
+    "shits",
+    "shitty",
+    "bullshit",
+    "bitch",
+    "bitches",
+    "bastard",
+    "bastards",
+    "asshole",
+    "assholes",
+    "arsehole",
+    "arseholes",
+    "cunt",
+    "cunts",
+    "dickhead",
+    "dickheads",
+    "wanker",
+    "wankers",
+    "twat",
+    "twats",
+];
+
+/// Whole words spelled differently in American and British English (American first).
+const WORD_SPELLINGS: &[(&str, &str)] = &[
+    ("center", "centre"),
+    ("centers", "centres"),
+    ("centered", "centred"),
+    ("theater", "theatre"),
+    ("theaters", "theatres"),
+    ("fiber", "fibre"),
+    ("fibers", "fibres"),
+    ("liter", "litre"),
+    ("liters", "litres"),
+    ("milliliter", "millilitre"),
+    ("milliliters", "millilitres"),
+    ("millimeter", "millimetre"),
+    ("millimeters", "millimetres"),
+    ("centimeter", "centimetre"),
+    ("centimeters", "centimetres"),
+    ("kilometer", "kilometre"),
+    ("kilometers", "kilometres"),
+    ("gray", "grey"),
+    ("defense", "defence"),
+    ("offense", "offence"),
+    ("catalog", "catalogue"),
+    ("catalogs", "catalogues"),
+    ("analog", "analogue"),
+    ("traveled", "travelled"),
+    ("traveling", "travelling"),
+    ("traveler", "traveller"),
+    ("travelers", "travellers"),
+    ("canceled", "cancelled"),
+    ("canceling", "cancelling"),
+    ("labeled", "labelled"),
+    ("labeling", "labelling"),
+    ("modeled", "modelled"),
+    ("modeling", "modelling"),
+    ("aluminum", "aluminium"),
+    ("judgment", "judgement"),
+    ("judgments", "judgements"),
+    ("aging", "ageing"),
+    ("jewelry", "jewellery"),
+    ("pajamas", "pyjamas"),
+    ("cozy", "cosy"),
+    ("skeptical", "sceptical"),
+    ("maneuver", "manoeuvre"),
+    ("maneuvers", "manoeuvres"),
+    ("mustache", "moustache"),
+    ("pediatric", "paediatric"),
+    ("pediatrics", "paediatrics"),
+    ("pediatrician", "paediatrician"),
+    ("anemia", "anaemia"),
+    ("anemic", "anaemic"),
+    ("anesthesia", "anaesthesia"),
+    ("anesthetic", "anaesthetic"),
+    ("hemoglobin", "haemoglobin"),
+    ("esophagus", "oesophagus"),
+    ("estrogen", "oestrogen"),
+    ("diarrhea", "diarrhoea"),
+    ("edema", "oedema"),
+    ("fetus", "foetus"),
+];
+
+/// Stems spelled "-or" in American English and "-our" in British English.
+const OR_STEMS: &[(&str, &str)] = &[
+    ("color", "colour"),
+    ("favor", "favour"),
+    ("honor", "honour"),
+    ("humor", "humour"),
+    ("labor", "labour"),
+    ("neighbor", "neighbour"),
+    ("behavior", "behaviour"),
+    ("flavor", "flavour"),
+    ("rumor", "rumour"),
+    ("harbor", "harbour"),
+    ("vapor", "vapour"),
+    ("tumor", "tumour"),
+];
+/// The endings [`OR_STEMS`] can take. Anything else (e.g. "humorous" or "humoral", which are the
+/// same everywhere) is left alone.
+const OR_SUFFIXES: &[&str] = &[
+    "", "s", "ed", "ing", "ful", "less", "able", "ably", "ite", "ites", "hood", "hoods",
+];
+
+/// Stems spelled "-ize" in American English and "-ise" in British English.
+const IZE_STEMS: &[(&str, &str)] = &[
+    ("organiz", "organis"),
+    ("realiz", "realis"),
+    ("recogniz", "recognis"),
+    ("apologiz", "apologis"),
+    ("prioritiz", "prioritis"),
+    ("minimiz", "minimis"),
+    ("maximiz", "maximis"),
+    ("emphasiz", "emphasis"),
+    ("summariz", "summaris"),
+    ("categoriz", "categoris"),
+    ("customiz", "customis"),
+    ("optimiz", "optimis"),
+    ("normaliz", "normalis"),
+    ("authoriz", "authoris"),
+    ("finaliz", "finalis"),
+    ("memoriz", "memoris"),
+    ("criticiz", "criticis"),
+    ("standardiz", "standardis"),
+    ("utiliz", "utilis"),
+];
+/// The endings [`IZE_STEMS`] can take. These must never be empty, since some stems are words in
+/// their own right (e.g. "emphasis").
+const IZE_SUFFIXES: &[&str] = &["e", "es", "ed", "ing", "er", "ers", "ation", "ations"];
+
+/// Stems spelled "-yze" in American English and "-yse" in British English.
+const YZE_STEMS: &[(&str, &str)] = &[("analyz", "analys"), ("paralyz", "paralys")];
+/// The endings [`YZE_STEMS`] can take. This is [`IZE_SUFFIXES`] without "-es", since "analyses"
+/// and "paralyses" are also the plurals of "analysis" and "paralysis" everywhere.
+const YZE_SUFFIXES: &[&str] = &["e", "ed", "ing", "er", "ers"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::Segment;
+
+    #[test]
+    fn times_are_written_with_figures() {
+        assert_eq!(times("meet at three thirty pm"), "meet at 3:30 pm");
+        assert_eq!(times("ten oh five a.m."), "10:05 am.");
+        assert_eq!(times("three o'clock"), "3:00");
+        assert_eq!(times("eleven pm"), "11 pm");
+    }
+
+    #[test]
+    fn numbers_without_a_meridiem_are_not_times() {
+        assert_eq!(times("I have three cats"), "I have three cats");
+        assert_eq!(times("thirteen thirty pm"), "thirteen thirty pm");
+    }
+
+    #[test]
+    fn dates_are_written_with_figures() {
+        assert_eq!(dates("March fifth twenty twenty four"), "March 5, 2024");
+        assert_eq!(dates("on the fifth of March"), "on 5 March");
+        assert_eq!(dates("June twenty"), "June 20");
+        assert_eq!(dates("back in nineteen ninety nine"), "back in 1999");
+    }
+
+    #[test]
+    fn may_and_march_need_an_ordinal() {
+        assert_eq!(dates("you may five times"), "you may five times");
+        assert_eq!(dates("May fifth"), "May 5");
+    }
+
+    #[test]
+    fn bare_years_need_context() {
+        assert_eq!(
+            dates("see you at eleven fifteen"),
+            "see you at eleven fifteen"
+        );
+        assert_eq!(dates("twelve thirty"), "twelve thirty");
+        assert_eq!(dates("built in eleven fifteen"), "built in 1115");
+        assert_eq!(dates("the year twelve hundred"), "the year 1200");
+        assert_eq!(dates("twenty twenty four"), "2024");
+    }
+
+    #[test]
+    fn numbers_are_written_with_figures() {
+        assert_eq!(numbers("twenty five people"), "25 people");
+        assert_eq!(numbers("two point five"), "2.5");
+        assert_eq!(numbers("fifty thousand"), "50,000");
+        assert_eq!(numbers("one hundred and five"), "105");
+        assert_eq!(numbers("one hundred and"), "100 and");
+    }
+
+    #[test]
+    fn small_numbers_stay_as_words() {
+        assert_eq!(numbers("one of them"), "one of them");
+        assert_eq!(numbers("already 42"), "already 42");
+    }
+
+    #[test]
+    fn numbers_stop_at_punctuation() {
+        assert_eq!(numbers("twenty, five"), "20, five");
+    }
+
+    #[test]
+    fn profanity_is_masked() {
+        assert_eq!(mask_profanity("What the fuck?"), "What the f***?");
+        assert_eq!(mask_profanity("Shit happens"), "S*** happens");
+        assert_eq!(mask_profanity("Scunthorpe"), "Scunthorpe");
+    }
+
+    #[test]
+    fn spelling_is_converted_to_british() {
+        assert_eq!(
+            spelling("The color of the organization", Spelling::British),
+            "The colour of the organisation"
+        );
+        assert_eq!(spelling("CENTER", Spelling::British), "CENTRE");
+        assert_eq!(spelling("analyzed", Spelling::British), "analysed");
+    }
+
+    #[test]
+    fn spelling_is_converted_to_american() {
+        assert_eq!(spelling("Colours", Spelling::American), "Colors");
+        assert_eq!(spelling("realised", Spelling::American), "realized");
+    }
+
+    #[test]
+    fn spelling_leaves_words_that_are_the_same_everywhere() {
+        assert_eq!(spelling("humorous", Spelling::British), "humorous");
+        assert_eq!(spelling("humoral", Spelling::British), "humoral");
+        assert_eq!(spelling("emphasis", Spelling::American), "emphasis");
+        assert_eq!(spelling("analyses", Spelling::American), "analyses");
+        assert_eq!(spelling("color", Spelling::Unchanged), "color");
+    }
+
+    #[test]
+    fn normalize_runs_rules_in_order() {
+        let settings = Normalization {
+            numbers: true,
+            mask_profanity: true,
+            spelling: Spelling::British,
+            sentence_case: true,
+            casing: vec!["GitHub".to_string()],
+        };
+        assert_eq!(
+            normalize(
+                "it's eleven thirty pm and the fucking color is gray. github says so",
+                &settings,
+                false
+            ),
+            "it's 11:30 pm and the f****** colour is grey. GitHub says so"
+        );
+    }
+
+    #[test]
+    fn sentences_start_with_capitals() {
+        assert_eq!(
+            sentence_case("done. then what? oh!", false),
+            "done. Then what? Oh!"
+        );
+        assert_eq!(sentence_case("\"stop.\" and go", false), "\"stop.\" And go");
+        assert_eq!(sentence_case("carry on", true), "Carry on");
+        assert_eq!(sentence_case("carry on", false), "carry on");
+    }
+
+    #[test]
+    fn abbreviations_do_not_end_sentences() {
+        assert_eq!(
+            sentence_case("fruit, e.g. apples. and so... on", false),
+            "fruit, e.g. apples. And so... on"
+        );
+        assert_eq!(sentence_case("ask dr. jones", false), "ask dr. jones");
+    }
+
+    #[test]
+    fn terms_keep_their_casing() {
+        let terms = ["iPhone".to_string(), "GitHub Actions".to_string()];
+        assert_eq!(
+            casing("My Iphone runs github actions.", &terms),
+            "My iPhone runs GitHub Actions."
+        );
+        assert_eq!(casing("IPHONES", &terms), "IPHONES");
+    }
+
+    #[test]
+    fn segments_carry_on_sentences() {
+        let mut transcript = Transcript {
+            segments: ["first one.", " second one", " third one."]
+                .iter()
+                .map(|text| Segment {
+                    text: text.to_string(),
+                    start_ms: 0,
+                    end_ms: 0,
+                    words: Vec::new(),
+                })
+                .collect(),
+            flagged: Vec::new(),
+        };
+        apply(&mut transcript, "en", &Normalization::default());
+        assert_eq!(transcript.text(), "first one. Second one third one.");
+    }
+}
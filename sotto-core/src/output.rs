@@ -0,0 +1,98 @@
+use crate::errors::Error;
+use crate::settings::OutputTarget;
+use arboard::Clipboard;
+use enigo::{Direction, Enigo, Key, Keyboard};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// How long to wait after pasting before restoring the user's clipboard, to give the target
+/// application a chance to actually read what we put there.
+const PASTE_RESTORE_DELAY: Duration = Duration::from_millis(300);
+
+/// Our connection to the clipboard, which is kept for as long as we're running. On Linux, the
+/// clipboard's contents are served by whoever put them there, so if we let go of this, anything we
+/// copied would vanish with it (unless there's a clipboard manager around to take it over).
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+/// Delivers the given transcript to the currently focused application, according to the given
+/// output target. This blocks while typing, so it should be called in a blocking task.
+pub fn deliver(text: &str, target: OutputTarget) -> Result<(), Error> {
+    match target {
+        OutputTarget::Sotto => Ok(()),
+        OutputTarget::Type => type_text(text),
+        OutputTarget::Paste => paste_text(text),
+    }
+}
+
+/// Copies the given text to the clipboard.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+    with_clipboard(|clipboard| clipboard.set_text(text))
+}
+
+/// Runs the given function with our clipboard, connecting to it first if we haven't yet.
+fn with_clipboard<T>(
+    f: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+) -> Result<T, Error> {
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(PoisonError::into_inner);
+    if clipboard.is_none() {
+        *clipboard =
+            Some(Clipboard::new().map_err(|err| Error::AccessClipboardFailed { source: err })?);
+    }
+    let clipboard = clipboard
+        .as_mut()
+        .expect("clipboard should have just been connected");
+
+    f(clipboard).map_err(|err| Error::AccessClipboardFailed { source: err })
+}
+
+/// Types the given text into the focused application, as if it were coming from a keyboard.
+/// On Linux, this uses XTest on X11 and the virtual keyboard protocol on Wayland.
+fn type_text(text: &str) -> Result<(), Error> {
+    let mut enigo = Enigo::new(&enigo::Settings::default())
+        .map_err(|err| Error::ConnectKeyboardFailed { source: err })?;
+    enigo
+        .text(text)
+        .map_err(|err| Error::TypeTextFailed { source: err })
+}
+
+/// Pastes the given text into the focused application through the clipboard. This is much
+/// faster than typing for long dictations, and copes better with unusual characters. Whatever
+/// text was on the clipboard before will be put back afterward.
+fn paste_text(text: &str) -> Result<(), Error> {
+    // If there was no text on the clipboard (e.g. an image), we have nothing to restore
+    let previous = with_clipboard(|clipboard| Ok(clipboard.get_text().ok()))?;
+    let pasted = with_clipboard(|clipboard| clipboard.set_text(text)).and_then(|_| press_paste());
+
+    // Put the user's clipboard back however far we got, and only wait for the target
+    // application if it might actually be reading from it
+    let restored = match previous {
+        Some(previous) => {
+            if pasted.is_ok() {
+                std::thread::sleep(PASTE_RESTORE_DELAY);
+            }
+            with_clipboard(|clipboard| clipboard.set_text(previous))
+        }
+        None => Ok(()),
+    };
+
+    pasted.and(restored)
+}
+
+/// Presses the platform's paste shortcut. The modifier is always released again, even if
+/// pressing the rest of the shortcut failed, so it can't get stuck down.
+fn press_paste() -> Result<(), Error> {
+    let mut enigo = Enigo::new(&enigo::Settings::default())
+        .map_err(|err| Error::ConnectKeyboardFailed { source: err })?;
+    #[cfg(target_os = "macos")]
+    let modifier = Key::Meta;
+    #[cfg(not(target_os = "macos"))]
+    let modifier = Key::Control;
+    let pressed = enigo
+        .key(modifier, Direction::Press)
+        .and_then(|_| enigo.key(Key::Unicode('v'), Direction::Click));
+    let released = enigo.key(modifier, Direction::Release);
+
+    pressed
+        .and(released)
+        .map_err(|err| Error::TypeTextFailed { source: err })
+}
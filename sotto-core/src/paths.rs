@@ -0,0 +1,202 @@
+//! Where Sotto keeps its files. By default, these follow the platform's conventions: on Linux,
+//! that's the XDG base directories (`~/.config/sotto` for settings, `~/.local/share/sotto` for
+//! models and the user's data, and `~/.cache/sotto` for logs), and on macOS and Windows, the
+//! usual application directories.
+//!
+//! If the `SOTTO_HOME` environment variable is set, everything goes in there instead, which is
+//! handy for portable installs and for tests that shouldn't touch the user's real files.
+//!
+//! Sotto used to keep everything in `~/.sotto`, so the first time any of these are asked for,
+//! whatever's still there is moved over.
+
+use crate::errors::Error;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use tempfile::NamedTempFile;
+
+/// The environment variable that overrides where all of Sotto's files go.
+pub const HOME_VAR: &str = "SOTTO_HOME";
+
+/// Whether or not we've checked for files left in `~/.sotto` yet.
+static MIGRATED: Mutex<bool> = Mutex::new(false);
+
+/// Gets the directory settings are kept in, creating it if necessary.
+pub fn config_dir() -> Result<PathBuf, Error> {
+    let dir = match home_override() {
+        Some(home) => home,
+        None => dirs::config_dir().ok_or(Error::NoHomeDir)?.join("sotto"),
+    };
+    create_dir(dir, |err| Error::CreateConfigDirFailed { source: err })
+}
+
+/// Gets the directory the user's data (their vault, history and documents) is kept in, creating
+/// it if necessary.
+pub fn data_dir() -> Result<PathBuf, Error> {
+    let dir = match home_override() {
+        Some(home) => home,
+        None => dirs::data_dir().ok_or(Error::NoHomeDir)?.join("sotto"),
+    };
+    create_dir(dir, |err| Error::CreateDataDirFailed { source: err })
+}
+
+/// Gets the directory things that can be thrown away (like logs) are kept in, creating it if
+/// necessary.
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    let dir = match home_override() {
+        Some(home) => home.join("cache"),
+        None => dirs::cache_dir().ok_or(Error::NoHomeDir)?.join("sotto"),
+    };
+    create_dir(dir, |err| Error::CreateCacheDirFailed { source: err })
+}
+
+/// Gets the directory speech recognition models are downloaded to, creating it if necessary.
+/// These are big, but they aren't kept in the cache, since downloading them again takes a while.
+pub fn models_dir() -> Result<PathBuf, Error> {
+    let dir = data_dir()?.join("models");
+    create_dir(dir, |err| Error::CreateDataDirFailed { source: err })
+}
+
+/// Writes the given contents to the given file without ever leaving it half-written: they go to a
+/// temporary file next to it first, which then replaces it in one go once it's safely on disk. If
+/// we crash partway through, the old file will still be there, untouched.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|err| err.error)?;
+
+    Ok(())
+}
+
+/// Gets the directory set by `SOTTO_HOME`, if there is one.
+fn home_override() -> Option<PathBuf> {
+    std::env::var_os(HOME_VAR)
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Creates the given directory if it doesn't exist yet, having first moved over anything left in
+/// `~/.sotto`.
+fn create_dir(
+    dir: PathBuf,
+    to_error: impl FnOnce(std::io::Error) -> Error,
+) -> Result<PathBuf, Error> {
+    migrate()?;
+    std::fs::create_dir_all(&dir).map_err(to_error)?;
+    Ok(dir)
+}
+
+/// Moves everything out of `~/.sotto` into the directories it belongs in now, and then deletes
+/// it. This only happens once per run, and never with `SOTTO_HOME` set (since that's not where
+/// the user's old files are meant to go). If it fails partway through, it'll pick up where it
+/// left off next time.
+fn migrate() -> Result<(), Error> {
+    let mut migrated = MIGRATED.lock().unwrap_or_else(PoisonError::into_inner);
+    if *migrated || home_override().is_some() {
+        return Ok(());
+    }
+    let old_dir = match dirs::home_dir() {
+        Some(home) => home.join(".sotto"),
+        None => return Ok(()),
+    };
+    if !old_dir.is_dir() {
+        *migrated = true;
+        return Ok(());
+    }
+    // Mark this as done first, since working out where things go uses the other directories
+    *migrated = true;
+    drop(migrated);
+
+    let res = move_old_files(&old_dir);
+    if res.is_err() {
+        // Let the next run try again
+        *MIGRATED.lock().unwrap_or_else(PoisonError::into_inner) = false;
+    }
+    res
+}
+
+/// Moves each file in the given old directory to wherever it belongs now, and then deletes the old
+/// directory if it's empty.
+fn move_old_files(old_dir: &Path) -> Result<(), Error> {
+    let entries =
+        std::fs::read_dir(old_dir).map_err(|err| Error::MigrateDataFailed { source: err })?;
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::MigrateDataFailed { source: err })?;
+        let name = entry.file_name();
+        let from = entry.path();
+        let to = if name == "settings.json" {
+            config_dir()?.join(&name)
+        } else if name == "logs" {
+            cache_dir()?.join(&name)
+        } else if from.extension().map_or(false, |ext| ext == "bin") {
+            models_dir()?.join(&name)
+        } else {
+            data_dir()?.join(&name)
+        };
+        // Whatever's already in the new place is newer, so it wins
+        if to.exists() {
+            continue;
+        }
+        move_path(&from, &to).map_err(|err| Error::MigrateDataFailed { source: err })?;
+        tracing::info!(from = %from.display(), to = %to.display(), "moved file out of ~/.sotto");
+    }
+    // Anything that was already in the new place is left behind, so keep it around to be safe
+    if let Err(err) = std::fs::remove_dir(old_dir) {
+        tracing::warn!(
+            ?err,
+            "couldn't remove ~/.sotto after moving files out of it"
+        );
+    }
+
+    Ok(())
+}
+
+/// Moves the given file or directory to the given path. Renaming won't work across filesystems,
+/// so this falls back to copying it to a temporary name next to the destination, renaming that
+/// into place once it's complete, and only then deleting the original. That way, if we're
+/// interrupted, the destination is either missing or a full copy, and never something half-copied
+/// that the next run would take as newer than the original.
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    let mut partial_name = OsString::from(".");
+    partial_name.push(to.file_name().unwrap_or_default());
+    partial_name.push(".partial");
+    let partial = to.with_file_name(partial_name);
+    // This could be left over from an earlier attempt that was interrupted
+    if partial.is_dir() {
+        std::fs::remove_dir_all(&partial)?;
+    } else if partial.exists() {
+        std::fs::remove_file(&partial)?;
+    }
+    copy_path(from, &partial)?;
+    std::fs::rename(&partial, to)?;
+
+    if from.is_dir() {
+        std::fs::remove_dir_all(from)
+    } else {
+        std::fs::remove_file(from)
+    }
+}
+
+/// Copies the given file or directory to the given path, making sure every file is on disk before
+/// this returns.
+fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to)?;
+        // Windows won't flush a file that's only open for reading
+        std::fs::OpenOptions::new().write(true).open(to)?.sync_all()
+    }
+}
@@ -0,0 +1,87 @@
+use crate::errors::Error;
+use crate::transcribe::SAMPLE_RATE;
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample, StreamConfig,
+};
+use std::sync::mpsc;
+use zeroize::Zeroizing;
+
+/// Plays the given audio (mono f32 at 16kHz) through the default output device, blocking
+/// until it's finished.
+///
+/// Hardly any output devices will play 16kHz mono directly, so the audio is resampled to
+/// whatever the device uses by default, and copied into every channel.
+pub fn play(audio: &[f32]) -> Result<(), Error> {
+    let host = cpal::default_host();
+    let output_device = host.default_output_device().ok_or(Error::NoOutputDevice)?;
+    let dflt_config = output_device
+        .default_output_config()
+        .map_err(|err| Error::GetDefaultOutputConfigFailed { source: err })?;
+    let config = StreamConfig {
+        channels: dflt_config.channels(),
+        sample_rate: dflt_config.sample_rate(),
+        buffer_size: cpal::BufferSize::Default,
+    };
+    let audio = crate::transcribe::resample(audio, SAMPLE_RATE, config.sample_rate.0);
+
+    // The callback will tell us when it's run out of audio to play
+    let (tx, rx) = mpsc::channel::<()>();
+    let output_stream = match dflt_config.sample_format() {
+        SampleFormat::I16 => build_stream::<i16>(&output_device, &config, audio, tx),
+        SampleFormat::U16 => build_stream::<u16>(&output_device, &config, audio, tx),
+        // Anything else can probably take floats, and they're what we have anyway
+        _ => build_stream::<f32>(&output_device, &config, audio, tx),
+    }?;
+
+    output_stream
+        .play()
+        .map_err(|err| Error::StartPlaybackFailed { source: err })?;
+
+    // If the sender has been dropped, the stream has died, so there's nothing left to wait for
+    let _ = rx.recv();
+    drop(output_stream);
+
+    Ok(())
+}
+
+/// Builds a stream that plays the given audio (mono, at the config's sample rate) on the given
+/// device in samples of type `T`, copying each sample into every channel. Once the audio's run
+/// out, the given sender will be notified.
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    audio: Zeroizing<Vec<f32>>,
+    tx: mpsc::Sender<()>,
+) -> Result<cpal::Stream, Error> {
+    let channels = config.channels.max(1) as usize;
+    let mut tx = Some(tx);
+    let mut position = 0;
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                for frame in data.chunks_mut(channels) {
+                    let sample = match audio.get(position) {
+                        Some(sample) => {
+                            position += 1;
+                            *sample
+                        }
+                        None => {
+                            if let Some(tx) = tx.take() {
+                                let _ = tx.send(());
+                            }
+                            0.0
+                        }
+                    };
+                    frame.fill(T::from_sample(sample));
+                }
+            },
+            |err| {
+                // Error callback
+                tracing::error!(?err, "error in audio output stream");
+            },
+            None,
+        )
+        .map_err(|err| Error::BuildOutputStreamFailed { source: err })
+}
@@ -0,0 +1,351 @@
+use crate::transcribe::Transcript;
+
+pub use sotto_types::{Formatting, SpokenCommand};
+
+/// Built-in single-word commands that are also ordinary words (e.g. "a period of two weeks" or
+/// "the colon was inflamed"), so they're only taken as commands when they're set off from the
+/// words on both sides of them by a pause.
+const AMBIGUOUS_COMMANDS: &[&str] = &["period", "colon", "point", "punkt", "punto", "coma"];
+
+/// Replaces spoken commands in the given transcript with the punctuation and formatting they
+/// stand for, using the built-in commands for the given language and any custom commands the user
+/// has defined for it (which take precedence). Built-in commands that are also ordinary words only
+/// count when the user pauses around them (see [`AMBIGUOUS_COMMANDS`]), since we'd otherwise mangle
+/// perfectly normal sentences. The user's own commands always count, since they chose them.
+///
+/// Each segment keeps its own text, so the transcript will still line up with its audio.
+pub fn apply(transcript: &mut Transcript, language: &str, custom_commands: &[SpokenCommand]) {
+    let mut commands = custom_commands
+        .iter()
+        .filter(|command| command.language == language)
+        .cloned()
+        .map(|command| (command, false))
+        .collect::<Vec<_>>();
+    commands.extend(builtin_commands(language).into_iter().map(|command| {
+        let ambiguous = AMBIGUOUS_COMMANDS.contains(&command.phrase.as_str());
+        (command, ambiguous)
+    }));
+    // Prepare the phrases for matching, trying the longest first so "full stop" beats "full"
+    let mut commands = commands
+        .into_iter()
+        .map(|(command, ambiguous)| {
+            let words = command
+                .phrase
+                .split_whitespace()
+                .map(normalize_word)
+                .collect::<Vec<_>>();
+            (words, command.formatting, ambiguous)
+        })
+        .filter(|(words, _, _)| !words.is_empty())
+        .collect::<Vec<_>>();
+    commands.sort_by(|(a, _, _), (b, _, _)| b.len().cmp(&a.len()));
+
+    // Commands can span segments, so we work on all the words at once, remembering which segment
+    // each came from
+    let words = transcript
+        .segments
+        .iter()
+        .enumerate()
+        .flat_map(|(i, segment)| segment.text.split_whitespace().map(move |word| (i, word)))
+        .collect::<Vec<_>>();
+    let normalized = words
+        .iter()
+        .map(|(_, word)| normalize_word(word))
+        .collect::<Vec<_>>();
+    // Whisper marks pauses with punctuation, and starts a new segment after a long one. It'll
+    // happily put a full stop after the word itself at the end of a sentence though ("surgery on
+    // my colon."), so that doesn't count, and the word has to end its segment instead
+    let set_off = |i: usize| {
+        let segment = words[i].0;
+        let after_pause = i == 0
+            || words[i - 1].0 != segment
+            || words[i - 1].1.ends_with(is_sentence_punctuation);
+        let before_pause = words.get(i + 1).map_or(true, |(next, _)| *next != segment);
+        after_pause && before_pause
+    };
+
+    let mut formatter = Formatter::new(transcript.segments.len());
+    let mut i = 0;
+    while i < words.len() {
+        let (segment, word) = words[i];
+        let command = commands.iter().find(|(phrase, _, ambiguous)| {
+            normalized
+                .get(i..i + phrase.len())
+                .map_or(false, |candidate| candidate == phrase.as_slice())
+                && (!ambiguous || set_off(i))
+        });
+        match command {
+            Some((phrase, formatting, _)) => {
+                formatter.format(segment, formatting);
+                i += phrase.len();
+            }
+            None => {
+                formatter.word(segment, word);
+                i += 1;
+            }
+        }
+    }
+
+    for (segment, text) in transcript.segments.iter_mut().zip(formatter.segments) {
+        segment.text = text;
+    }
+}
+
+/// Builds up the text of each segment as words and formatting are added.
+struct Formatter {
+    /// The text of each segment so far.
+    segments: Vec<String>,
+    /// The segment the last thing was added to, which is where trailing punctuation goes.
+    last_segment: usize,
+    /// Whether or not the next word should be joined to what came before without a space.
+    attach_next: bool,
+    /// Whether or not the next word should be capitalized.
+    capitalize_next: bool,
+}
+impl Formatter {
+    fn new(num_segments: usize) -> Self {
+        Self {
+            segments: vec![String::new(); num_segments],
+            last_segment: 0,
+            attach_next: false,
+            capitalize_next: false,
+        }
+    }
+    /// Adds an ordinary word to the given segment.
+    fn word(&mut self, segment: usize, word: &str) {
+        let out = &mut self.segments[segment];
+        if !self.attach_next {
+            // Whisper prefixes its segments with a space, so we do too
+            out.push(' ');
+        }
+        if self.capitalize_next {
+            out.push_str(&capitalize(word));
+        } else {
+            out.push_str(word);
+        }
+
+        self.last_segment = segment;
+        self.attach_next = false;
+        self.capitalize_next = false;
+    }
+    /// Adds the given formatting, spoken in the given segment.
+    fn format(&mut self, segment: usize, formatting: &Formatting) {
+        match formatting {
+            Formatting::Punctuation {
+                text,
+                ends_sentence,
+            } => {
+                let out = &mut self.segments[self.last_segment];
+                let trimmed_len = out.trim_end_matches(is_sentence_punctuation).len();
+                out.truncate(trimmed_len);
+                out.push_str(text);
+                self.attach_next = false;
+                self.capitalize_next |= *ends_sentence;
+                return;
+            }
+            Formatting::Opening(text) => {
+                let out = &mut self.segments[segment];
+                if !self.attach_next {
+                    out.push(' ');
+                }
+                out.push_str(text);
+            }
+            Formatting::NewLine => self.segments[segment].push('\n'),
+            Formatting::NewParagraph => {
+                self.segments[segment].push_str("\n\n");
+                self.capitalize_next = true;
+            }
+            Formatting::BulletPoint => {
+                self.segments[segment].push_str("\n- ");
+                self.capitalize_next = true;
+            }
+        }
+
+        self.last_segment = segment;
+        self.attach_next = true;
+    }
+}
+
+/// Normalizes a word for matching against command phrases, lowercasing it and stripping any
+/// surrounding punctuation.
+pub fn normalize_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Whether or not the given character is punctuation Whisper might have put at the end of a word.
+fn is_sentence_punctuation(c: char) -> bool {
+    matches!(c, '.' | ',' | '?' | '!' | ';' | ':')
+}
+
+/// Capitalizes the first letter of the given word.
+pub fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Gets the commands Sotto understands out of the box in the given language. Anything else can
+/// be added through custom commands.
+fn builtin_commands(language: &str) -> Vec<SpokenCommand> {
+    use Formatting::*;
+
+    let punctuation = |text: &str| Punctuation {
+        text: text.to_string(),
+        ends_sentence: false,
+    };
+    let sentence_end = |text: &str| Punctuation {
+        text: text.to_string(),
+        ends_sentence: true,
+    };
+    let opening = |text: &str| Opening(text.to_string());
+    let commands = match language {
+        "en" => vec![
+            ("new paragraph", NewParagraph),
+            ("new line", NewLine),
+            ("bullet point", BulletPoint),
+            ("full stop", sentence_end(".")),
+            ("period", sentence_end(".")),
+            ("question mark", sentence_end("?")),
+            ("exclamation mark", sentence_end("!")),
+            ("exclamation point", sentence_end("!")),
+            ("comma", punctuation(",")),
+            ("colon", punctuation(":")),
+            ("semicolon", punctuation(";")),
+            ("open quote", opening("\"")),
+            ("close quote", punctuation("\"")),
+            ("end quote", punctuation("\"")),
+            ("open bracket", opening("(")),
+            ("close bracket", punctuation(")")),
+        ],
+        "fr" => vec![
+            ("nouveau paragraphe", NewParagraph),
+            ("à la ligne", NewLine),
+            ("puce", BulletPoint),
+            ("point", sentence_end(".")),
+            ("point d'interrogation", sentence_end(" ?")),
+            ("point d'exclamation", sentence_end(" !")),
+            ("virgule", punctuation(",")),
+            ("deux points", punctuation(" :")),
+            ("point virgule", punctuation(" ;")),
+            ("ouvrez les guillemets", opening("« ")),
+            ("fermez les guillemets", punctuation(" »")),
+        ],
+        "de" => vec![
+            ("neuer absatz", NewParagraph),
+            ("neue zeile", NewLine),
+            ("aufzählungspunkt", BulletPoint),
+            ("punkt", sentence_end(".")),
+            ("fragezeichen", sentence_end("?")),
+            ("ausrufezeichen", sentence_end("!")),
+            ("komma", punctuation(",")),
+            ("doppelpunkt", punctuation(":")),
+            ("semikolon", punctuation(";")),
+            ("anführungszeichen auf", opening("„")),
+            ("anführungszeichen zu", punctuation("“")),
+        ],
+        "es" => vec![
+            ("nuevo párrafo", NewParagraph),
+            ("nueva línea", NewLine),
+            ("viñeta", BulletPoint),
+            ("punto", sentence_end(".")),
+            ("signo de interrogación", sentence_end("?")),
+            ("signo de exclamación", sentence_end("!")),
+            ("coma", punctuation(",")),
+            ("dos puntos", punctuation(":")),
+            ("punto y coma", punctuation(";")),
+            ("abrir comillas", opening("«")),
+            ("cerrar comillas", punctuation("»")),
+        ],
+        _ => Vec::new(),
+    };
+
+    commands
+        .into_iter()
+        .map(|(phrase, formatting)| SpokenCommand {
+            language: language.to_string(),
+            phrase: phrase.to_string(),
+            formatting,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::Segment;
+
+    /// Applies the built-in commands for the given language to a transcript with the given
+    /// segments, giving back its text.
+    fn tidy(language: &str, segments: &[&str]) -> String {
+        let mut transcript = Transcript {
+            segments: segments
+                .iter()
+                .map(|text| Segment {
+                    text: format!(" {text}"),
+                    start_ms: 0,
+                    end_ms: 0,
+                    words: Vec::new(),
+                })
+                .collect(),
+            flagged: Vec::new(),
+        };
+        apply(&mut transcript, language, &[]);
+        transcript.text().trim().to_string()
+    }
+
+    #[test]
+    fn commands_become_punctuation() {
+        assert_eq!(tidy("en", &["apples comma pears"]), "apples, pears");
+        assert_eq!(
+            tidy("en", &["the end full stop new paragraph next"]),
+            "the end.\n\nNext"
+        );
+    }
+
+    #[test]
+    fn ambiguous_words_mid_sentence_are_left_alone() {
+        assert_eq!(
+            tidy("en", &["it lasted a period of two weeks"]),
+            "it lasted a period of two weeks"
+        );
+        assert_eq!(
+            tidy("en", &["the colon was inflamed"]),
+            "the colon was inflamed"
+        );
+    }
+
+    #[test]
+    fn ambiguous_words_ending_sentences_are_left_alone() {
+        assert_eq!(
+            tidy("en", &["I had surgery on my colon."]),
+            "I had surgery on my colon."
+        );
+        assert_eq!(tidy("en", &["It lasted a period."]), "It lasted a period.");
+        assert_eq!(tidy("fr", &["C'est le point."]), "C'est le point.");
+    }
+
+    #[test]
+    fn ambiguous_words_need_a_pause_on_both_sides() {
+        assert_eq!(
+            tidy("en", &["Take two tablets, period", "then rest."]),
+            "Take two tablets. Then rest."
+        );
+        // A pause before isn't enough, even if Whisper punctuated the word itself
+        assert_eq!(
+            tidy("en", &["I said, colon, nothing more."]),
+            "I said, colon, nothing more."
+        );
+    }
+
+    #[test]
+    fn ambiguous_words_count_on_their_own() {
+        assert_eq!(
+            tidy("en", &["Take two tablets.", "Period."]),
+            "Take two tablets."
+        );
+        assert_eq!(tidy("en", &["Colon."]), ":");
+    }
+}
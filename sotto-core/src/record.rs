@@ -0,0 +1,199 @@
+use crate::autosave::RecoveryRecording;
+use crate::errors::Error;
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    StreamConfig,
+};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot::{error::TryRecvError, Receiver};
+use zeroize::Zeroizing;
+
+/// How often in-progress recordings are checkpointed for crash recovery.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Lists the names of the microphones that can be recorded from.
+pub fn list_input_devices() -> Result<Vec<String>, Error> {
+    let devices = cpal::default_host()
+        .input_devices()
+        .map_err(|err| Error::ListInputDevicesFailed { source: err })?;
+    // Devices we can't get the name of can't be chosen anyway
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+/// Records audio from the given microphone (or the default one) to a WAV file at the given path
+/// until a signal is received. If a recovery recording is given, the audio will be periodically
+/// checkpointed to it as well.
+///
+/// This should only be used when the recording needs to end up on disk anyway, prefer
+/// [`record_to_memory`] otherwise.
+pub fn start_recording(
+    path: &Path,
+    device: Option<&str>,
+    rx: Receiver<()>,
+    recovery: Option<&mut RecoveryRecording>,
+) -> Result<(), Error> {
+    let input_device = find_input_device(device)?;
+    let dflt_config = input_device
+        .default_input_config()
+        .map_err(|err| Error::GetDefaultInputConfigFailed { source: err })?;
+
+    // Initialize the WAV writer
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16_000,
+        bits_per_sample: (dflt_config.sample_format().sample_size() * 8) as u16,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)
+        .map_err(|err| Error::CreateWavWriterFailed { source: err })?;
+
+    record(input_device, rx, recovery, move |data| {
+        for sample in data {
+            if let Err(err) = writer.write_sample(*sample) {
+                tracing::error!(?err, "failed to write audio data to wav file");
+            }
+        }
+    })
+}
+
+/// Records audio from the given microphone (or the default one) into memory until a signal is
+/// received, never touching the disk (unless a recovery recording is given, which will be
+/// periodically checkpointed to). The returned buffer (and any intermediate buffers) will be
+/// zeroed when dropped.
+pub fn record_to_memory(
+    device: Option<&str>,
+    rx: Receiver<()>,
+    recovery: Option<&mut RecoveryRecording>,
+) -> Result<Zeroizing<Vec<f32>>, Error> {
+    let input_device = find_input_device(device)?;
+
+    // Start with room for a minute of audio, which should cover most dictations
+    let buffer = Arc::new(Mutex::new(Zeroizing::new(Vec::with_capacity(16_000 * 60))));
+    let callback_buffer = buffer.clone();
+    record(input_device, rx, recovery, move |data| {
+        extend_zeroizing(&mut callback_buffer.lock().unwrap(), data);
+    })?;
+
+    // The stream has been dropped, so we're the only ones holding the buffer now
+    let audio = std::mem::take(&mut **buffer.lock().unwrap());
+    Ok(Zeroizing::new(audio))
+}
+
+/// Finds the microphone with the given name, or the default one if there's no name. If the named
+/// microphone can't be found (e.g. it's been unplugged), the default will be used instead, since
+/// recording from something is better than failing.
+fn find_input_device(name: Option<&str>) -> Result<cpal::Device, Error> {
+    let host = cpal::default_host();
+    if let Some(name) = name {
+        let found = host.input_devices().ok().and_then(|mut devices| {
+            devices.find(|device| device.name().map_or(false, |device| device == name))
+        });
+        match found {
+            Some(device) => return Ok(device),
+            None => tracing::warn!(device = name, "chosen microphone not found, using default"),
+        }
+    }
+
+    host.default_input_device().ok_or(Error::NoInputDevice)
+}
+
+/// Records audio from the given device, passing it to the given callback, until a signal is
+/// received. If a recovery recording is given, the audio will be checkpointed to it every
+/// [`CHECKPOINT_INTERVAL`], and once more when the recording ends.
+fn record(
+    input_device: cpal::Device,
+    mut rx: Receiver<()>,
+    mut recovery: Option<&mut RecoveryRecording>,
+    mut on_data: impl FnMut(&[f32]) + Send + 'static,
+) -> Result<(), Error> {
+    let config = StreamConfig {
+        channels: 1,
+        sample_rate: cpal::SampleRate(16_000),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    // Audio that hasn't been checkpointed yet
+    let pending = Arc::new(Mutex::new(Zeroizing::new(Vec::new())));
+    let callback_pending = pending.clone();
+    let checkpointing = recovery.is_some();
+
+    // Initialize the CPAL audio input stream
+    let input_stream = input_device
+        .build_input_stream(
+            &config,
+            move |data: &[f32], _| {
+                // Callback function to receive audio data
+                on_data(data);
+                if checkpointing {
+                    extend_zeroizing(&mut callback_pending.lock().unwrap(), data);
+                }
+            },
+            |err| {
+                // Error callback
+                tracing::error!(?err, "error in audio input stream");
+            },
+            None,
+        )
+        .map_err(|err| Error::BuildInputStreamFailed { source: err })?;
+
+    // Start the audio stream
+    input_stream
+        .play()
+        .map_err(|err| Error::StartStreamFailed { source: err })?;
+    tracing::debug!(
+        device = ?input_device.name().ok(),
+        checkpointing,
+        "started recording"
+    );
+
+    // Wait for a signal from the receiver to stop recording.
+    // If this fails, that just implies the app has started to terminate, and we should halt
+    // anyway.
+    if let Some(recovery) = recovery.as_mut() {
+        // We need to wake up periodically to checkpoint
+        let mut last_checkpoint = Instant::now();
+        while let Err(TryRecvError::Empty) = rx.try_recv() {
+            std::thread::sleep(Duration::from_millis(50));
+            if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                checkpoint(recovery, &pending);
+                last_checkpoint = Instant::now();
+            }
+        }
+    } else {
+        let _ = rx.blocking_recv();
+    }
+
+    // Stop and close the audio stream
+    drop(input_stream);
+
+    // Get whatever's left into the recovery recording, in case we die while transcribing
+    if let Some(recovery) = recovery {
+        checkpoint(recovery, &pending);
+    }
+
+    Ok(())
+}
+
+/// Writes all pending audio to the given recovery recording.
+fn checkpoint(recovery: &mut RecoveryRecording, pending: &Mutex<Zeroizing<Vec<f32>>>) {
+    // Take the pending audio out so we don't hold up the audio callback while we encrypt it
+    let samples = std::mem::take(&mut *pending.lock().unwrap());
+    // Failing to checkpoint isn't worth stopping the recording over
+    if let Err(err) = recovery.append(&samples) {
+        tracing::warn!(?err, "failed to checkpoint recording for recovery");
+    }
+}
+
+/// Extends the given buffer with the given audio. If we let the `Vec` reallocate itself, it would
+/// leave the old allocation lying around in memory, so we grow it manually and zero the old one.
+fn extend_zeroizing(buffer: &mut Zeroizing<Vec<f32>>, data: &[f32]) {
+    if buffer.len() + data.len() > buffer.capacity() {
+        let mut grown = Vec::with_capacity((buffer.capacity() * 2).max(buffer.len() + data.len()));
+        grown.extend_from_slice(buffer);
+        // The old buffer is zeroed as it's dropped here
+        *buffer = Zeroizing::new(grown);
+    }
+    buffer.extend_from_slice(data);
+}
@@ -0,0 +1,183 @@
+use crate::errors::Error;
+use crate::transcribe::{Transcript, WhisperOptions};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+pub use sotto_types::{
+    Decoding, HotkeyMode, InsertionMode, Normalization, OutputTarget, Profile, Settings, Spelling,
+    DEFAULT_PROFILE, SETTINGS_VERSION,
+};
+
+/// Migrations from each version of the settings file to the next, starting with version 1. Each
+/// of these works on the raw JSON of the file, since it won't parse as [`Settings`] until it's
+/// been migrated. Whenever [`SETTINGS_VERSION`] is bumped, a migration must be added here.
+const MIGRATIONS: [fn(&mut Map<String, Value>); 2] = [migrate_v1, migrate_v2];
+
+/// The parts of [`Settings`] only the backend can do, like reading and writing them on disk.
+/// (The settings themselves are shared with the frontend, so they live in `sotto-types`.)
+pub trait SettingsExt: Sized {
+    /// Loads the user's settings, falling back to the defaults if they haven't saved any yet. If
+    /// the settings were saved by an older version of Sotto, they'll be migrated and saved again,
+    /// keeping a copy of the old file (e.g. `settings.v1.json`) in case the user goes back to it.
+    fn load() -> Result<Self, Error>;
+    /// Loads the user's settings as with [`Self::load`], but if they can't be understood (e.g. the
+    /// file's damaged, or from a newer version of Sotto), falls back to the defaults rather than
+    /// failing. The file is moved aside to `settings.invalid.json` first, so saving the defaults
+    /// doesn't destroy it, and the error is given back so the user can be told what happened.
+    fn load_or_default() -> Result<(Self, Option<Error>), Error>;
+    /// Saves these settings, overwriting whatever was there before.
+    fn save(&self) -> Result<(), Error>;
+    /// Checks that these settings make sense to save: that there's at least one profile, and that
+    /// every profile has a name no other profile has.
+    fn validate(&self) -> Result<(), Error>;
+    /// Tidies up the given transcript as the active profile asks, turning spoken commands into
+    /// punctuation and normalizing numbers, spelling and so on.
+    fn tidy(&self, transcript: &mut Transcript);
+    /// Gets the options Whisper should be run with for the active profile.
+    fn whisper_options(&self) -> WhisperOptions;
+}
+impl SettingsExt for Settings {
+    fn load() -> Result<Self, Error> {
+        let path = settings_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|err| Error::ReadSettingsFailed { source: err })?;
+        let mut raw: Value = serde_json::from_str(&contents)
+            .map_err(|err| Error::ParseSettingsFailed { source: err })?;
+        // Settings from before they were versioned don't say what version they are
+        let version = raw
+            .get("version")
+            .and_then(Value::as_u64)
+            .map_or(1, |version| version.max(1) as u32);
+        if version > SETTINGS_VERSION {
+            return Err(Error::SettingsTooNew { version });
+        }
+        if let Some(fields) = raw.as_object_mut() {
+            for migration in &MIGRATIONS[(version - 1) as usize..] {
+                migration(fields);
+            }
+            fields.insert("version".to_string(), SETTINGS_VERSION.into());
+        }
+        let mut settings: Self = serde_json::from_value(raw)
+            .map_err(|err| Error::ParseSettingsFailed { source: err })?;
+        // Everything else relies on there being an active profile, so make sure there is one
+        if settings.profiles.is_empty() {
+            settings.profiles.push(Profile::default());
+        }
+        if settings.find_profile(&settings.active_profile).is_none() {
+            settings.active_profile = settings.profiles[0].name.clone();
+        }
+
+        if version < SETTINGS_VERSION {
+            std::fs::copy(
+                &path,
+                path.with_file_name(format!("settings.v{version}.json")),
+            )
+            .map_err(|err| Error::WriteSettingsFailed { source: err })?;
+            settings.save()?;
+            tracing::info!(from = version, to = SETTINGS_VERSION, "migrated settings");
+        }
+
+        Ok(settings)
+    }
+    fn load_or_default() -> Result<(Self, Option<Error>), Error> {
+        match Self::load() {
+            Ok(settings) => Ok((settings, None)),
+            Err(err @ (Error::ParseSettingsFailed { .. } | Error::SettingsTooNew { .. })) => {
+                let path = settings_path()?;
+                std::fs::rename(&path, path.with_file_name("settings.invalid.json"))
+                    .map_err(|err| Error::WriteSettingsFailed { source: err })?;
+                tracing::warn!(?err, "failed to load settings, using the defaults instead");
+                Ok((Self::default(), Some(err)))
+            }
+            Err(err) => Err(err),
+        }
+    }
+    fn save(&self) -> Result<(), Error> {
+        let path = settings_path()?;
+        // This can't fail, we have no maps with non-string keys
+        let contents = serde_json::to_string_pretty(self).unwrap();
+        // If we're interrupted halfway through, the old settings are better than half of the new
+        crate::paths::write_atomic(&path, contents.as_bytes())
+            .map_err(|err| Error::WriteSettingsFailed { source: err })
+    }
+    fn validate(&self) -> Result<(), Error> {
+        if self.profiles.is_empty() {
+            return Err(Error::NoProfiles);
+        }
+        for (idx, profile) in self.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                return Err(Error::UnnamedProfile);
+            }
+            if self.profiles[..idx]
+                .iter()
+                .any(|other| other.name == profile.name)
+            {
+                return Err(Error::DuplicateProfile {
+                    name: profile.name.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+    fn tidy(&self, transcript: &mut Transcript) {
+        let profile = self.profile();
+        if profile.spoken_commands {
+            crate::punctuation::apply(transcript, &profile.language, &self.custom_commands);
+        }
+        crate::normalize::apply(transcript, &profile.language, &profile.normalization);
+    }
+    fn whisper_options(&self) -> WhisperOptions {
+        let profile = self.profile();
+        let dflt = WhisperOptions::default();
+        WhisperOptions {
+            language: profile.language.clone(),
+            threads: self
+                .threads
+                .filter(|threads| *threads > 0)
+                .unwrap_or(dflt.threads),
+            beam_size: profile.decoding.beam_size.max(1),
+            temperature: profile.decoding.temperature.max(0.0),
+            // Whisper takes the prompt as what came before, so a list of terms primes it for them
+            initial_prompt: Some(profile.glossary.join(", ")).filter(|prompt| !prompt.is_empty()),
+        }
+    }
+}
+
+/// Gets the path to the settings file, making sure the directory it's in exists.
+fn settings_path() -> Result<PathBuf, Error> {
+    Ok(crate::paths::config_dir()?.join("settings.json"))
+}
+
+/// Migrates settings from version 1, which is what they were before they were versioned. Those
+/// only lacked the transcription settings (the model, language, microphone and threads), which
+/// all have defaults, so there's nothing to change.
+fn migrate_v1(_fields: &mut Map<String, Value>) {}
+
+/// Migrates settings from version 2, before there were profiles. Everything about how dictations
+/// were transcribed and what happened to them afterwards moves into a single profile, which
+/// becomes the active one.
+fn migrate_v2(fields: &mut Map<String, Value>) {
+    let mut profile = Map::new();
+    profile.insert("name".to_string(), DEFAULT_PROFILE.into());
+    for key in [
+        "model",
+        "language",
+        "output_target",
+        "spoken_commands",
+        "normalization",
+    ] {
+        if let Some(value) = fields.remove(key) {
+            profile.insert(key.to_string(), value);
+        }
+    }
+    fields.insert(
+        "profiles".to_string(),
+        Value::Array(vec![Value::Object(profile)]),
+    );
+    fields.insert("active_profile".to_string(), DEFAULT_PROFILE.into());
+}
@@ -0,0 +1,294 @@
+use crate::errors::Error;
+use crate::hallucination::Candidate;
+use hound::{SampleFormat, WavReader};
+use std::io::Read;
+use std::path::Path;
+use whisper_rs::{FullParams, SamplingStrategy};
+
+pub use sotto_types::{
+    FlagReason, FlaggedSegment, Segment, Transcript, Word, DEFAULT_LANGUAGE, UNCERTAIN_CONFIDENCE,
+};
+pub use whisper_rs::WhisperContext;
+use zeroize::Zeroizing;
+
+/// The sample rate of all audio we record and transcribe (this is what Whisper expects).
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// How Whisper should be run, which comes from the user's settings.
+#[derive(Clone, Debug)]
+pub struct WhisperOptions {
+    /// The language to transcribe in (e.g. `en`).
+    pub language: String,
+    /// How many threads Whisper should use.
+    pub threads: usize,
+    /// How many candidates Whisper should weigh up at once when decoding, where 1 means it'll
+    /// just take the likeliest word each time.
+    pub beam_size: usize,
+    /// The temperature Whisper should start decoding at.
+    pub temperature: f32,
+    /// Text to give Whisper as if it came before the audio, which nudges it towards the words
+    /// in it (e.g. a glossary of terms it'd otherwise get wrong).
+    pub initial_prompt: Option<String>,
+}
+impl Default for WhisperOptions {
+    fn default() -> Self {
+        Self {
+            language: DEFAULT_LANGUAGE.to_string(),
+            // BUG M1 reports cores weirdly...
+            threads: num_cpus::get_physical(),
+            beam_size: 1,
+            temperature: 0.0,
+            initial_prompt: None,
+        }
+    }
+}
+
+/// Loads the speech recognition model at the given path, ready to transcribe with. This is slow, so
+/// the context should be kept around for as long as it's needed.
+pub fn load_context(model_path: &Path) -> Result<WhisperContext, Error> {
+    tracing::info!(path = %model_path.display(), "loading model");
+    WhisperContext::new(&model_path.to_string_lossy())
+        .map_err(|err| Error::LoadWhisperCtxFailed { source: err })
+}
+
+/// Reads the audio in the given file into memory. This assumes the file was written by
+/// [`crate::record::start_recording`], and therefore contains mono f32 audio at 16kHz. The
+/// returned buffer will be zeroed when it's dropped.
+pub fn read_audio(file: &Path) -> Result<Zeroizing<Vec<f32>>, Error> {
+    let mut reader =
+        WavReader::open(file).map_err(|err| Error::CreateWavReaderFailed { source: err })?;
+    // Allocate everything up front so we don't leave copies of the audio lying around in memory
+    let mut audio = Zeroizing::new(Vec::with_capacity(reader.len() as usize));
+    for sample in reader.samples::<f32>() {
+        let sample = sample.map_err(|err| Error::ParseSampleFailed { source: err })?;
+        audio.push(sample);
+    }
+
+    Ok(audio)
+}
+
+/// Reads any WAV file (e.g. one given to the CLI) into memory, converting it to the mono f32 audio
+/// at 16kHz that Whisper needs. The returned buffer will be zeroed when it's dropped.
+pub fn read_wav(file: &Path) -> Result<Zeroizing<Vec<f32>>, Error> {
+    let reader =
+        WavReader::open(file).map_err(|err| Error::CreateWavReaderFailed { source: err })?;
+    // If we can't tell how big the file is, we'll just have to trust its header
+    let file_len = std::fs::metadata(file).map_or(u64::MAX, |meta| meta.len());
+    convert_wav(reader, file_len)
+}
+
+/// Like [`read_wav`], but decodes the given WAV data (e.g. an uploaded file in memory).
+pub fn decode_wav(data: &[u8]) -> Result<Zeroizing<Vec<f32>>, Error> {
+    let reader =
+        WavReader::new(data).map_err(|err| Error::CreateWavReaderFailed { source: err })?;
+    convert_wav(reader, data.len() as u64)
+}
+
+/// Converts the audio from the given WAV reader, which is reading data of the given length, into
+/// mono f32 audio at 16kHz.
+fn convert_wav<R: Read>(
+    mut reader: WavReader<R>,
+    data_len: u64,
+) -> Result<Zeroizing<Vec<f32>>, Error> {
+    let spec = reader.spec();
+    // We allocate everything up front, since growing the buffer would leave copies of the audio
+    // lying around unzeroed. The header says how many samples there are, but it could say
+    // anything (e.g. in an upload), so we never believe it over how much data there actually is.
+    let bytes_per_sample = (spec.bits_per_sample as u64).div_ceil(8).max(1);
+    let len = (reader.len() as u64).min(data_len / bytes_per_sample);
+    let mut samples = Zeroizing::new(Vec::with_capacity(len as usize));
+    match spec.sample_format {
+        SampleFormat::Float => {
+            for sample in reader.samples::<f32>() {
+                let sample = sample.map_err(|err| Error::ParseSampleFailed { source: err })?;
+                samples.push(sample);
+            }
+        }
+        SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+            for sample in reader.samples::<i32>() {
+                let sample = sample.map_err(|err| Error::ParseSampleFailed { source: err })?;
+                samples.push(sample as f32 / scale);
+            }
+        }
+    }
+
+    // Average the channels down to mono (this knows its size up front, so it won't reallocate)
+    let channels = spec.channels.max(1) as usize;
+    let mono = Zeroizing::new(
+        samples
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(resample(&mono, spec.sample_rate, SAMPLE_RATE))
+}
+
+/// Resamples the given mono audio from one sample rate to another. This just interpolates
+/// linearly, which is plenty for speech.
+pub(crate) fn resample(audio: &[f32], from_rate: u32, to_rate: u32) -> Zeroizing<Vec<f32>> {
+    if from_rate == to_rate || audio.is_empty() {
+        return Zeroizing::new(audio.to_vec());
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let len = (audio.len() as f64 / ratio) as usize;
+    let mut resampled = Zeroizing::new(Vec::with_capacity(len));
+    for i in 0..len {
+        let position = i as f64 * ratio;
+        let idx = position as usize;
+        let frac = (position - idx as f64) as f32;
+        let current = audio[idx];
+        let next = audio.get(idx + 1).copied().unwrap_or(current);
+        resampled.push(current + (next - current) * frac);
+    }
+
+    resampled
+}
+
+/// Transcribes the given audio (mono f32 at 16kHz) into a [`Transcript`]. Any segments Whisper
+/// seems to have made up are flagged rather than included (see [`crate::hallucination::filter`]).
+///
+/// If asked to, this will also look for alternatives to any words Whisper wasn't sure about
+/// (see [`crate::alternatives::find`]), which takes a bit longer.
+pub fn transcribe(
+    audio: &[f32],
+    ctx: &WhisperContext,
+    options: &WhisperOptions,
+    find_alternatives: bool,
+) -> Result<Transcript, Error> {
+    let strategy = match options.beam_size {
+        0 | 1 => SamplingStrategy::Greedy { best_of: 0 },
+        beam_size => SamplingStrategy::BeamSearch {
+            beam_size: beam_size as i32,
+            patience: -1.0,
+        },
+    };
+    let candidates = run_whisper(audio, ctx, options, strategy, options.temperature)?;
+    let mut transcript = crate::hallucination::filter(candidates, audio);
+    if find_alternatives {
+        crate::alternatives::find(&mut transcript, audio, ctx, options);
+    }
+
+    Ok(transcript)
+}
+
+/// Gets the audio under the given segment.
+pub fn segment_audio<'a>(audio: &'a [f32], segment: &Segment) -> &'a [f32] {
+    let samples_per_ms = (SAMPLE_RATE / 1000) as u64;
+    let start = ((segment.start_ms * samples_per_ms) as usize).min(audio.len());
+    let end = ((segment.end_ms * samples_per_ms) as usize).clamp(start, audio.len());
+    &audio[start..end]
+}
+
+/// Runs Whisper over the given audio with the given options, sampling strategy and temperature,
+/// returning the segments it produced along with how confident it was in them. Above a temperature
+/// of zero, Whisper samples what it writes rather than always going with what it thinks is most
+/// likely.
+pub fn run_whisper(
+    audio: &[f32],
+    ctx: &WhisperContext,
+    options: &WhisperOptions,
+    strategy: SamplingStrategy,
+    temperature: f32,
+) -> Result<Vec<Candidate>, Error> {
+    // Create a state for this transcription run
+    let mut state = ctx
+        .create_state()
+        .map_err(|err| Error::CreateWhisperStateFailed { source: err })?;
+
+    // Sampling parameters for the model
+    let mut params = FullParams::new(strategy);
+    params.set_temperature(temperature);
+
+    params.set_n_threads(options.threads.max(1) as i32);
+    params.set_translate(false);
+    params.set_language(Some(&options.language));
+    if let Some(prompt) = &options.initial_prompt {
+        params.set_initial_prompt(prompt);
+    }
+    // Disable any printing to stdout (this is what we get for a wrapper over C++!)
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+
+    // Run the inference (this is blocking, and should be called in a blocking task)
+    state
+        .full(params, audio)
+        .map_err(|err| Error::WhisperRunFailed { source: err })?;
+
+    // Iterate through the segments of the transcript to extract the actual text
+    let num_segments = state
+        .full_n_segments()
+        .map_err(|err| Error::GetNumSegmentsFailed { source: err })?;
+    let eot = ctx.token_eot();
+    let mut candidates = Vec::new();
+    for i in 0..num_segments {
+        let text = state
+            .full_get_segment_text(i)
+            .map_err(|err| Error::GetSegmentTextFailed { source: err })?;
+        // Whisper gives us timestamps in centiseconds
+        let t0 = state
+            .full_get_segment_t0(i)
+            .map_err(|err| Error::GetSegmentTimestampFailed { source: err })?;
+        let t1 = state
+            .full_get_segment_t1(i)
+            .map_err(|err| Error::GetSegmentTimestampFailed { source: err })?;
+
+        // Work out how confident Whisper was in what it heard, word by word
+        let num_tokens = state
+            .full_n_tokens(i)
+            .map_err(|err| Error::GetSegmentTokensFailed { source: err })?;
+        let mut probabilities = Vec::new();
+        let mut words: Vec<Word> = Vec::new();
+        for j in 0..num_tokens {
+            let token = state
+                .full_get_token_data(i, j)
+                .map_err(|err| Error::GetSegmentTokensFailed { source: err })?;
+            // Special tokens (timestamps, end of text, etc.) come after the end of text token, and
+            // aren't part of what was said
+            if token.id >= eot {
+                continue;
+            }
+            probabilities.push(token.p);
+
+            // Tokens can split multi-byte characters, in which case they aren't valid UTF-8 on
+            // their own, and we just have to leave them out of the words
+            let piece = match state.full_get_token_text(i, j) {
+                Ok(piece) => piece,
+                Err(_) => continue,
+            };
+            // Tokens that start with a space start a new word, anything else is part of the last
+            match words.last_mut() {
+                Some(word) if !piece.starts_with(' ') => {
+                    word.text.push_str(&piece);
+                    word.confidence = word.confidence.min(token.p);
+                }
+                _ => words.push(Word {
+                    text: piece.trim_start().to_string(),
+                    confidence: token.p,
+                    alternatives: Vec::new(),
+                }),
+            }
+        }
+        let confidence = if probabilities.is_empty() {
+            1.0
+        } else {
+            probabilities.iter().sum::<f32>() / probabilities.len() as f32
+        };
+
+        candidates.push(Candidate {
+            segment: Segment {
+                text,
+                start_ms: t0.max(0) as u64 * 10,
+                end_ms: t1.max(0) as u64 * 10,
+                words,
+            },
+            confidence,
+        });
+    }
+
+    Ok(candidates)
+}
@@ -0,0 +1,200 @@
+use crate::errors::Error;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use zeroize::Zeroizing;
+
+pub use sotto_types::VaultStatus;
+
+/// The length of the salt used to derive the vault key from the user's passphrase.
+const SALT_LEN: usize = 16;
+/// The length of the nonces we prefix to each encrypted blob.
+const NONCE_LEN: usize = 24;
+/// A known plaintext encrypted into the vault file, which lets us tell whether or not the user
+/// has given us the right passphrase.
+const CHECK_PLAINTEXT: &[u8] = b"sotto-vault";
+
+/// The lock state of the vault that encrypts everything Sotto persists about the user's
+/// dictations (history, retained audio, etc.). Nothing sensitive is ever written to disk
+/// unencrypted: if the vault is locked, anything that needs to be persisted will fail.
+///
+/// The key is derived from a passphrase of the user's choosing with Argon2, and everything is
+/// encrypted with XChaCha20-Poly1305. The salt and a check value are kept in `vault` in Sotto's
+/// data directory (see [`crate::paths`]), which is created the first time the vault is unlocked.
+#[derive(Default)]
+pub struct Vault {
+    /// The key, if the vault is unlocked.
+    key: Option<VaultKey>,
+}
+impl Vault {
+    /// Whether or not the user has chosen a passphrase yet.
+    pub fn is_set_up() -> Result<bool, Error> {
+        Ok(vault_path()?.exists())
+    }
+    /// Whether or not the vault is currently unlocked.
+    pub fn is_unlocked(&self) -> bool {
+        self.key.is_some()
+    }
+    /// Unlocks the vault with the given key, which should come from [`Self::open`].
+    pub fn unlock(&mut self, key: VaultKey) {
+        self.key = Some(key);
+    }
+    /// Gets the key for the vault from the given passphrase, checking it's the right one. If the
+    /// vault hasn't been set up yet, this will set it up with this passphrase.
+    ///
+    /// This is deliberately slow (that's what makes passphrases hard to guess), so it shouldn't be
+    /// called anywhere that has to stay responsive, and it doesn't need the vault itself, so
+    /// nothing has to wait on it.
+    pub fn open(passphrase: &str) -> Result<VaultKey, Error> {
+        let path = vault_path()?;
+        if path.exists() {
+            let contents =
+                std::fs::read(&path).map_err(|err| Error::ReadVaultFailed { source: err })?;
+            if contents.len() < SALT_LEN {
+                return Err(Error::VaultCorrupted);
+            }
+            let (salt, check) = contents.split_at(SALT_LEN);
+            let key = VaultKey::derive(passphrase, salt)?;
+            // If this fails, the passphrase was wrong (or someone's been meddling)
+            match key.decrypt(check) {
+                Ok(plaintext) if plaintext.as_slice() == CHECK_PLAINTEXT => {
+                    tracing::info!("unlocked vault");
+                    Ok(key)
+                }
+                _ => {
+                    tracing::warn!("failed to unlock vault with incorrect passphrase");
+                    Err(Error::IncorrectPassphrase)
+                }
+            }
+        } else {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let key = VaultKey::derive(passphrase, &salt)?;
+
+            let mut contents = salt.to_vec();
+            contents.extend(key.encrypt(CHECK_PLAINTEXT)?);
+            crate::paths::write_atomic(&path, &contents)
+                .map_err(|err| Error::WriteVaultFailed { source: err })?;
+
+            tracing::info!("set up new vault");
+            Ok(key)
+        }
+    }
+    /// Locks the vault, forgetting the key (which will be zeroed in memory).
+    pub fn lock(&mut self) {
+        if self.key.take().is_some() {
+            tracing::info!("locked vault");
+        }
+    }
+    /// Gets a handle to the key, which can be used to read and write encrypted files. This
+    /// will fail if the vault is locked.
+    pub fn key(&self) -> Result<VaultKey, Error> {
+        self.key.clone().ok_or(Error::VaultLocked)
+    }
+}
+
+/// The key for the vault, which can be used to encrypt and decrypt data. This will be zeroed
+/// in memory when it's dropped.
+#[derive(Clone)]
+pub struct VaultKey(Zeroizing<[u8; 32]>);
+impl VaultKey {
+    /// Derives a key from the given passphrase and salt.
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self, Error> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+            .map_err(|_| Error::DeriveKeyFailed)?;
+
+        Ok(Self(key))
+    }
+    /// Encrypts the given data, returning the nonce followed by the ciphertext.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&*self.0));
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| Error::EncryptFailed)?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(data)
+    }
+    /// Decrypts data produced by [`Self::encrypt`].
+    pub fn decrypt(&self, data: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        if data.len() < NONCE_LEN {
+            return Err(Error::DecryptFailed);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&*self.0));
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::DecryptFailed)?;
+
+        Ok(Zeroizing::new(plaintext))
+    }
+    /// Encrypts the given data and writes it to the given file. This replaces the file in one go,
+    /// so a crash partway through can't leave it truncated (and so unreadable).
+    pub fn write(&self, path: &Path, plaintext: &[u8]) -> Result<(), Error> {
+        let data = self.encrypt(plaintext)?;
+        crate::paths::write_atomic(path, &data)
+            .map_err(|err| Error::WriteEncryptedFileFailed { source: err })
+    }
+    /// Reads the given file and decrypts it.
+    pub fn read(&self, path: &Path) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let data =
+            std::fs::read(path).map_err(|err| Error::ReadEncryptedFileFailed { source: err })?;
+        self.decrypt(&data)
+    }
+}
+
+/// A temporary file that will be overwritten with zeroes before it's deleted, so that what was
+/// in it (e.g. a raw recording) can't be trivially recovered from the disk afterward.
+///
+/// Note that this is a best effort: on SSDs and copy-on-write filesystems, the old blocks may
+/// well survive. Full-disk encryption is the only real answer there.
+pub struct ScratchFile(NamedTempFile);
+impl ScratchFile {
+    /// Creates a new scratch file in the system's temporary directory.
+    pub fn new() -> Result<Self, Error> {
+        NamedTempFile::new()
+            .map(Self)
+            .map_err(|err| Error::TmpFileCreationFailed { source: err })
+    }
+    /// Gets the path to this scratch file.
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+}
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        // We can't do anything useful with errors here, the file will be deleted regardless
+        let len = self
+            .0
+            .as_file()
+            .metadata()
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+        if let Ok(mut file) = std::fs::OpenOptions::new().write(true).open(self.0.path()) {
+            let zeroes = vec![0u8; 64 * 1024];
+            let mut remaining = len;
+            while remaining > 0 {
+                let chunk = remaining.min(zeroes.len() as u64) as usize;
+                if file.write_all(&zeroes[..chunk]).is_err() {
+                    break;
+                }
+                remaining -= chunk as u64;
+            }
+            let _ = file.sync_all();
+        }
+        // The `NamedTempFile` will now delete itself
+    }
+}
+
+/// Gets the path to the vault file, making sure the directory it's in exists.
+fn vault_path() -> Result<PathBuf, Error> {
+    Ok(crate::paths::data_dir()?.join("vault"))
+}
@@ -0,0 +1,284 @@
+use crate::document::{Document, UncertainWord};
+use crate::errors::Error;
+use crate::vault::VaultKey;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub use sotto_types::{DocumentSummary, WorkspaceSummary};
+
+/// The name given to new documents.
+const DEFAULT_NAME: &str = "Untitled";
+
+/// All the user's named documents, one of which is active (i.e. shown in Sotto, and where
+/// dictations go).
+///
+/// If the user has turned on autosaving, the workspace is stored in `documents` in Sotto's data
+/// directory (see [`crate::paths`]): an index of document names in `index.enc`, and the text of each document in `<id>.enc`. Like
+/// everything else we persist, these are encrypted with the vault key. Otherwise, documents only
+/// last as long as Sotto is open.
+pub struct Workspace {
+    /// The documents, in the order they were created.
+    documents: Vec<WorkspaceDocument>,
+    /// The ID of the active document.
+    active: u64,
+}
+
+/// A single named document in the [`Workspace`].
+struct WorkspaceDocument {
+    /// The unique identifier of this document, which is when it was created (in milliseconds
+    /// since the Unix epoch).
+    id: u64,
+    name: String,
+    /// The document itself, which keeps its own undo history.
+    document: Document,
+}
+
+/// The index of the workspace, as stored on disk.
+#[derive(Serialize, Deserialize)]
+struct WorkspaceIndex {
+    documents: Vec<DocumentSummary>,
+    active: u64,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        let id = new_id(&[]);
+        Self {
+            documents: vec![WorkspaceDocument {
+                id,
+                name: DEFAULT_NAME.to_string(),
+                document: Document::default(),
+            }],
+            active: id,
+        }
+    }
+}
+impl Workspace {
+    /// Gets the ID of the active document.
+    pub fn active_id(&self) -> u64 {
+        self.active
+    }
+    /// Gets the active document.
+    pub fn active(&mut self) -> &mut Document {
+        let active = self.active;
+        // We never let the active document be deleted
+        &mut self
+            .documents
+            .iter_mut()
+            .find(|doc| doc.id == active)
+            .expect("active document not in workspace")
+            .document
+    }
+    /// Gets an overview of the workspace.
+    pub fn summary(&self) -> WorkspaceSummary {
+        let active = self.find_ref(self.active);
+        WorkspaceSummary {
+            documents: self.index().documents,
+            active: self.active,
+            text: active
+                .map(|doc| doc.document.text().to_string())
+                .unwrap_or_default(),
+            uncertain: active
+                .map(|doc| doc.document.uncertain().to_vec())
+                .unwrap_or_default(),
+        }
+    }
+    /// Creates a new, empty document with the given name, and makes it active.
+    pub fn create(&mut self, name: &str) {
+        let ids = self.documents.iter().map(|doc| doc.id).collect::<Vec<_>>();
+        let id = new_id(&ids);
+        self.documents.push(WorkspaceDocument {
+            id,
+            name: clean_name(name),
+            document: Document::default(),
+        });
+        self.active = id;
+    }
+    /// Renames the given document.
+    pub fn rename(&mut self, id: u64, name: &str) -> Result<(), Error> {
+        self.find(id)?.name = clean_name(name);
+        Ok(())
+    }
+    /// Makes the given document active.
+    pub fn switch(&mut self, id: u64) -> Result<(), Error> {
+        self.find(id)?;
+        self.active = id;
+        Ok(())
+    }
+    /// Deletes the given document. If it was active, the most recently created document will
+    /// become active instead, and if it was the last document, a new empty one will be created.
+    pub fn delete(&mut self, id: u64) -> Result<(), Error> {
+        self.find(id)?;
+        self.documents.retain(|doc| doc.id != id);
+        if self.documents.is_empty() {
+            self.create(DEFAULT_NAME);
+        } else if self.active == id {
+            // This can't fail, we just checked it wasn't empty
+            self.active = self.documents.last().unwrap().id;
+        }
+
+        Ok(())
+    }
+    /// Adds the documents from the given workspace to this one, if they have anything in them.
+    /// This is used to make sure anything the user wrote before unlocking the vault isn't lost
+    /// when their saved workspace is loaded.
+    pub fn absorb(&mut self, other: Workspace) {
+        for doc in other.documents {
+            if doc.document.text().trim().is_empty() || self.find(doc.id).is_ok() {
+                continue;
+            }
+            self.documents.push(doc);
+        }
+    }
+    /// Loads the user's saved workspace, if there is one. If the user autosaved their work
+    /// before Sotto supported several documents, that will be imported as a single document.
+    pub fn load(key: &VaultKey) -> Result<Option<Self>, Error> {
+        let dir = documents_dir()?;
+        let index_path = dir.join("index.enc");
+        if !index_path.exists() {
+            return Ok(crate::autosave::load_document(key)?.map(|text| {
+                let mut workspace = Self::default();
+                workspace.active().set_text(text, None);
+                workspace
+            }));
+        }
+
+        let index = key.read(&index_path)?;
+        let index: WorkspaceIndex = serde_json::from_slice(&index)
+            .map_err(|err| Error::ParseWorkspaceFailed { source: err })?;
+        let mut documents = Vec::new();
+        for summary in index.documents {
+            let path = dir.join(format!("{}.enc", summary.id));
+            let text = if path.exists() {
+                let text = key.read(&path)?;
+                // We only ever write valid UTF-8, so anything else is corruption
+                String::from_utf8(text.to_vec()).map_err(|_| Error::DecryptFailed)?
+            } else {
+                String::new()
+            };
+            let mut document = Document::default();
+            document.set_text(text, None);
+            documents.push(WorkspaceDocument {
+                id: summary.id,
+                name: summary.name,
+                document,
+            });
+        }
+        if documents.is_empty() {
+            return Ok(None);
+        }
+        let active = if documents.iter().any(|doc| doc.id == index.active) {
+            index.active
+        } else {
+            documents[0].id
+        };
+
+        Ok(Some(Self { documents, active }))
+    }
+    /// Saves the whole workspace, deleting any documents that are no longer in it.
+    pub fn save(&self, key: &VaultKey) -> Result<(), Error> {
+        let dir = documents_dir()?;
+        self.save_index(key)?;
+        for doc in &self.documents {
+            key.write(
+                &dir.join(format!("{}.enc", doc.id)),
+                doc.document.text().as_bytes(),
+            )?;
+        }
+
+        for file in
+            std::fs::read_dir(&dir).map_err(|err| Error::ReadWorkspaceFailed { source: err })?
+        {
+            let path = file
+                .map_err(|err| Error::ReadWorkspaceFailed { source: err })?
+                .path();
+            let id = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".enc"))
+                .and_then(|id| id.parse::<u64>().ok());
+            if let Some(id) = id {
+                if self.find_ref(id).is_none() {
+                    std::fs::remove_file(&path)
+                        .map_err(|err| Error::WriteWorkspaceFailed { source: err })?;
+                }
+            }
+        }
+        // Anything left over from before we had a workspace is now in it
+        crate::autosave::delete_document()
+    }
+    /// Saves just the active document (and the index), which is all that changes as the user
+    /// writes.
+    pub fn save_active(&self, key: &VaultKey) -> Result<(), Error> {
+        let dir = documents_dir()?;
+        self.save_index(key)?;
+        if let Some(doc) = self.find_ref(self.active) {
+            key.write(
+                &dir.join(format!("{}.enc", doc.id)),
+                doc.document.text().as_bytes(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the index of document names.
+    fn save_index(&self, key: &VaultKey) -> Result<(), Error> {
+        // This can't fail, we have no maps with non-string keys
+        let index = serde_json::to_vec(&self.index()).unwrap();
+        key.write(&documents_dir()?.join("index.enc"), &index)
+    }
+    fn index(&self) -> WorkspaceIndex {
+        WorkspaceIndex {
+            documents: self
+                .documents
+                .iter()
+                .map(|doc| DocumentSummary {
+                    id: doc.id,
+                    name: doc.name.clone(),
+                })
+                .collect(),
+            active: self.active,
+        }
+    }
+    fn find(&mut self, id: u64) -> Result<&mut WorkspaceDocument, Error> {
+        self.documents
+            .iter_mut()
+            .find(|doc| doc.id == id)
+            .ok_or(Error::DocumentNotFound)
+    }
+    fn find_ref(&self, id: u64) -> Option<&WorkspaceDocument> {
+        self.documents.iter().find(|doc| doc.id == id)
+    }
+}
+
+/// Generates an ID for a new document, making sure it doesn't clash with any of the given ones.
+fn new_id(existing: &[u64]) -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_millis() as u64)
+        .unwrap_or(0);
+    // Two documents created in the same millisecond will just be one apart
+    let max = existing.iter().copied().max().unwrap_or(0);
+    now.max(max + 1)
+}
+
+/// Tidies up a document name the user has given us, falling back to the default if it's empty.
+fn clean_name(name: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() {
+        DEFAULT_NAME.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Gets the directory documents are stored in, creating it if necessary.
+fn documents_dir() -> Result<PathBuf, Error> {
+    let documents_dir = crate::paths::data_dir()?.join("documents");
+    std::fs::create_dir_all(&documents_dir)
+        .map_err(|err| Error::CreateDocumentsDirFailed { source: err })?;
+
+    Ok(documents_dir)
+}
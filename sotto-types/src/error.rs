@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An error from a Tauri command, in a form the frontend can both act on and show to the user.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CommandError {
+    /// What kind of error this was, which the frontend can match on.
+    pub code: ErrorCode,
+    /// A description of what went wrong, written for the user.
+    pub message: String,
+    /// The full technical details of the error (including whatever caused it), which are only
+    /// really useful in bug reports.
+    pub detail: String,
+    /// Whether or not the user can carry on after this error (e.g. by trying again). If not,
+    /// Sotto needs to be restarted.
+    pub recoverable: bool,
+}
+impl CommandError {
+    /// Creates a new error with the given code, which decides whether or not it's recoverable.
+    pub fn new(code: ErrorCode, message: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            detail: detail.into(),
+            recoverable: code.is_recoverable(),
+        }
+    }
+}
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+impl std::error::Error for CommandError {}
+
+/// The kinds of errors a Tauri command can fail with. These are broader than the backend's own
+/// errors, since the frontend only needs to know enough to decide what to do.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// A dictation was started while another one was still going.
+    AlreadyDictating,
+    /// A dictation was ended when there wasn't one going.
+    NotDictating,
+    /// The dictation was cancelled (e.g. over D-Bus), which usually isn't worth telling the user
+    /// about.
+    DictationCancelled,
+    /// There's no microphone to record from.
+    NoInputDevice,
+    /// There are no speakers or headphones to play recordings back on.
+    NoOutputDevice,
+    /// An audio device couldn't be used.
+    AudioDevice,
+    /// Recorded audio couldn't be captured or read back.
+    Recording,
+    /// Whisper failed to transcribe something.
+    Transcription,
+    /// The speech recognition model couldn't be found or loaded.
+    Model,
+    /// The speech recognition model couldn't be downloaded.
+    ModelDownload,
+    /// Something needs the vault, but it's locked.
+    VaultLocked,
+    /// The user gave the wrong passphrase for the vault.
+    IncorrectPassphrase,
+    /// The vault, or something encrypted with it, is corrupted.
+    VaultCorrupted,
+    /// Something couldn't be read from or written to disk.
+    Storage,
+    /// The settings file couldn't be parsed, so the defaults are being used instead.
+    InvalidSettings,
+    /// A set of dictation profiles couldn't be saved (e.g. two had the same name).
+    InvalidProfile,
+    /// The dictation shortcut couldn't be used.
+    InvalidHotkey,
+    /// Text couldn't be typed, pasted or copied.
+    Output,
+    /// An export couldn't be done (e.g. subtitles were asked for without timestamps).
+    Export,
+    /// Something the user asked for (e.g. a document, or a past recording) doesn't exist.
+    NotFound,
+    /// Something went wrong that shouldn't have (i.e. a bug).
+    Internal,
+}
+impl ErrorCode {
+    /// Whether or not the user can carry on after an error of this kind. For everything else, the
+    /// app is probably in a state we can't trust anymore.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            Self::AlreadyDictating
+            | Self::NotDictating
+            | Self::DictationCancelled
+            | Self::NoInputDevice
+            | Self::NoOutputDevice
+            | Self::AudioDevice
+            | Self::ModelDownload
+            | Self::VaultLocked
+            | Self::IncorrectPassphrase
+            | Self::InvalidSettings
+            | Self::InvalidProfile
+            | Self::InvalidHotkey
+            | Self::Output
+            | Self::Export
+            | Self::NotFound => true,
+            Self::Recording
+            | Self::Transcription
+            | Self::Model
+            | Self::VaultCorrupted
+            | Self::Storage
+            | Self::Internal => false,
+        }
+    }
+    /// A short, friendly explanation of what went wrong, for the top of the error screen.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::AlreadyDictating => "Sotto's already dictating",
+            Self::NotDictating => "Sotto isn't dictating",
+            Self::DictationCancelled => "Dictation cancelled",
+            Self::NoInputDevice => "No microphone found",
+            Self::NoOutputDevice => "No speakers or headphones found",
+            Self::AudioDevice => "Your audio device isn't working",
+            Self::Recording => "Your recording couldn't be processed",
+            Self::Transcription => "Your speech couldn't be transcribed",
+            Self::Model => "The speech recognition model couldn't be loaded",
+            Self::ModelDownload => "The speech recognition model couldn't be downloaded",
+            Self::VaultLocked => "Your recordings are locked",
+            Self::IncorrectPassphrase => "That passphrase isn't right",
+            Self::VaultCorrupted => "Your encrypted data is damaged",
+            Self::Storage => "Sotto couldn't read or save its files",
+            Self::InvalidSettings => "Your settings couldn't be loaded",
+            Self::InvalidProfile => "Those profiles can't be saved",
+            Self::InvalidHotkey => "That shortcut can't be used",
+            Self::Output => "Your text couldn't be typed or copied",
+            Self::Export => "That couldn't be exported",
+            Self::NotFound => "That couldn't be found",
+            Self::Internal => "Something went wrong inside Sotto",
+        }
+    }
+    /// What the user can do about an error like this.
+    pub fn remedy(&self) -> &'static str {
+        match self {
+            Self::AlreadyDictating => "Finish the dictation you've already started, then try again.",
+            Self::NotDictating => "Start a dictation first.",
+            Self::DictationCancelled => "Start a new dictation whenever you're ready.",
+            Self::NoInputDevice => "Plug in a microphone, then press Retry.",
+            Self::NoOutputDevice => "Plug in some speakers or headphones, then try again.",
+            Self::AudioDevice => "Check that your microphone or speakers are plugged in and not being used by another app, then try again.",
+            Self::Recording => "Restart Sotto and try again. If you'd turned on autosaving, what you said will be recovered.",
+            Self::Transcription => "Restart Sotto and try again. If this keeps happening, please report it, along with the details below.",
+            Self::Model => "Restart Sotto while you're connected to the internet, so the model can be downloaded again.",
+            Self::ModelDownload => "Check that you're connected to the internet, then try again.",
+            Self::VaultLocked => "Unlock your recordings with your passphrase, then try again.",
+            Self::IncorrectPassphrase => "Check your passphrase and try again.",
+            Self::VaultCorrupted => "Restart Sotto. If this keeps happening, your saved recordings may not be recoverable.",
+            Self::Storage => "Check that your disk isn't full and that Sotto can write to its folder, then restart Sotto.",
+            Self::InvalidSettings => "Sotto's using the default settings for now. Your old settings file has been kept as 'settings.invalid.json', in case you want to fix it (or update Sotto, if a newer version saved it).",
+            Self::InvalidProfile => "Give every profile a different name, and keep at least one profile.",
+            Self::InvalidHotkey => "Pick a different shortcut (something like 'Ctrl+Shift+Space').",
+            Self::Output => "Your text is still in Sotto, so you can copy it from there. Press Back to carry on.",
+            Self::Export => "Try exporting something else, or to a different place.",
+            Self::NotFound => "It may have been deleted. Press Back to carry on.",
+            Self::Internal => "Restart Sotto. If this keeps happening, please report it, along with the details below.",
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! The types Sotto's backend and frontend send to each other through Tauri commands. Both sides
+//! depend on this crate, so these can't drift out of sync, and it has to stay light enough to be
+//! compiled to Wasm (which means no dependencies beyond `serde`). Anything that needs more than
+//! that (like loading the settings from disk) lives in the backend.
+//!
+//! Most importantly, every command that can fail does so with a [`CommandError`], which tells the
+//! frontend what went wrong in a form it can both act on and show to the user.
+
+mod error;
+mod settings;
+mod status;
+mod transcript;
+mod workspace;
+
+pub use error::{CommandError, ErrorCode};
+pub use settings::{
+    Decoding, Formatting, HotkeyMode, InsertionMode, Model, Normalization, OutputTarget, Profile,
+    Settings, Spelling, SpokenCommand, DEFAULT_LANGUAGE, DEFAULT_PROFILE, SETTINGS_VERSION,
+};
+pub use status::{DictationStatus, VaultStatus};
+pub use transcript::{
+    ExportFormat, ExportSource, FlagReason, FlaggedSegment, HistoryEntry, Segment, Transcript,
+    Word, UNCERTAIN_CONFIDENCE,
+};
+pub use workspace::{DocumentSummary, DocumentUpdate, UncertainWord, WorkspaceSummary};
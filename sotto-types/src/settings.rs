@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+
+/// The current version of the settings file. This must be bumped whenever the settings change in
+/// a way older files can't just be read as (e.g. a field being renamed), with a migration added
+/// to the backend. New fields with defaults don't need a new version.
+pub const SETTINGS_VERSION: u32 = 3;
+/// The name of the profile everyone starts with.
+pub const DEFAULT_PROFILE: &str = "Default";
+/// The language we transcribe in, unless the user's chosen another.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// The user's settings for Sotto. These are stored in `settings.json` in the platform's config
+/// directory (e.g. `~/.config/sotto` on Linux), and are deliberately conservative by default
+/// (e.g. we don't keep any recordings unless the user explicitly asks us to).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// The version of the settings file these were loaded from, which will always be
+    /// [`SETTINGS_VERSION`] once they've been loaded.
+    pub version: u32,
+    /// The user's dictation profiles, each of which bundles up how to transcribe and what to do
+    /// with the result for a different kind of dictation (e.g. quick notes or medical letters).
+    /// There's always at least one.
+    pub profiles: Vec<Profile>,
+    /// The name of the profile new dictations use.
+    pub active_profile: String,
+    /// The name of the microphone to record from, or `None` for the system default. If the chosen
+    /// microphone isn't plugged in, the default will be used instead.
+    pub input_device: Option<String>,
+    /// How many threads Whisper should use, or `None` for one per physical core.
+    pub threads: Option<usize>,
+    /// Whether or not recordings should be kept after they've been transcribed, so the user
+    /// can listen back to them.
+    pub retain_recordings: bool,
+    /// Whether or not the working document (and any recording in progress) should be
+    /// periodically saved, so it can be recovered if Sotto crashes or is closed.
+    pub autosave: bool,
+    /// The global shortcut for dictating, which works even when Sotto isn't focused (e.g.
+    /// `Ctrl+Shift+Space`). If this is empty, there will be no global shortcut.
+    pub hotkey: String,
+    /// How the global shortcut behaves.
+    pub hotkey_mode: HotkeyMode,
+    /// Where new dictations go in the document.
+    pub insertion_mode: InsertionMode,
+    /// Whether or not finished transcripts should be copied to the clipboard automatically.
+    pub copy_on_finish: bool,
+    /// Extra spoken commands the user has defined, on top of the built-in ones for each language.
+    /// These apply in every profile that uses spoken commands.
+    pub custom_commands: Vec<SpokenCommand>,
+    /// Whether or not Whisper should be run again on anything it wasn't sure about, to suggest
+    /// alternatives for the words the user should check. This is off by default, since it means
+    /// running Whisper again after nearly every dictation, which makes transcription much slower.
+    pub suggest_alternatives: bool,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            profiles: vec![Profile::default()],
+            active_profile: DEFAULT_PROFILE.to_string(),
+            input_device: None,
+            threads: None,
+            retain_recordings: false,
+            autosave: false,
+            hotkey: "Ctrl+Shift+Space".to_string(),
+            hotkey_mode: HotkeyMode::Toggle,
+            insertion_mode: InsertionMode::Append,
+            copy_on_finish: false,
+            custom_commands: Vec::new(),
+            suggest_alternatives: false,
+        }
+    }
+}
+impl Settings {
+    /// Gets the profile with the given name, if there is one.
+    pub fn find_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+    /// Gets the active profile. If that's somehow gone missing, the first profile is used instead.
+    pub fn profile(&self) -> &Profile {
+        self.find_profile(&self.active_profile)
+            .or_else(|| self.profiles.first())
+            .expect("settings should always have at least one profile")
+    }
+    /// Gets the active profile mutably, as with [`Self::profile`].
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        let idx = self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == self.active_profile)
+            .unwrap_or(0);
+        &mut self.profiles[idx]
+    }
+}
+
+/// A named bundle of everything about how a dictation is transcribed and what happens to it
+/// afterwards, so the user can switch between setups for different tasks in one go.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Profile {
+    /// The name the user knows this profile by, which is unique among their profiles.
+    pub name: String,
+    /// A global shortcut that switches to this profile (e.g. `Ctrl+Shift+1`). If this is empty,
+    /// there's no shortcut for it.
+    pub hotkey: String,
+    /// The speech recognition model to transcribe with.
+    pub model: Model,
+    /// The language the user speaks in (e.g. `en`), which decides what Whisper listens for and
+    /// which spoken commands and tidying rules apply.
+    pub language: String,
+    /// How Whisper should decode what it hears.
+    pub decoding: Decoding,
+    /// Words and phrases Whisper should expect to hear (e.g. medical terms or people's names),
+    /// which makes it more likely to get them right.
+    pub glossary: Vec<String>,
+    /// Where finished transcripts should go, other than into Sotto itself.
+    pub output_target: OutputTarget,
+    /// Whether or not spoken commands like "comma" and "new paragraph" should be turned into
+    /// punctuation and formatting.
+    pub spoken_commands: bool,
+    /// How finished transcripts should be tidied up.
+    pub normalization: Normalization,
+}
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            hotkey: String::new(),
+            model: Model::WhisperBase,
+            language: DEFAULT_LANGUAGE.to_string(),
+            decoding: Decoding::default(),
+            glossary: Vec::new(),
+            output_target: OutputTarget::Sotto,
+            spoken_commands: true,
+            normalization: Normalization::default(),
+        }
+    }
+}
+
+/// How Whisper decodes what it hears into text.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct Decoding {
+    /// How many candidate transcriptions Whisper should weigh up at once. With 1, it just takes
+    /// the likeliest word each time, which is fastest; bigger beams are more accurate, but slower.
+    pub beam_size: usize,
+    /// How much randomness Whisper should use when choosing words, from 0 (none) upwards. Whisper
+    /// will still fall back to higher temperatures if it gets stuck.
+    pub temperature: f32,
+}
+impl Default for Decoding {
+    fn default() -> Self {
+        Self {
+            beam_size: 1,
+            temperature: 0.0,
+        }
+    }
+}
+
+/// The different kinds of models that can be downloaded in Sotto.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Model {
+    WhisperTiny,
+    WhisperBase,
+    WhisperSmall,
+    WhisperMedium,
+    WhisperLarge,
+    // TODO TTS models
+}
+
+/// The ways the global dictation shortcut can behave.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HotkeyMode {
+    /// Pressing the shortcut starts recording, and pressing it again stops.
+    Toggle,
+    /// Recording continues only while the shortcut is held down (push-to-talk).
+    Hold,
+}
+
+/// Where new dictations go in the working document.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InsertionMode {
+    /// Dictations are added to the end of the document as new paragraphs.
+    Append,
+    /// Dictations are inserted at the cursor, replacing whatever's selected.
+    Cursor,
+}
+
+/// The rules used to tidy up finished transcripts, which make up for Whisper being inconsistent
+/// about how it writes things (e.g. "twenty five mg" one time and "25 mg" the next).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Normalization {
+    /// Whether or not numbers, currency, dates, times and units should be written with figures
+    /// (e.g. "twenty five milligrams" becomes "25 mg").
+    pub numbers: bool,
+    /// Whether or not profanity should be masked.
+    pub mask_profanity: bool,
+    /// Which variety of English spelling should be used.
+    pub spelling: Spelling,
+    /// Whether or not the first word of every sentence should be capitalized, which Whisper
+    /// sometimes forgets (particularly after spoken punctuation).
+    pub sentence_case: bool,
+    /// Words and phrases that should always be written exactly as they are here (e.g. "iPhone" or
+    /// "GitHub"), however Whisper capitalizes them.
+    pub casing: Vec<String>,
+}
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            numbers: true,
+            mask_profanity: false,
+            spelling: Spelling::Unchanged,
+            sentence_case: true,
+            casing: Vec::new(),
+        }
+    }
+}
+
+/// The varieties of English spelling transcripts can be converted to.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Spelling {
+    /// Words are left however Whisper spelled them.
+    Unchanged,
+    /// British spelling (e.g. "colour", "organise").
+    British,
+    /// American spelling (e.g. "color", "organize").
+    American,
+}
+
+/// Where finished transcripts should go. They always go into Sotto's own document, but they can
+/// also be sent to whatever application is focused, which is particularly useful with the global
+/// dictation shortcut.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputTarget {
+    /// Transcripts only go into Sotto.
+    Sotto,
+    /// Transcripts are typed into the focused application, as if from a keyboard.
+    Type,
+    /// Transcripts are pasted into the focused application through the clipboard.
+    Paste,
+}
+
+/// A spoken command that should be turned into punctuation or formatting, like "full stop" or
+/// "new paragraph".
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpokenCommand {
+    /// The language this command is spoken in (e.g. `en`), which should match the language
+    /// Whisper is transcribing.
+    pub language: String,
+    /// What the user says. This is matched case-insensitively, ignoring any punctuation Whisper
+    /// adds around it.
+    pub phrase: String,
+    /// What the phrase becomes.
+    pub formatting: Formatting,
+}
+
+/// What a [`SpokenCommand`] turns into.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Formatting {
+    /// Punctuation that attaches to the end of the previous word (e.g. a comma or a closing
+    /// quote). If this ends a sentence, the next word will be capitalized. Any punctuation
+    /// Whisper put after the previous word is replaced, since the user knows better.
+    Punctuation { text: String, ends_sentence: bool },
+    /// Punctuation that attaches to the start of the next word (e.g. an opening quote).
+    Opening(String),
+    /// A line break.
+    NewLine,
+    /// A paragraph break.
+    NewParagraph,
+    /// A new bullet point, on its own line.
+    BulletPoint,
+}
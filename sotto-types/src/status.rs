@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// The status of dictation, as seen from outside the backend's state.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DictationStatus {
+    /// We're recording audio.
+    Recording,
+    /// We're transcribing audio that's been recorded.
+    Transcribing,
+    /// We're ready to start a new dictation.
+    Idle,
+}
+
+/// The status of the vault, which encrypts anything Sotto keeps on disk.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct VaultStatus {
+    /// Whether or not the user has chosen a passphrase yet.
+    pub set_up: bool,
+    /// Whether or not the vault is currently unlocked.
+    pub unlocked: bool,
+}
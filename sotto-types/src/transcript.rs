@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+/// Words Whisper is less confident than this in are highlighted for the user to check.
+pub const UNCERTAIN_CONFIDENCE: f32 = 0.5;
+
+/// A transcript of some audio, broken into the segments Whisper produced.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Transcript {
+    pub segments: Vec<Segment>,
+    /// Segments we think Whisper made up (e.g. from silence) or got stuck repeating, as Whisper
+    /// produced them. These aren't in `segments`, so they don't end up in the user's text unless
+    /// they ask for them.
+    #[serde(default)]
+    pub flagged: Vec<FlaggedSegment>,
+}
+impl Transcript {
+    /// Gets the full text of this transcript.
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<Vec<_>>()
+            .join("")
+    }
+}
+
+/// A single segment of a transcript, with its position in the source audio.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Segment {
+    /// The text of this segment. Whisper usually prefixes this with a space.
+    pub text: String,
+    /// The time at which this segment starts in the audio, in milliseconds.
+    pub start_ms: u64,
+    /// The time at which this segment ends in the audio, in milliseconds.
+    pub end_ms: u64,
+    /// The words Whisper heard in this segment, with how confident it was in each.
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+/// A word in a [`Segment`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Word {
+    /// The word as Whisper wrote it, including any punctuation attached to it. This is from before
+    /// any of our own punctuation or normalization rules were applied to the segment, so it might
+    /// not appear exactly like this in the segment's text.
+    pub text: String,
+    /// How confident Whisper was in this word, from 0 to 1. This is the probability of the least
+    /// likely token in the word, since one doubtful piece makes the whole word doubtful.
+    pub confidence: f32,
+    /// Other things that might have been said instead, if we looked for any.
+    #[serde(default)]
+    pub alternatives: Vec<String>,
+}
+impl Word {
+    /// Whether or not the user should check this word.
+    pub fn is_uncertain(&self) -> bool {
+        self.confidence < UNCERTAIN_CONFIDENCE
+    }
+}
+
+/// A segment that was left out of a [`Transcript`], and why.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FlaggedSegment {
+    pub segment: Segment,
+    pub reason: FlagReason,
+}
+
+/// The reasons a segment might be left out of a [`Transcript`].
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FlagReason {
+    /// The audio was (nearly) silent and Whisper wasn't confident, so this probably wasn't said.
+    NoSpeech,
+    /// This is a phrase Whisper often makes up (like "Thank you for watching."), and it wasn't
+    /// confident enough in it for us to believe it.
+    KnownHallucination,
+    /// This was the same as the segment before it, which is what Whisper getting stuck in a loop
+    /// looks like.
+    Repeated,
+    /// This had a phrase repeated over and over in it. Only one copy of the phrase was kept, and
+    /// this is the segment as it was before that.
+    Looped,
+    /// This was so repetitive that it's almost certainly Whisper stuck in a loop.
+    Repetitive,
+}
+impl FlagReason {
+    /// Explains this reason to the user.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::NoSpeech => "this was heard in silence",
+            Self::KnownHallucination => "this is something Sotto often imagines hearing",
+            Self::Repeated => "this repeated what came before it",
+            Self::Looped => "this had the same words over and over, so only one copy was kept",
+            Self::Repetitive => "this was very repetitive",
+        }
+    }
+}
+
+/// A past dictation whose recording has been retained, linked to the transcript produced from it.
+///
+/// Entries are only created if the user has opted into retaining their recordings. The backend
+/// keeps each one's transcript and audio encrypted with the vault key, so the vault has to be
+/// unlocked to do anything with them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    /// The unique identifier of this entry, which is the number of milliseconds since the Unix
+    /// epoch at which it was created.
+    pub id: u64,
+    /// The transcript of the recording.
+    pub transcript: Transcript,
+}
+impl HistoryEntry {
+    /// Gets a short preview of this entry's text, for showing in lists.
+    pub fn preview(&self) -> String {
+        let text = self.transcript.text();
+        let text = text.trim();
+        match text.char_indices().nth(40) {
+            Some((idx, _)) => format!("{}...", &text[..idx]),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// The formats text can be exported in.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    /// Plain text.
+    Txt,
+    /// Markdown, with each paragraph kept as a paragraph.
+    Markdown,
+    /// A Word document.
+    Docx,
+    /// SubRip subtitles. These need segment timestamps.
+    Srt,
+    /// WebVTT subtitles. These need segment timestamps.
+    Vtt,
+}
+impl ExportFormat {
+    /// Gets the file extension for this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Txt => "txt",
+            Self::Markdown => "md",
+            Self::Docx => "docx",
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+        }
+    }
+    /// Gets a human-readable name for this format, for the save dialog.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Txt => "Plain text",
+            Self::Markdown => "Markdown",
+            Self::Docx => "Word document",
+            Self::Srt => "SubRip subtitles",
+            Self::Vtt => "WebVTT subtitles",
+        }
+    }
+}
+
+/// What the user wants to export.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ExportSource {
+    /// The working document, as it currently appears in Sotto (including any edits the user has
+    /// made). This has no timestamps, so it can't be exported as subtitles.
+    Document(String),
+    /// The history entry with the given ID.
+    History(u64),
+}
@@ -0,0 +1,56 @@
+use crate::{CommandError, FlaggedSegment};
+use serde::{Deserialize, Serialize};
+
+/// A document's name and ID, without its text.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DocumentSummary {
+    pub id: u64,
+    pub name: String,
+}
+
+/// An overview of the user's documents.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkspaceSummary {
+    /// All the documents, in the order they were created.
+    pub documents: Vec<DocumentSummary>,
+    /// The ID of the active document.
+    pub active: u64,
+    /// The text of the active document.
+    pub text: String,
+    /// The words in the active document the user should check.
+    pub uncertain: Vec<UncertainWord>,
+}
+
+/// A word in a document that Whisper wasn't sure about, so the user should check it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct UncertainWord {
+    /// Where the word starts in the text, as a byte offset.
+    pub start: usize,
+    /// Where the word ends in the text, as a byte offset.
+    pub end: usize,
+    /// How confident Whisper was in the word, from 0 to 1.
+    pub confidence: f32,
+    /// Other things that might have been said instead.
+    pub alternatives: Vec<String>,
+}
+
+/// The result of applying a dictation to the working document.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DocumentUpdate {
+    /// The full text of the document after the dictation.
+    pub text: String,
+    /// The new text that was inserted, without any voice commands. This will be empty if the
+    /// dictation was only a command.
+    pub inserted: String,
+    /// Where the cursor should go after the dictation, as a byte offset into the text.
+    pub cursor: usize,
+    /// Anything that was left out of the dictation because we think Whisper made it up. The
+    /// document knows nothing about this, so it's filled in by whoever has the transcript.
+    pub flagged: Vec<FlaggedSegment>,
+    /// All the words in the document the user should check.
+    pub uncertain: Vec<UncertainWord>,
+    /// Why the new text couldn't be typed, pasted or copied into another app, if it couldn't be.
+    /// The text is in the document either way, so this shouldn't stop it being shown.
+    #[serde(default)]
+    pub output_error: Option<CommandError>,
+}
@@ -0,0 +1,118 @@
+//! Sotto's D-Bus service on Linux, which lets other apps and scripts (e.g. window manager
+//! keybindings) control dictation, and hear about what it produces. The interface is
+//! `org.sotto.Dictation`, at `/org/sotto/Dictation` on the session bus, under the well-known name
+//! `org.sotto.Dictation`. For instance:
+//!
+//! ```sh
+//! busctl --user call org.sotto.Dictation /org/sotto/Dictation org.sotto.Dictation StartDictation
+//! busctl --user call org.sotto.Dictation /org/sotto/Dictation org.sotto.Dictation EndRecording
+//! ```
+
+use crate::dictate::{AppState, DictationStatus};
+use crate::document::DocumentUpdate;
+use crate::errors::AppError;
+use sotto_core::Error;
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+use zbus::{dbus_interface, fdo, Connection, ConnectionBuilder, SignalContext};
+
+/// The well-known name we take on the session bus.
+const NAME: &str = "org.sotto.Dictation";
+/// The path of the dictation object.
+const PATH: &str = "/org/sotto/Dictation";
+
+/// The `org.sotto.Dictation` interface, which mirrors the dictation commands the frontend has.
+struct Dictation {
+    app: AppHandle,
+}
+#[dbus_interface(name = "org.sotto.Dictation")]
+impl Dictation {
+    /// Starts recording a new dictation, which will be inserted into the active document when
+    /// it's finished (and typed or pasted elsewhere, if the user's asked for that).
+    /// `TranscriptionFinished` will be emitted with the result.
+    fn start_dictation(&self) -> fdo::Result<()> {
+        crate::dictate_in_background(&self.app).map_err(to_fdo)
+    }
+    /// Stops recording, and starts transcribing what's been recorded.
+    async fn end_recording(&self) -> fdo::Result<()> {
+        let state = self.app.state::<AppState>();
+        state.end_recording().await.map_err(to_fdo)
+    }
+    /// Cancels the current dictation, throwing away whatever's been recorded.
+    fn cancel(&self) -> fdo::Result<()> {
+        self.app.state::<AppState>().cancel().map_err(to_fdo)
+    }
+    /// What we're currently doing: `Recording`, `Transcribing` or `Idle`.
+    #[dbus_interface(property)]
+    fn status(&self) -> String {
+        format!("{:?}", self.app.state::<AppState>().status())
+    }
+
+    /// Emitted whenever the dictation state changes.
+    #[dbus_interface(signal)]
+    async fn state_changed(ctxt: &SignalContext<'_>, state: &str) -> zbus::Result<()>;
+    /// Emitted when a dictation has been transcribed, with the text it produced.
+    #[dbus_interface(signal)]
+    async fn transcription_finished(ctxt: &SignalContext<'_>, text: &str) -> zbus::Result<()>;
+    /// Emitted when a dictation fails, with a description of what went wrong.
+    #[dbus_interface(signal)]
+    async fn dictation_failed(ctxt: &SignalContext<'_>, error: &str) -> zbus::Result<()>;
+}
+
+/// Starts the D-Bus service, which will run until the app shuts down.
+pub async fn init(app: AppHandle) -> Result<(), AppError> {
+    let connection = ConnectionBuilder::session()
+        .and_then(|builder| builder.name(NAME))
+        .and_then(|builder| builder.serve_at(PATH, Dictation { app: app.clone() }))
+        .map_err(|err| AppError::StartDbusFailed { source: err })?
+        .build()
+        .await
+        .map_err(|err| AppError::StartDbusFailed { source: err })?;
+
+    let status_rx = app.state::<AppState>().subscribe();
+    tauri::async_runtime::spawn(watch_status(connection.clone(), status_rx));
+    app.manage(connection);
+
+    Ok(())
+}
+
+/// Tells anyone listening on D-Bus about the result of a dictation. This does nothing if the
+/// service isn't running.
+pub async fn announce_result(app: &AppHandle, res: &Result<DocumentUpdate, Error>) {
+    let connection = match app.try_state::<Connection>() {
+        Some(connection) => connection,
+        None => return,
+    };
+    // This can't fail, the path is valid
+    let ctxt = SignalContext::new(&connection, PATH).unwrap();
+    // Nobody listening is worth failing a dictation over
+    let _ = match res {
+        Ok(update) => Dictation::transcription_finished(&ctxt, &update.inserted).await,
+        // The user knows they cancelled, and the state change says as much
+        Err(Error::DictationCancelled) => Ok(()),
+        Err(err) => Dictation::dictation_failed(&ctxt, &err.to_string()).await,
+    };
+}
+
+/// Emits `StateChanged` (and a change to the `Status` property) whenever the dictation status
+/// changes, until the app shuts down.
+async fn watch_status(connection: Connection, mut rx: watch::Receiver<DictationStatus>) {
+    // This can't fail, the path is valid
+    let ctxt = SignalContext::new(&connection, PATH).unwrap();
+    while rx.changed().await.is_ok() {
+        let status = *rx.borrow_and_update();
+        let _ = Dictation::state_changed(&ctxt, &format!("{status:?}")).await;
+        if let Ok(iface) = connection
+            .object_server()
+            .interface::<_, Dictation>(PATH)
+            .await
+        {
+            let _ = iface.get().await.status_changed(&ctxt).await;
+        }
+    }
+}
+
+/// Converts one of our errors into one D-Bus clients will understand.
+fn to_fdo(err: Error) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
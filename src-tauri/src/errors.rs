@@ -1,125 +1,82 @@
+use sotto_types::{CommandError, ErrorCode};
 use thiserror::Error;
 
-/// Errors that can occur in Sotto.
+/// Errors that can occur in the parts of Sotto that only the app has. Everything else uses
+/// [`sotto_core::Error`].
 #[derive(Debug, Error)]
-pub enum Error {
-    #[error("cannot begin dictation, we're already dictating")]
-    AlreadyDictating,
-    #[error("cannot end recording, we aren't dictating")]
-    NotDictating,
-    #[error("failed to create temporary file to record to")]
-    TmpFileCreationFailed {
+pub enum AppError {
+    #[error("failed to set up global shortcuts (these only work on X11 on Linux)")]
+    CreateHotkeyManagerFailed {
         #[source]
-        source: std::io::Error,
+        source: global_hotkey::Error,
     },
-    #[error("dictation task panicked")]
-    DictationTaskPanicked { source: tokio::task::JoinError },
-    #[error("no audio input device found (do you have a microphone connected?)")]
-    NoInputDevice,
-    #[error("failed to get configuration for default audio input device")]
-    GetDefaultInputConfigFailed {
+    #[error("invalid dictation shortcut (it should look something like 'Ctrl+Shift+Space')")]
+    InvalidHotkey {
         #[source]
-        source: cpal::DefaultStreamConfigError,
+        source: global_hotkey::hotkey::HotKeyParseError,
     },
-    #[error("failed to create a wav file writer to record audio")]
-    CreateWavWriterFailed {
-        #[source]
-        source: hound::Error,
-    },
-    #[error("failed to build input stream for recording audio")]
-    BuildInputStreamFailed {
-        #[source]
-        source: cpal::BuildStreamError,
-    },
-    #[error("failed to start audio input stream for recording")]
-    StartStreamFailed {
-        #[source]
-        source: cpal::PlayStreamError,
-    },
-    #[error("failed to instantiate context for speech recognition")]
-    LoadWhisperCtxFailed {
-        #[source]
-        source: whisper_rs::WhisperError,
-    },
-    #[error("failed to create state for speech recognition")]
-    CreateWhisperStateFailed {
-        #[source]
-        source: whisper_rs::WhisperError,
-    },
-    #[error("failed to create a reader for recorded audio")]
-    CreateWavReaderFailed {
-        #[source]
-        source: hound::Error,
-    },
-    #[error("failed to parse recorded audio sample (try again, but your microphone may not be compatible with Sotto)")]
-    ParseSampleFailed {
-        #[source]
-        source: hound::Error,
-    },
-    #[error("failed to run speech recognition model")]
-    WhisperRunFailed {
-        #[source]
-        source: whisper_rs::WhisperError,
-    },
-    #[error("failed to get number of segments in speech recognition output")]
-    GetNumSegmentsFailed {
+    #[error(
+        "invalid shortcut for the '{name}' profile (it should look something like 'Ctrl+Shift+1')"
+    )]
+    InvalidProfileHotkey {
+        name: String,
         #[source]
-        source: whisper_rs::WhisperError,
+        source: global_hotkey::hotkey::HotKeyParseError,
     },
-    #[error("failed to extract text from speech recognition output")]
-    GetSegmentTextFailed {
+    #[error("the shortcut '{hotkey}' is used for more than one thing")]
+    DuplicateHotkey { hotkey: String },
+    #[error("failed to register dictation shortcut (another app might be using it already)")]
+    RegisterHotkeyFailed {
         #[source]
-        source: whisper_rs::WhisperError,
+        source: global_hotkey::Error,
     },
-    #[error(
-        "couldn't find a home directory (please execute this program on a sane operating system)"
-    )]
-    NoHomeDir,
-    #[error("failed to download model (are you connected to the internet?)")]
-    DownloadModelFailed {
+    #[cfg(target_os = "linux")]
+    #[error("failed to start d-bus service (is another instance of sotto running?)")]
+    StartDbusFailed {
         #[source]
-        source: reqwest::Error,
+        source: zbus::Error,
     },
-    #[error("downloading model failed with http status code {status}")]
-    DownloadModelBadStatus { status: u16 },
-    #[error("failed to create a file for the downloaded model")]
-    CreateModelFileFailed {
+    #[error("failed to create directory for logs")]
+    CreateLogDirFailed {
         #[source]
-        source: std::io::Error,
+        source: sotto_core::Error,
     },
-    #[error("found bad chunk in downloaded model response")]
-    BadChunk {
+    #[error("failed to open log file")]
+    CreateLogFileFailed {
         #[source]
-        source: reqwest::Error,
+        source: tracing_appender::rolling::InitError,
     },
-    #[error("failed to write chunk of downloaded model to file")]
-    WriteChunkFailed {
+    #[error("background task panicked")]
+    TaskPanicked {
         #[source]
-        source: std::io::Error,
+        source: tokio::task::JoinError,
     },
-    #[error("failed to get model index to download model (are you connected to the internet?)")]
-    GetModelIndexFailed { source: reqwest::Error },
-    #[error("getting model index failed with http status code {status}")]
-    GetModelIndexBadStatus { status: u16 },
-    #[error(transparent)]
-    ModelIndexError(#[from] ModelIndexError),
-    #[error("failed to create `.sotto` directory in your home directory for storing models")]
-    CreateSottoDirFailed {
+    #[error("failed to run task on main thread")]
+    RunOnMainThreadFailed {
         #[source]
-        source: std::io::Error,
+        source: tauri::Error,
     },
 }
-
-/// Errors that can occur with the model index. These are specifically errors that herald a severe
-/// problem with the model index which should be immediately reported to prevent issues for
-/// all users of Sotto worldwide.
-#[derive(Debug, Error)]
-pub enum ModelIndexError {
-    #[error("failed to parse global model index (this is a bug in Sotto, and will be fixed as soon as possible)")]
-    ParseFailed {
-        #[source]
-        source: serde_json::Error,
-    },
-    #[error("the global model index is missing a key (this is a bug in Sotto, and will be fixed as soon as possible)")]
-    Incomplete { missing_key: &'static str },
+impl AppError {
+    /// Gets the kind of error this is, as far as the frontend's concerned.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidHotkey { .. }
+            | Self::InvalidProfileHotkey { .. }
+            | Self::DuplicateHotkey { .. }
+            | Self::RegisterHotkeyFailed { .. } => ErrorCode::InvalidHotkey,
+            #[cfg(target_os = "linux")]
+            Self::StartDbusFailed { .. } => ErrorCode::Internal,
+            Self::CreateHotkeyManagerFailed { .. }
+            | Self::CreateLogDirFailed { .. }
+            | Self::CreateLogFileFailed { .. }
+            | Self::TaskPanicked { .. }
+            | Self::RunOnMainThreadFailed { .. } => ErrorCode::Internal,
+        }
+    }
+}
+impl From<AppError> for CommandError {
+    fn from(err: AppError) -> Self {
+        CommandError::new(err.code(), err.to_string(), format!("{err:?}"))
+    }
 }
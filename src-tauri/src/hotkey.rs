@@ -0,0 +1,171 @@
+use crate::dictate::{AppState, DictationStatus};
+use crate::document::DocumentUpdate;
+use crate::errors::AppError;
+use crate::settings::{HotkeyMode, Settings};
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use sotto_core::Error;
+use sotto_types::CommandError;
+use std::cell::RefCell;
+use std::sync::{Mutex, PoisonError};
+use tauri::{AppHandle, Manager};
+
+thread_local! {
+    /// The global hotkey manager, along with the hotkeys currently registered with it. This has to
+    /// live on the main thread, because macOS and Windows need it to be on the same thread as the
+    /// event loop.
+    static MANAGER: RefCell<Option<(GlobalHotKeyManager, Vec<HotKey>)>> = RefCell::new(None);
+}
+/// The IDs of the currently registered hotkeys and what each of them does, which the event handler
+/// uses to work out what to do (and to filter out events for hotkeys that have since been
+/// unregistered).
+static BINDINGS: Mutex<Vec<(u32, Action)>> = Mutex::new(Vec::new());
+
+/// The things a global shortcut can do.
+#[derive(Clone)]
+enum Action {
+    /// Start or stop dictation.
+    Dictate,
+    /// Switch to the profile with the given name.
+    SelectProfile(String),
+}
+
+/// Sets up the global dictation and profile shortcuts from the user's settings. This must be
+/// called on the main thread.
+pub fn init(app: &AppHandle) -> Result<(), AppError> {
+    let manager = GlobalHotKeyManager::new()
+        .map_err(|err| AppError::CreateHotkeyManagerFailed { source: err })?;
+    MANAGER.with(|cell| *cell.borrow_mut() = Some((manager, Vec::new())));
+
+    let handler_app = app.clone();
+    GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+        let action = BINDINGS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|(id, _)| *id == event.id)
+            .map(|(_, action)| action.clone());
+        match action {
+            Some(Action::Dictate) => handle_event(&handler_app, event.state),
+            // Switching happens as soon as the shortcut's pressed
+            Some(Action::SelectProfile(name)) if event.state == HotKeyState::Pressed => {
+                select_profile(&handler_app, name)
+            }
+            _ => {}
+        }
+    }));
+
+    register(&app.state::<AppState>().settings())
+}
+
+/// Checks that the shortcuts in the given settings can actually be parsed, and that none of them
+/// are used twice, without registering them. This can be called from any thread.
+pub fn validate(settings: &Settings) -> Result<(), AppError> {
+    parse(settings).map(|_| ())
+}
+
+/// Registers the shortcuts in the given settings, replacing whatever was registered before. This
+/// must be called on the main thread, and will do nothing if [`init`] hasn't been called.
+pub fn register(settings: &Settings) -> Result<(), AppError> {
+    let bindings = parse(settings)?;
+    MANAGER.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let (manager, current) = match cell.as_mut() {
+            Some(manager) => manager,
+            None => return Ok(()),
+        };
+
+        for old_hotkey in current.drain(..) {
+            // If this fails, the old hotkey is already gone
+            let _ = manager.unregister(old_hotkey);
+        }
+        let mut registered = BINDINGS.lock().unwrap_or_else(PoisonError::into_inner);
+        registered.clear();
+        for (hotkey, action) in bindings {
+            manager
+                .register(hotkey)
+                .map_err(|err| AppError::RegisterHotkeyFailed { source: err })?;
+            registered.push((hotkey.id(), action));
+            current.push(hotkey);
+        }
+
+        Ok(())
+    })
+}
+
+/// Parses the shortcuts in the given settings, along with what each of them does. Any the user
+/// has left empty are skipped.
+fn parse(settings: &Settings) -> Result<Vec<(HotKey, Action)>, AppError> {
+    let mut bindings: Vec<(HotKey, Action)> = Vec::new();
+    let dictate = settings.hotkey.trim();
+    if !dictate.is_empty() {
+        let hotkey = dictate
+            .parse()
+            .map_err(|err| AppError::InvalidHotkey { source: err })?;
+        bindings.push((hotkey, Action::Dictate));
+    }
+    for profile in &settings.profiles {
+        let shortcut = profile.hotkey.trim();
+        if shortcut.is_empty() {
+            continue;
+        }
+        let hotkey: HotKey = shortcut
+            .parse()
+            .map_err(|err| AppError::InvalidProfileHotkey {
+                name: profile.name.clone(),
+                source: err,
+            })?;
+        // Only one of these could ever work
+        if bindings.iter().any(|(other, _)| other.id() == hotkey.id()) {
+            return Err(AppError::DuplicateHotkey {
+                hotkey: shortcut.to_string(),
+            });
+        }
+        bindings.push((hotkey, Action::SelectProfile(profile.name.clone())));
+    }
+
+    Ok(bindings)
+}
+
+/// Starts or stops dictation in response to the hotkey being pressed or released, according to
+/// the user's chosen mode.
+fn handle_event(app: &AppHandle, key_state: HotKeyState) {
+    let state = app.state::<AppState>();
+    let mode = state.settings().hotkey_mode;
+    match (mode, key_state, state.status()) {
+        (_, HotKeyState::Pressed, DictationStatus::Idle) => {
+            if let Err(err) = crate::dictate_in_background(app) {
+                emit_result(app, Err(err));
+            }
+        }
+        (HotkeyMode::Toggle, HotKeyState::Pressed, DictationStatus::Recording)
+        | (HotkeyMode::Hold, HotKeyState::Released, DictationStatus::Recording) => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<AppState>();
+                if let Err(err) = state.end_recording().await {
+                    emit_result(&app, Err(err));
+                }
+            });
+        }
+        // We can't do anything while transcribing
+        _ => {}
+    }
+}
+
+/// Switches to the profile with the given name in response to its shortcut being pressed, letting
+/// the frontend know (see [`crate::switch_profile`]).
+fn select_profile(app: &AppHandle, name: String) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = crate::switch_profile(&app, &name).await {
+            tracing::warn!(?err, "failed to switch profile from shortcut");
+        }
+    });
+}
+
+/// Sends the result of a dictation started by the hotkey to the frontend. (When the frontend
+/// starts a dictation itself, it gets the result directly.)
+fn emit_result(app: &AppHandle, res: Result<DocumentUpdate, Error>) {
+    let _ = app.emit_all("dictation-finished", res.map_err(CommandError::from));
+}
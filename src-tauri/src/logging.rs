@@ -0,0 +1,62 @@
+use crate::errors::AppError;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// How many days of logs are kept before the oldest are deleted.
+const MAX_LOG_FILES: usize = 7;
+/// What's logged if `SOTTO_LOG` isn't set: everything interesting from Sotto itself, and only
+/// problems from our dependencies.
+const DEFAULT_FILTER: &str = "warn,app=info,sotto_core=info";
+
+/// Sets up logging to stderr and to a log file in the log directory, which is rotated daily.
+/// What's logged can be changed with the `SOTTO_LOG` environment variable (e.g.
+/// `SOTTO_LOG=debug`). Panics will be logged too.
+///
+/// If the log file can't be opened, we'll still log to stderr. The returned guard must be held
+/// until the app exits, or anything not yet written to the log file will be lost.
+pub fn init() -> Option<WorkerGuard> {
+    let filter =
+        EnvFilter::try_from_env("SOTTO_LOG").unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (file_layer, guard, file_err) = match open_log_file() {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = fmt::layer().with_ansi(false).with_writer(writer);
+            (Some(layer), Some(guard), None)
+        }
+        Err(err) => (None, None, Some(err)),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .init();
+    // We can only say this once there's somewhere to say it
+    if let Some(err) = file_err {
+        tracing::warn!(?err, "failed to open log file, only logging to stderr");
+    }
+
+    // Panics would otherwise only go to stderr, which nobody sees in the app
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!(%info, "panicked");
+        default_hook(info);
+    }));
+
+    guard
+}
+
+/// Opens the rotating log file in the log directory.
+fn open_log_file() -> Result<RollingFileAppender, AppError> {
+    let log_dir = sotto_core::diagnostics::log_dir()
+        .map_err(|err| AppError::CreateLogDirFailed { source: err })?;
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("sotto")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+        .map_err(|err| AppError::CreateLogFileFailed { source: err })
+}
@@ -3,27 +3,92 @@
     windows_subsystem = "windows"
 )]
 
-mod dictate;
+#[cfg(target_os = "linux")]
+mod dbus;
 mod errors;
-mod model;
-mod record;
-mod transcribe;
+mod hotkey;
+mod logging;
+mod tray;
+
+// Everything that doesn't need Tauri lives in `sotto-core`, so other frontends can use it too
+use sotto_core::{
+    diagnostics, dictate, document, export, history, model, output, record, settings, vault,
+    workspace,
+};
 
 use crate::dictate::AppState;
-use crate::model::Model;
-use tauri::State;
+use crate::document::{DocumentUpdate, UncertainWord};
+use crate::errors::AppError;
+use crate::export::{ExportFormat, ExportSource};
+use crate::history::HistoryEntry;
+use crate::settings::{OutputTarget, Settings};
+use crate::vault::VaultStatus;
+use crate::workspace::WorkspaceSummary;
+use sotto_core::Error;
+use sotto_types::CommandError;
+use tauri::{AppHandle, Manager, State};
 
 #[tokio::main]
 async fn main() {
-    // TODO Don't panic, show an error page inside Sotto
-    // We should lazily instantiate the model so Sotto can show a loading bar
-    let state = AppState::new(Model::WhisperBase)
-        .await
-        .expect("failed to instantate sotto");
+    // This has to live until we exit, so everything gets written to the log file
+    let _log_guard = crate::logging::init();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "starting sotto");
+
+    // If anything goes wrong here, we still start, and the frontend shows the error
+    // TODO We should lazily instantiate the model so Sotto can show a loading bar
+    let state = AppState::from_settings_or_degraded().await;
 
     tauri::Builder::default()
         .manage(state)
-        .invoke_handler(tauri::generate_handler![dictate, end_recording])
+        .system_tray(crate::tray::system_tray())
+        .on_system_tray_event(crate::tray::on_event)
+        .setup(|app| {
+            // Global shortcuts are a nicety, Sotto can be used fine without them
+            if let Err(err) = crate::hotkey::init(&app.handle()) {
+                tracing::warn!(?err, "failed to set up global shortcut");
+            }
+            let status_rx = app.state::<AppState>().subscribe();
+            tauri::async_runtime::spawn(crate::tray::watch_status(app.handle(), status_rx));
+            // Like global shortcuts, the D-Bus service is only for integrating with other apps
+            #[cfg(target_os = "linux")]
+            {
+                let handle = app.handle();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(err) = crate::dbus::init(handle).await {
+                        tracing::warn!(?err, "failed to start d-bus service");
+                    }
+                });
+            }
+
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            dictate,
+            end_recording,
+            play_recording_of,
+            get_settings,
+            settings_error,
+            update_settings,
+            list_input_devices,
+            select_profile,
+            vault_status,
+            unlock_vault,
+            lock_vault,
+            set_document,
+            resolve_word,
+            get_workspace,
+            restore_workspace,
+            create_document,
+            rename_document,
+            switch_document,
+            delete_document,
+            recover_recording,
+            copy_text,
+            list_history,
+            export,
+            create_diagnostic_report,
+            restart
+        ])
         .run(tauri::generate_context!())
         // Critical error, we definitionally can't proceed
         .expect("failed to start tauri application");
@@ -34,11 +99,291 @@ async fn main() {
 // and a mutex is locked anyway, so the locking would block until it was ready, so this should
 // always work)
 #[tauri::command]
-async fn dictate(state: State<'_, AppState>) -> Result<String, String> {
-    let task_fut = state.dictate().map_err(|e| format!("{e:?}"))?;
-    task_fut.await.map_err(|e| format!("{e:?}"))
+async fn dictate(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DocumentUpdate, CommandError> {
+    let task_fut = state.dictate()?;
+    let res = finish_dictation(&app, task_fut.await).await;
+    announce_result(&app, &res).await;
+
+    Ok(res?)
+}
+#[tauri::command]
+async fn end_recording(state: State<'_, AppState>) -> Result<(), CommandError> {
+    Ok(state.end_recording().await?)
+}
+#[tauri::command]
+async fn play_recording_of(state: State<'_, AppState>, text: String) -> Result<(), CommandError> {
+    let key = state.vault_key()?;
+    // Playback blocks until it's done, so keep it off the async runtime
+    tokio::task::spawn_blocking(move || crate::history::play_recording_of(&text, &key))
+        .await
+        .map_err(|err| AppError::TaskPanicked { source: err })??;
+
+    Ok(())
+}
+#[tauri::command]
+fn get_settings(state: State<'_, AppState>) -> Settings {
+    state.settings()
+}
+/// Gets the error the user's settings couldn't be loaded with, if there was one, so the frontend
+/// can explain why they've been reset to the defaults.
+#[tauri::command]
+fn settings_error(state: State<'_, AppState>) -> Option<CommandError> {
+    state.take_settings_error().map(CommandError::from)
+}
+/// Saves the given settings, and then gets the active profile's model ready (downloading it if
+/// necessary), so this can take a while if the user's picked a new model.
+#[tauri::command]
+async fn update_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: Settings,
+) -> Result<(), CommandError> {
+    // Don't save shortcuts we can't use
+    crate::hotkey::validate(&settings)?;
+    state.update_settings(settings)?;
+    // The shortcuts can only be registered on the main thread
+    let settings = state.settings();
+    app.run_on_main_thread(move || {
+        if let Err(err) = crate::hotkey::register(&settings) {
+            tracing::warn!(?err, "failed to register global shortcuts");
+        }
+    })
+    .map_err(|err| AppError::RunOnMainThreadFailed { source: err })?;
+
+    Ok(state.prepare_model().await?)
+}
+#[tauri::command]
+fn list_input_devices() -> Result<Vec<String>, CommandError> {
+    Ok(crate::record::list_input_devices()?)
+}
+#[tauri::command]
+async fn select_profile(app: AppHandle, name: String) -> Result<(), CommandError> {
+    Ok(switch_profile(&app, &name).await?)
+}
+#[tauri::command]
+fn vault_status(state: State<'_, AppState>) -> Result<VaultStatus, CommandError> {
+    Ok(state.vault_status()?)
+}
+#[tauri::command]
+async fn unlock_vault(state: State<'_, AppState>, passphrase: String) -> Result<(), CommandError> {
+    Ok(state.unlock_vault(passphrase).await?)
+}
+#[tauri::command]
+fn lock_vault(state: State<'_, AppState>) {
+    state.lock_vault()
+}
+#[tauri::command]
+fn set_document(
+    state: State<'_, AppState>,
+    id: u64,
+    text: String,
+    selection: Option<(usize, usize)>,
+) -> Result<Option<Vec<UncertainWord>>, CommandError> {
+    Ok(state.set_document(id, text, selection)?)
+}
+#[tauri::command]
+fn resolve_word(
+    state: State<'_, AppState>,
+    id: u64,
+    start: usize,
+    correction: Option<String>,
+) -> Result<DocumentUpdate, CommandError> {
+    Ok(state.resolve_word(id, start, correction)?)
+}
+#[tauri::command]
+fn get_workspace(state: State<'_, AppState>) -> WorkspaceSummary {
+    state.workspace()
+}
+#[tauri::command]
+fn restore_workspace(state: State<'_, AppState>) -> Result<WorkspaceSummary, CommandError> {
+    Ok(state.restore_workspace()?)
+}
+#[tauri::command]
+fn create_document(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<WorkspaceSummary, CommandError> {
+    Ok(state.create_document(&name)?)
+}
+#[tauri::command]
+fn rename_document(
+    state: State<'_, AppState>,
+    id: u64,
+    name: String,
+) -> Result<WorkspaceSummary, CommandError> {
+    Ok(state.rename_document(id, &name)?)
+}
+#[tauri::command]
+fn switch_document(state: State<'_, AppState>, id: u64) -> Result<WorkspaceSummary, CommandError> {
+    Ok(state.switch_document(id)?)
 }
 #[tauri::command]
-async fn end_recording(state: State<'_, AppState>) -> Result<(), String> {
-    state.end_recording().await.map_err(|e| format!("{e:?}"))
+fn delete_document(state: State<'_, AppState>, id: u64) -> Result<WorkspaceSummary, CommandError> {
+    Ok(state.delete_document(id)?)
+}
+#[tauri::command]
+async fn recover_recording(state: State<'_, AppState>) -> Result<Option<String>, CommandError> {
+    Ok(state.recover_recording().await?)
+}
+
+#[tauri::command]
+fn copy_text(text: String) -> Result<(), CommandError> {
+    Ok(crate::output::copy_to_clipboard(&text)?)
+}
+#[tauri::command]
+fn list_history(state: State<'_, AppState>) -> Result<Vec<HistoryEntry>, CommandError> {
+    Ok(state.history()?)
+}
+/// Exports the given source in the given format, asking the user where to save it. This returns
+/// whether or not anything was actually saved (the user might have cancelled).
+#[tauri::command]
+async fn export(
+    app: AppHandle,
+    source: ExportSource,
+    format: ExportFormat,
+) -> Result<bool, CommandError> {
+    // The dialog blocks until the user's chosen somewhere, so keep it off the async runtime
+    let saved = tokio::task::spawn_blocking(move || {
+        let path = tauri::api::dialog::blocking::FileDialogBuilder::new()
+            .set_title("Export")
+            .set_file_name(&format!("sotto.{}", format.extension()))
+            .add_filter(format.name(), &[format.extension()])
+            .save_file();
+        match path {
+            Some(path) => app
+                .state::<AppState>()
+                .export(source, format, &path)
+                .map(|_| true),
+            None => Ok(false),
+        }
+    })
+    .await
+    .map_err(|err| AppError::TaskPanicked { source: err })??;
+
+    Ok(saved)
+}
+
+/// Creates a diagnostic report for the user to attach to an issue, asking them where to save it.
+/// This returns whether or not anything was actually saved (the user might have cancelled).
+#[tauri::command]
+async fn create_diagnostic_report(app: AppHandle) -> Result<bool, CommandError> {
+    // As with exports, the dialog blocks until the user's chosen somewhere
+    let saved = tokio::task::spawn_blocking(move || {
+        let path = tauri::api::dialog::blocking::FileDialogBuilder::new()
+            .set_title("Save diagnostic report")
+            .set_file_name("sotto-diagnostics.zip")
+            .add_filter("Zip archive", &["zip"])
+            .save_file();
+        match path {
+            Some(path) => crate::diagnostics::create_report(
+                &path,
+                &app.package_info().version.to_string(),
+                &app.state::<AppState>().settings(),
+            )
+            .map(|_| true),
+            None => Ok(false),
+        }
+    })
+    .await
+    .map_err(|err| AppError::TaskPanicked { source: err })??;
+
+    Ok(saved)
+}
+
+/// Starts a dictation that the frontend didn't ask for (e.g. from the global shortcut), sending the
+/// result to the frontend as a `dictation-finished` event when it's done.
+pub fn dictate_in_background(app: &AppHandle) -> Result<(), Error> {
+    let task_fut = app.state::<AppState>().dictate()?;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let res = finish_dictation(&app, task_fut.await).await;
+        announce_result(&app, &res).await;
+        let _ = app.emit_all("dictation-finished", res.map_err(CommandError::from));
+    });
+
+    Ok(())
+}
+
+/// Switches to the profile with the given name, however that was asked for, and tells the frontend
+/// with a `profile-changed` event (so the main screen keeps up when a shortcut's used). This waits
+/// until the profile's model is ready.
+pub async fn switch_profile(app: &AppHandle, name: &str) -> Result<(), Error> {
+    let state = app.state::<AppState>();
+    let res = state.select_profile(name).await;
+    // Even if the model couldn't be got ready, the profile might still have been switched to
+    if state.settings().active_profile == name {
+        let _ = app.emit_all("profile-changed", name);
+    }
+
+    res
+}
+
+/// Delivers the text from a finished dictation to wherever the user wants it. The text's already
+/// in the document by now, so if it can't be delivered, that's recorded in the update rather than
+/// failing the whole dictation (which would leave the frontend without the text).
+async fn finish_dictation(
+    app: &AppHandle,
+    res: Result<DocumentUpdate, Error>,
+) -> Result<DocumentUpdate, Error> {
+    let mut update = res?;
+    if let Err(err) = deliver_output(app, &update.inserted).await {
+        tracing::error!(?err, "failed to deliver dictation");
+        update.output_error = Some(err.into());
+    }
+
+    Ok(update)
+}
+
+/// Tells other apps (and the log) about the result of a dictation, however it was started.
+#[allow(unused_variables)]
+async fn announce_result(app: &AppHandle, res: &Result<DocumentUpdate, Error>) {
+    match res {
+        Ok(_) | Err(Error::DictationCancelled) => {}
+        Err(err) => tracing::error!(?err, "dictation failed"),
+    }
+    #[cfg(target_os = "linux")]
+    crate::dbus::announce_result(app, res).await;
+}
+
+/// Restarts Sotto, which the frontend offers after errors it can't recover from.
+#[tauri::command]
+fn restart(app: AppHandle) {
+    app.restart();
+}
+
+/// Delivers the result of a dictation to the focused application, according to the user's
+/// chosen output target, and copies it to the clipboard if they've asked for that. If Sotto
+/// itself is focused, nothing will be delivered, since the text will end up in Sotto's own
+/// document anyway.
+async fn deliver_output(app: &AppHandle, text: &str) -> Result<(), Error> {
+    // Voice commands don't produce any new text
+    if text.is_empty() {
+        return Ok(());
+    }
+    let sotto_focused = app
+        .get_window("main")
+        .map_or(false, |window| window.is_focused().unwrap_or(false));
+    let settings = app.state::<AppState>().settings();
+    let target = if sotto_focused {
+        OutputTarget::Sotto
+    } else {
+        settings.profile().output_target
+    };
+
+    let text = text.to_string();
+    // Typing blocks for a while
+    tokio::task::spawn_blocking(move || {
+        crate::output::deliver(&text, target)?;
+        // This has to come after delivery, since pasting restores the old clipboard
+        if settings.copy_on_finish {
+            crate::output::copy_to_clipboard(&text)?;
+        }
+
+        Ok(())
+    })
+    .await
+    .map_err(|err| Error::DictationTaskPanicked { source: err })?
 }
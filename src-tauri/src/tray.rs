@@ -0,0 +1,94 @@
+use crate::dictate::DictationStatus;
+use tauri::{
+    AppHandle, CustomMenuItem, Icon, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    SystemTrayMenuItem,
+};
+use tokio::sync::watch;
+
+/// The size of the tray icon we draw, in pixels.
+const ICON_SIZE: u32 = 32;
+
+/// Creates the system tray, which shows the user what Sotto's doing when its window isn't
+/// visible (e.g. when they're dictating into another app with the global shortcut).
+pub fn system_tray() -> SystemTray {
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new("status", label(DictationStatus::Idle)).disabled())
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("show", "Show Sotto"))
+        .add_item(CustomMenuItem::new("quit", "Quit"));
+    SystemTray::new().with_menu(menu)
+}
+
+/// Handles clicks on the tray menu.
+pub fn on_event(app: &AppHandle, event: SystemTrayEvent) {
+    if let SystemTrayEvent::MenuItemClick { id, .. } = event {
+        match id.as_str() {
+            "show" => {
+                if let Some(window) = app.get_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+            "quit" => app.exit(0),
+            _ => {}
+        }
+    }
+}
+
+/// Keeps the tray and the frontend up-to-date with the dictation status, until the app shuts
+/// down.
+pub async fn watch_status(app: AppHandle, mut rx: watch::Receiver<DictationStatus>) {
+    loop {
+        let status = *rx.borrow_and_update();
+        // None of these are worth crashing over (e.g. tooltips aren't supported on Linux)
+        let tray = app.tray_handle();
+        let _ = tray.set_icon(indicator_icon(status));
+        let _ = tray.set_tooltip(&format!("Sotto: {}", label(status)));
+        let _ = tray.get_item("status").set_title(label(status));
+        let _ = app.emit_all("dictation-status", status);
+
+        if rx.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Gets a human-readable label for the given status.
+fn label(status: DictationStatus) -> &'static str {
+    match status {
+        DictationStatus::Recording => "Recording...",
+        DictationStatus::Transcribing => "Transcribing...",
+        DictationStatus::Idle => "Ready to dictate",
+    }
+}
+
+/// Draws a tray icon for the given status: a circle in the same colour as the big button in the
+/// main window.
+fn indicator_icon(status: DictationStatus) -> Icon {
+    let [r, g, b] = match status {
+        DictationStatus::Recording => [0xf8, 0x71, 0x71],
+        DictationStatus::Transcribing => [0x34, 0xd3, 0x99],
+        DictationStatus::Idle => [0xa3, 0xa3, 0xa3],
+    };
+
+    let radius = ICON_SIZE as f32 / 2.0;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let dx = x as f32 + 0.5 - radius;
+            let dy = y as f32 + 0.5 - radius;
+            let alpha = if (dx * dx + dy * dy).sqrt() <= radius - 1.0 {
+                0xff
+            } else {
+                0
+            };
+            rgba.extend([r, g, b, alpha]);
+        }
+    }
+
+    Icon::Rgba {
+        rgba,
+        width: ICON_SIZE,
+        height: ICON_SIZE,
+    }
+}
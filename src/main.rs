@@ -9,6 +9,7 @@ use sycamore::prelude::*;
 pub fn main<G: Html>() -> PerseusApp<G> {
     PerseusApp::new()
         .template(crate::templates::index::get_template())
+        .template(crate::templates::settings::get_template())
         // TODO
         .error_views(ErrorViews::unlocalized_development_default())
         .index_view(|cx| {
@@ -1,13 +1,239 @@
 //! This module is responsible for providing the FFI interface to our Tauri commands (which are written in Rust, exposed through a JS
 //! FFI interface, and then re-accessed through Rust --- efficiency!).
+//!
+//! The raw bindings deal in `JsValue`s, so each command has a typed wrapper here that sends and
+//! receives the same types the backend does, and fails with the [`CommandError`] the backend gave
+//! us (or one describing why we couldn't understand what it sent).
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sotto_types::{
+    CommandError, DocumentUpdate, ErrorCode, ExportFormat, ExportSource, HistoryEntry, Settings,
+    UncertainWord, VaultStatus, WorkspaceSummary,
+};
 use wasm_bindgen::prelude::*;
 
-#[cfg(client)]
-#[wasm_bindgen(module = "/src/tauri_glue.js")]
-extern "C" {
-    #[wasm_bindgen(catch)]
-    pub async fn dictate() -> Result<JsValue, JsValue>;
-    #[wasm_bindgen(catch)]
-    pub async fn end_recording() -> Result<JsValue, JsValue>;
+mod glue {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen(module = "/src/tauri_glue.js")]
+    extern "C" {
+        #[wasm_bindgen(catch)]
+        pub async fn dictate() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn end_recording() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn play_recording_of(text: String) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn get_settings() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn settings_error() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn update_settings(settings: JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn list_input_devices() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn select_profile(name: String) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn vault_status() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn unlock_vault(passphrase: String) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn lock_vault() -> Result<JsValue, JsValue>;
+        // Document IDs are passed as `f64`s, since a `u64` would become a `BigInt`, which Tauri
+        // can't serialize (they're millisecond timestamps, so they fit easily)
+        #[wasm_bindgen(catch)]
+        pub async fn set_document(
+            id: f64,
+            text: String,
+            selection: JsValue,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn resolve_word(
+            id: f64,
+            start: f64,
+            correction: JsValue,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn get_workspace() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn restore_workspace() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn create_document(name: String) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn rename_document(id: f64, name: String) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn switch_document(id: f64) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn delete_document(id: f64) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn recover_recording() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn copy_text(text: String) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn list_history() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn export_text(source: JsValue, format: JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn create_diagnostic_report() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn restart() -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn listen_dictation(
+            on_status: JsValue,
+            on_finished: JsValue,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn listen_profiles(on_changed: JsValue) -> Result<JsValue, JsValue>;
+    }
+}
+
+pub async fn dictate() -> Result<DocumentUpdate, CommandError> {
+    decode(glue::dictate().await)
+}
+pub async fn end_recording() -> Result<(), CommandError> {
+    decode(glue::end_recording().await)
+}
+pub async fn play_recording_of(text: String) -> Result<(), CommandError> {
+    decode(glue::play_recording_of(text).await)
+}
+pub async fn get_settings() -> Result<Settings, CommandError> {
+    decode(glue::get_settings().await)
+}
+/// Gets the error the user's settings couldn't be loaded with, if there was one (in which case
+/// they've been reset to the defaults). This is only given once.
+pub async fn settings_error() -> Result<Option<CommandError>, CommandError> {
+    decode(glue::settings_error().await)
+}
+pub async fn update_settings(settings: &Settings) -> Result<(), CommandError> {
+    decode(glue::update_settings(encode(settings)).await)
+}
+/// Lists the names of the microphones that can be recorded from.
+pub async fn list_input_devices() -> Result<Vec<String>, CommandError> {
+    decode(glue::list_input_devices().await)
+}
+/// Switches to the profile with the given name for future dictations. This resolves once the
+/// profile's model is ready, which can take a while if it has to be downloaded.
+pub async fn select_profile(name: &str) -> Result<(), CommandError> {
+    decode(glue::select_profile(name.to_string()).await)
+}
+pub async fn vault_status() -> Result<VaultStatus, CommandError> {
+    decode(glue::vault_status().await)
+}
+pub async fn unlock_vault(passphrase: String) -> Result<(), CommandError> {
+    decode(glue::unlock_vault(passphrase).await)
+}
+pub async fn lock_vault() -> Result<(), CommandError> {
+    decode(glue::lock_vault().await)
+}
+/// Sends the current text of the given document, and the user's selection in it (as byte
+/// offsets), to the backend. This gives back the words the user should check, unless the
+/// document isn't active anymore (in which case the edit is ignored).
+pub async fn set_document(
+    id: u64,
+    text: String,
+    selection: Option<(usize, usize)>,
+) -> Result<Option<Vec<UncertainWord>>, CommandError> {
+    decode(glue::set_document(id as f64, text, encode(&selection)).await)
+}
+/// Marks an uncertain word as checked, replacing it with the given correction if there is one.
+pub async fn resolve_word(
+    id: u64,
+    start: usize,
+    correction: Option<String>,
+) -> Result<DocumentUpdate, CommandError> {
+    decode(glue::resolve_word(id as f64, start as f64, encode(&correction)).await)
+}
+pub async fn get_workspace() -> Result<WorkspaceSummary, CommandError> {
+    decode(glue::get_workspace().await)
+}
+pub async fn restore_workspace() -> Result<WorkspaceSummary, CommandError> {
+    decode(glue::restore_workspace().await)
+}
+pub async fn create_document(name: String) -> Result<WorkspaceSummary, CommandError> {
+    decode(glue::create_document(name).await)
+}
+pub async fn rename_document(id: u64, name: String) -> Result<WorkspaceSummary, CommandError> {
+    decode(glue::rename_document(id as f64, name).await)
+}
+pub async fn switch_document(id: u64) -> Result<WorkspaceSummary, CommandError> {
+    decode(glue::switch_document(id as f64).await)
+}
+pub async fn delete_document(id: u64) -> Result<WorkspaceSummary, CommandError> {
+    decode(glue::delete_document(id as f64).await)
+}
+/// Transcribes the recording that was in progress when Sotto last closed, if there was one.
+pub async fn recover_recording() -> Result<Option<String>, CommandError> {
+    decode(glue::recover_recording().await)
+}
+pub async fn copy_text(text: String) -> Result<(), CommandError> {
+    decode(glue::copy_text(text).await)
+}
+pub async fn list_history() -> Result<Vec<HistoryEntry>, CommandError> {
+    decode(glue::list_history().await)
+}
+/// Asks the user where to save the export, and resolves to whether or not they went through with
+/// it.
+pub async fn export_text(
+    source: &ExportSource,
+    format: ExportFormat,
+) -> Result<bool, CommandError> {
+    decode(glue::export_text(encode(source), encode(&format)).await)
+}
+/// Asks the user where to save a diagnostic report (logs, versions, audio devices and settings,
+/// but never anything they've said), and resolves to whether or not they went through with it.
+pub async fn create_diagnostic_report() -> Result<bool, CommandError> {
+    decode(glue::create_diagnostic_report().await)
+}
+/// Restarts Sotto, which is the only way out of some errors.
+pub async fn restart() -> Result<(), CommandError> {
+    decode(glue::restart().await)
+}
+/// Registers callbacks for changes to the dictation status, and for the results of dictations
+/// started outside the frontend (e.g. by the global shortcut). Both of these should be closures
+/// that take a single argument, which can be decoded with [`decode_event`].
+pub async fn listen_dictation(
+    on_status: JsValue,
+    on_finished: JsValue,
+) -> Result<(), CommandError> {
+    decode(glue::listen_dictation(on_status, on_finished).await)
+}
+/// Registers a callback for the active profile being switched, which will be called with the new
+/// profile's name (decodable with [`decode_event`]).
+pub async fn listen_profiles(on_changed: JsValue) -> Result<(), CommandError> {
+    decode(glue::listen_profiles(on_changed).await)
+}
+
+/// Decodes the payload of an event from the backend.
+pub fn decode_event<T: DeserializeOwned>(payload: JsValue) -> Result<T, CommandError> {
+    serde_wasm_bindgen::from_value(payload).map_err(|err| bridge_error(err.to_string()))
+}
+
+/// Encodes an argument to a command.
+fn encode<T: Serialize>(value: &T) -> JsValue {
+    // This can't fail, we only send plain data
+    serde_wasm_bindgen::to_value(value).unwrap()
+}
+/// Decodes the result of a command, which is either what the command gave back, or a
+/// [`CommandError`].
+fn decode<T: DeserializeOwned>(res: Result<JsValue, JsValue>) -> Result<T, CommandError> {
+    match res {
+        Ok(value) => decode_event(value),
+        Err(err) => Err(
+            match serde_wasm_bindgen::from_value::<CommandError>(err.clone()) {
+                Ok(err) => err,
+                // Tauri itself fails with plain strings (e.g. if a command doesn't exist)
+                Err(_) => bridge_error(err.as_string().unwrap_or_else(|| format!("{err:?}"))),
+            },
+        ),
+    }
+}
+/// Creates an error for when something goes wrong in talking to the backend, rather than in the
+/// backend itself.
+fn bridge_error(detail: String) -> CommandError {
+    CommandError::new(
+        ErrorCode::Internal,
+        "failed to communicate with the backend",
+        detail,
+    )
 }
@@ -1,6 +1,7 @@
 // This file contains glue code needed to get the Tauri commands to work with Perseus.
 
 const invoke = window.__TAURI__.tauri.invoke;
+const listen = window.__TAURI__.event.listen;
 
 export async function dictate() {
   return await invoke("dictate");
@@ -8,3 +9,79 @@ export async function dictate() {
 export async function end_recording() {
   return await invoke("end_recording");
 }
+export async function play_recording_of(text) {
+  return await invoke("play_recording_of", { text });
+}
+export async function get_settings() {
+  return await invoke("get_settings");
+}
+export async function settings_error() {
+  return await invoke("settings_error");
+}
+export async function update_settings(settings) {
+  return await invoke("update_settings", { settings });
+}
+export async function list_input_devices() {
+  return await invoke("list_input_devices");
+}
+export async function select_profile(name) {
+  return await invoke("select_profile", { name });
+}
+export async function vault_status() {
+  return await invoke("vault_status");
+}
+export async function unlock_vault(passphrase) {
+  return await invoke("unlock_vault", { passphrase });
+}
+export async function lock_vault() {
+  return await invoke("lock_vault");
+}
+export async function set_document(id, text, selection) {
+  return await invoke("set_document", { id, text, selection });
+}
+export async function resolve_word(id, start, correction) {
+  return await invoke("resolve_word", { id, start, correction });
+}
+export async function get_workspace() {
+  return await invoke("get_workspace");
+}
+export async function restore_workspace() {
+  return await invoke("restore_workspace");
+}
+export async function create_document(name) {
+  return await invoke("create_document", { name });
+}
+export async function rename_document(id, name) {
+  return await invoke("rename_document", { id, name });
+}
+export async function switch_document(id) {
+  return await invoke("switch_document", { id });
+}
+export async function delete_document(id) {
+  return await invoke("delete_document", { id });
+}
+export async function recover_recording() {
+  return await invoke("recover_recording");
+}
+export async function copy_text(text) {
+  return await invoke("copy_text", { text });
+}
+export async function list_history() {
+  return await invoke("list_history");
+}
+export async function export_text(source, format) {
+  return await invoke("export", { source, format });
+}
+export async function create_diagnostic_report() {
+  return await invoke("create_diagnostic_report");
+}
+export async function restart() {
+  return await invoke("restart");
+}
+export async function listen_dictation(on_status, on_finished) {
+  await listen("dictation-status", (event) => on_status(event.payload));
+  await listen("dictation-finished", (event) => on_finished(event.payload));
+}
+export async function listen_profiles(on_changed) {
+  await listen("profile-changed", (event) => on_changed(event.payload));
+}
@@ -1,11 +1,357 @@
 use perseus::prelude::*;
 use serde::{Deserialize, Serialize};
+use sotto_types::{
+    CommandError, DocumentSummary, FlaggedSegment, HistoryEntry, HotkeyMode, InsertionMode,
+    OutputTarget, Settings, Spelling, UncertainWord, VaultStatus,
+};
+#[cfg(client)]
+use sotto_types::{DictationStatus, ErrorCode, WorkspaceSummary};
+#[cfg(client)]
+use sotto_types::{DocumentUpdate, ExportFormat, ExportSource};
 use sycamore::prelude::*;
+#[cfg(client)]
+use wasm_bindgen::prelude::*;
 
 #[auto_scope]
 fn index_page<G: Html>(cx: Scope, state: &IndexStateRx) -> View<G> {
     let help_shown = create_signal(cx, false);
 
+    let passphrase = create_signal(cx, String::new());
+    // These are edited locally, and only saved when the user's done
+    let hotkey = create_signal(cx, String::new());
+    let hotkey_mode = create_signal(cx, String::new());
+    let active_profile = create_signal(cx, String::new());
+    let output_target = create_signal(cx, String::new());
+    let insertion_mode = create_signal(cx, String::new());
+    let spelling = create_signal(cx, String::new());
+    // Either `document` or the ID of a history entry
+    let export_source = create_signal(cx, "document".to_string());
+    let export_format = create_signal(cx, "txt".to_string());
+    create_effect(cx, move || {
+        let settings = state.settings.get();
+        hotkey.set(settings.hotkey.clone());
+        hotkey_mode.set(match settings.hotkey_mode {
+            HotkeyMode::Toggle => "toggle".to_string(),
+            HotkeyMode::Hold => "hold".to_string(),
+        });
+        active_profile.set(settings.active_profile.clone());
+        output_target.set(match settings.profile().output_target {
+            OutputTarget::Sotto => "sotto".to_string(),
+            OutputTarget::Type => "type".to_string(),
+            OutputTarget::Paste => "paste".to_string(),
+        });
+        insertion_mode.set(match settings.insertion_mode {
+            InsertionMode::Append => "append".to_string(),
+            InsertionMode::Cursor => "cursor".to_string(),
+        });
+        spelling.set(match settings.profile().normalization.spelling {
+            Spelling::Unchanged => "unchanged".to_string(),
+            Spelling::British => "british".to_string(),
+            Spelling::American => "american".to_string(),
+        });
+    });
+
+    // The settings in the build state are just the defaults, so fetch the user's real ones
+    #[cfg(client)]
+    {
+        state.load_settings(cx);
+        state.load_workspace(cx);
+        state.load_vault_status(cx);
+        state.listen_for_dictation(cx);
+        state.listen_for_profiles(cx);
+
+        // Keep the backend's copy of the document up-to-date with the user's edits, so voice
+        // commands work on what's actually there
+        // (this also saves it, if the user's autosaving)
+        create_effect(cx, move || {
+            let text = state.text.get();
+            // Until we've loaded the workspace, we'd just be overwriting the active document
+            // with nothing!
+            if *state.workspace_loaded.get_untracked() {
+                // The selection is synced separately, when it changes in the textarea itself
+                state.sync_document(cx, (*text).clone(), None);
+            }
+        });
+    }
+
+    // If the user is keeping recordings or autosaving, those are encrypted, so they need to be
+    // able to lock and unlock them
+    let vault_view = create_memo(cx, move || {
+        let vault = *state.vault.get();
+        let settings = state.settings.get();
+        if !settings.retain_recordings && !settings.autosave {
+            View::empty()
+        } else if vault.unlocked {
+            view! { cx,
+                button(
+                    on:click = move |_| {
+                        #[cfg(client)]
+                        state.lock_vault(cx);
+                    },
+                    class = "ml-4 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                ) { "Lock recordings" }
+            }
+        } else {
+            view! { cx,
+                div(class = "flex flex-row items-center ml-4") {
+                    input(
+                        type = "password",
+                        class = "p-2 border border-black rounded-md",
+                        placeholder = if vault.set_up { "Passphrase" } else { "Choose a passphrase" },
+                        bind:value = passphrase
+                    ) {}
+                    button(
+                        on:click = move |_| {
+                            #[cfg(client)]
+                            state.unlock_vault(cx, passphrase);
+                        },
+                        class = "ml-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                    ) { (if vault.set_up { "Unlock recordings" } else { "Set passphrase" }) }
+                    p(class = "ml-2 text-red-800") { (state.vault_message.get().to_string()) }
+                }
+            }
+        }
+    });
+
+    // Every document gets a tab, which switches to it
+    let document_tabs = create_memo(cx, move || {
+        let active = *state.active_document.get();
+        View::new_fragment(
+            state
+                .documents
+                .get()
+                .iter()
+                .map(|doc| {
+                    let id = doc.id;
+                    let name = doc.name.clone();
+                    let class = if id == active {
+                        "mr-2 p-2 bg-neutral-700 text-white rounded-md"
+                    } else {
+                        "mr-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                    };
+                    view! { cx,
+                        button(
+                            class = class,
+                            on:click = move |_| {
+                                #[cfg(client)]
+                                state.switch_document(cx, id);
+                            }
+                        ) { (name) }
+                    }
+                })
+                .collect(),
+        )
+    });
+    // The name of the active document, which is edited locally and saved when the user's done
+    let document_name = create_signal(cx, String::new());
+    let confirming_delete = create_signal(cx, false);
+    create_effect(cx, move || {
+        let active = *state.active_document.get();
+        let name = state
+            .documents
+            .get()
+            .iter()
+            .find(|doc| doc.id == active)
+            .map(|doc| doc.name.clone())
+            .unwrap_or_default();
+        document_name.set(name);
+        confirming_delete.set(false);
+    });
+
+    // Anything left out of the last dictation, with a way to put it back
+    let flagged_view = create_memo(cx, move || {
+        let flagged = state.flagged.get();
+        if flagged.is_empty() {
+            return View::empty();
+        }
+
+        let items = View::new_fragment(
+            flagged
+                .iter()
+                .enumerate()
+                .map(|(i, flagged)| {
+                    let text = flagged.segment.text.trim().to_string();
+                    let quoted = format!("\"{}\" ({})", text, flagged.reason.description());
+                    view! { cx,
+                        li(class = "flex flex-row items-center mt-2") {
+                            (quoted)
+                            button(
+                                on:click = move |_| {
+                                    #[cfg(client)]
+                                    state.extend_transcription(text.clone());
+                                    state.flagged.modify().remove(i);
+                                },
+                                class = "ml-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                            ) { "Put it back" }
+                        }
+                    }
+                })
+                .collect(),
+        );
+        view! { cx,
+            div(class = "flex flex-col mt-4 text-xl max-w-4xl") {
+                div(class = "flex flex-row items-center") {
+                    "Some of your last dictation was left out, because it looked like something Sotto imagined:"
+                    button(
+                        on:click = move |_| state.flagged.set(Vec::new()),
+                        class = "ml-2 p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors"
+                    ) { "Dismiss" }
+                }
+                ul { (items) }
+            }
+        }
+    });
+
+    // When proofreading, the text is shown with the words Sotto wasn't sure about highlighted,
+    // and clicking one lets the user correct it
+    let proofreading = create_signal(cx, false);
+    // The start of the uncertain word the user's checking, and what they've typed to correct it
+    let checking = create_signal(cx, None::<usize>);
+    let correction = create_signal(cx, String::new());
+    let proofread_view = create_memo(cx, move || {
+        let text = state.text.get();
+        let mut pieces = Vec::new();
+        let mut shown = 0;
+        for word in state.uncertain.get().iter() {
+            // The text and the words are updated separately, so they might not match for a moment
+            let valid = shown <= word.start
+                && word.start < word.end
+                && word.end <= text.len()
+                && text.is_char_boundary(word.start)
+                && text.is_char_boundary(word.end);
+            if !valid {
+                continue;
+            }
+
+            let before = text[shown..word.start].to_string();
+            let uncertain = text[word.start..word.end].to_string();
+            let start = word.start;
+            let class = if word.confidence < 0.3 {
+                "bg-red-200 rounded-sm cursor-pointer hover:bg-red-400"
+            } else {
+                "bg-yellow-200 rounded-sm cursor-pointer hover:bg-yellow-400"
+            };
+            pieces.push(view! { cx, (before) });
+            pieces.push(view! { cx,
+                span(
+                    class = class,
+                    on:click = move |_| {
+                        checking.set(Some(start));
+                        correction.set(String::new());
+                    }
+                ) { (uncertain) }
+            });
+            shown = word.end;
+        }
+        let rest = text[shown..].to_string();
+        pieces.push(view! { cx, (rest) });
+
+        View::new_fragment(pieces)
+    });
+    // The word the user's checking, with what else it might be
+    let checking_view = create_memo(cx, move || {
+        let start = match *checking.get() {
+            Some(start) => start,
+            None => return View::empty(),
+        };
+        let text = state.text.get();
+        let word = match state
+            .uncertain
+            .get()
+            .iter()
+            .find(|word| word.start == start)
+            .cloned()
+        {
+            Some(word) => word,
+            None => return View::empty(),
+        };
+        let current = text
+            .get(word.start..word.end)
+            .unwrap_or_default()
+            .to_string();
+
+        let alternatives = View::new_fragment(
+            word.alternatives
+                .iter()
+                .map(|alternative| {
+                    let alternative = alternative.clone();
+                    let label = alternative.clone();
+                    view! { cx,
+                        button(
+                            on:click = move |_| {
+                                checking.set(None);
+                                #[cfg(client)]
+                                state.resolve_word(cx, start, Some(alternative.clone()));
+                            },
+                            class = "ml-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                        ) { (label) }
+                    }
+                })
+                .collect(),
+        );
+        view! { cx,
+            div(class = "flex flex-row flex-wrap items-center mt-4 text-xl") {
+                (format!("\"{}\" might be:", current))
+                (alternatives)
+                input(
+                    class = "ml-2 p-2 border border-black rounded-md",
+                    placeholder = "Something else",
+                    bind:value = correction
+                ) {}
+                button(
+                    on:click = move |_| {
+                        let corrected = (*correction.get_untracked()).clone();
+                        if corrected.trim().is_empty() {
+                            return;
+                        }
+                        checking.set(None);
+                        #[cfg(client)]
+                        state.resolve_word(cx, start, Some(corrected.trim().to_string()));
+                    },
+                    class = "ml-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                ) { "Correct" }
+                button(
+                    on:click = move |_| {
+                        checking.set(None);
+                        #[cfg(client)]
+                        state.resolve_word(cx, start, None);
+                    },
+                    class = "ml-2 p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors"
+                ) { "It's right" }
+            }
+        }
+    });
+
+    // Past dictations can be exported too, if we have them
+    let profile_options = create_memo(cx, move || {
+        View::new_fragment(
+            state
+                .settings
+                .get()
+                .profiles
+                .iter()
+                .map(|profile| {
+                    let name = profile.name.clone();
+                    view! { cx, option(value = name.clone()) { (name.clone()) } }
+                })
+                .collect(),
+        )
+    });
+    let history_options = create_memo(cx, move || {
+        View::new_fragment(
+            state
+                .history
+                .get()
+                .iter()
+                .map(|entry| {
+                    let id = entry.id.to_string();
+                    let preview = entry.preview();
+                    view! { cx, option(value = id) { (preview) } }
+                })
+                .collect(),
+        )
+    });
+
     // Displays the appropriate 'big button' according to the current app state
     let button_view = create_memo(cx, move || {
         let sotto_state = state.state.get();
@@ -58,11 +404,50 @@ fn index_page<G: Html>(cx: Scope, state: &IndexStateRx) -> View<G> {
                 }
             },
             SottoState::Err(err) => {
-                let err = err.to_string();
+                let err = err.clone();
+                let can_retry = err.recoverable && *state.retry_dictation.get();
+                let actions = if err.recoverable {
+                    view! {
+                        cx,
+                        div(class = "flex justify-center mt-4") {
+                            (if can_retry {
+                                view! {
+                                    cx,
+                                    button(
+                                        on:click = move |_| {
+                                            #[cfg(client)]
+                                            state.retry(cx);
+                                        },
+                                        class = "rounded-lg bg-red-700 text-white text-lg px-6 py-2 mx-2"
+                                    ) { "Retry" }
+                                }
+                            } else {
+                                View::empty()
+                            })
+                            button(
+                                on:click = move |_| state.state.set(SottoState::Ready),
+                                class = "rounded-lg border border-red-700 text-red-800 text-lg px-6 py-2 mx-2"
+                            ) { "Back" }
+                        }
+                    }
+                } else {
+                    view! {
+                        cx,
+                        p(class = "text-lg max-w-sm text-center text-red-800 mt-4") { "Sotto can't carry on after this, so it needs to restart. Make sure to copy any transcribed text first (unless you've turned on autosaving)!" }
+                        div(class = "flex justify-center mt-4") {
+                            button(
+                                on:click = move |_| {
+                                    #[cfg(client)]
+                                    state.restart(cx);
+                                },
+                                class = "rounded-lg bg-red-700 text-white text-lg px-6 py-2"
+                            ) { "Restart Sotto" }
+                        }
+                    }
+                };
                 view! {
                     cx,
-                    div(class = "flex flex-col justify-center align-items") {
-                        // Not a button, the user should manually restart the app
+                    div(class = "flex flex-col justify-center items-center") {
                         div(
                             class = "relative flex justify-center items-center rounded-full h-96 w-96 text-red-700"
                         ) {
@@ -72,12 +457,24 @@ fn index_page<G: Html>(cx: Scope, state: &IndexStateRx) -> View<G> {
                                 circle(cx = "50", cy = "80", r = "10", fill = "white") {}
                             }
                         }
-                        p(class = "text-xl max-w-sm text-center text-red-800 mt-4") { "An error has occurred. Please take a photo of this error so we can understand it better, and then restart the app. Make sure to copy any transcribed text first!" }
-                        div(class = "bg-red-400 rounded-lg max-w-md text-white p-6 text-lg mt-4") {
-                            p(class = "break-words") { (format!("Error: '{}'", err)) }
+                        p(class = "text-2xl max-w-sm text-center text-red-800 mt-4") { (err.code.title()) }
+                        p(class = "text-xl max-w-sm text-center text-red-800 mt-2") { (err.code.remedy()) }
+                        (actions)
+                        // The details are only really useful for bug reports
+                        details(class = "bg-red-400 rounded-lg max-w-md text-white p-6 text-lg mt-4") {
+                            summary { "Details" }
+                            p(class = "break-words mt-2") { (err.message.clone()) }
+                            p(class = "break-words text-sm mt-2") { (err.detail.clone()) }
+                            button(
+                                on:click = move |_| {
+                                    #[cfg(client)]
+                                    state.create_report(cx);
+                                },
+                                class = "rounded-lg border border-white text-white px-4 py-1 mt-4"
+                            ) { "Create diagnostic report" }
+                            p(class = "text-sm mt-2") { (state.report_message.get().to_string()) }
                         }
                     }
-
                 }
             }
         }
@@ -85,20 +482,342 @@ fn index_page<G: Html>(cx: Scope, state: &IndexStateRx) -> View<G> {
 
     view! { cx,
         div(class = "w-full h-screen flex flex-col justify-center items-center") {
+            div(class = "flex flex-row flex-wrap items-center mb-4 text-xl") {
+                (*document_tabs.get())
+                button(
+                    on:click = move |_| {
+                        #[cfg(client)]
+                        state.create_document(cx);
+                    },
+                    class = "p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors"
+                ) { "New document" }
+            }
+            div(class = "flex flex-row items-center mb-4 text-xl") {
+                "Name"
+                input(
+                    class = "ml-2 p-2 border border-black rounded-md",
+                    bind:value = document_name,
+                    on:change = move |_| {
+                        #[cfg(client)]
+                        state.rename_document(cx, (*document_name.get_untracked()).clone());
+                    }
+                ) {}
+                // Deleting can't be undone, so the user has to click twice
+                (if *confirming_delete.get() {
+                    view! { cx,
+                        button(
+                            on:click = move |_| {
+                                confirming_delete.set(false);
+                                #[cfg(client)]
+                                state.delete_document(cx);
+                            },
+                            class = "ml-2 p-2 bg-red-700 text-white rounded-md"
+                        ) { "Really delete?" }
+                        button(
+                            on:click = move |_| confirming_delete.set(false),
+                            class = "ml-2 p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors"
+                        ) { "Keep it" }
+                    }
+                } else {
+                    view! { cx,
+                        button(
+                            on:click = move |_| confirming_delete.set(true),
+                            class = "ml-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-red-700 transition-colors"
+                        ) { "Delete document" }
+                    }
+                })
+            }
+            div(class = "flex flex-row items-center mb-4 text-xl") {
+                "Profile"
+                select(
+                    class = "ml-2 p-2 border border-black rounded-md",
+                    bind:value = active_profile,
+                    on:change = move |_| {
+                        #[cfg(client)]
+                        state.select_profile(cx, (*active_profile.get_untracked()).clone());
+                    }
+                ) {
+                    (*profile_options.get())
+                }
+                p(class = "ml-2") { (state.profile_message.get().to_string()) }
+            }
             div(class = "flex flex-row justify-center items-center w-full") {
                 (*button_view.get())
+                div(
+                    class = format!(
+                        "p-4 mx-4 border border-black text-4xl h-96 w-1/2 overflow-auto whitespace-pre-wrap {}",
+                        if *proofreading.get() { "block" } else { "hidden" }
+                    )
+                ) { (*proofread_view.get()) }
                 textarea(
-                    class = "p-4 mx-4 border border-black text-4xl h-96 w-1/2 resize",
+                    id = "transcript",
+                    class = format!(
+                        "p-4 mx-4 border border-black text-4xl h-96 w-1/2 resize {}",
+                        if *proofreading.get() { "hidden" } else { "block" }
+                    ),
                     bind:value = state.text,
+                    // Keep track of where the user's cursor is, so we can dictate there
+                    on:input = move |_| {
+                        #[cfg(client)]
+                        state.sync_selection(cx);
+                    },
+                    on:select = move |_| {
+                        #[cfg(client)]
+                        state.sync_selection(cx);
+                    },
+                    on:keyup = move |_| {
+                        #[cfg(client)]
+                        state.sync_selection(cx);
+                    },
+                    on:mouseup = move |_| {
+                        #[cfg(client)]
+                        state.sync_selection(cx);
+                    },
                     placeholder = "Try recording some audio, and, when it's been transcribed, the text will appear here!"
                 ) {}
             }
+            (*flagged_view.get())
+            div(class = "flex flex-row items-center mt-4 text-xl") {
+                button(
+                    on:click = move |_| {
+                        proofreading.set(!*proofreading.get_untracked());
+                        checking.set(None);
+                    },
+                    class = "p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                ) { (if *proofreading.get() { "Back to editing" } else { "Proofread" }) }
+                p(class = "ml-4") {
+                    (match state.uncertain.get().len() {
+                        0 => "No words to check".to_string(),
+                        1 => "1 word to check".to_string(),
+                        n => format!("{} words to check", n),
+                    })
+                }
+            }
+            (*checking_view.get())
+            div(class = "flex flex-row items-center mt-4 text-xl") {
+                button(
+                    on:click = move |_| {
+                        #[cfg(client)]
+                        state.copy_text(cx, (*state.text.get_untracked()).clone());
+                    },
+                    class = "p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                ) { "Copy all" }
+                button(
+                    on:click = move |_| {
+                        #[cfg(client)]
+                        state.copy_text(cx, (*state.last_dictation.get_untracked()).clone());
+                    },
+                    disabled = state.last_dictation.get().is_empty(),
+                    class = "ml-4 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 disabled:opacity-50 transition-colors"
+                ) { "Copy last dictation" }
+                label(class = "flex items-center ml-4") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().copy_on_finish,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_copy_on_finish(cx);
+                        }
+                    ) {}
+                    "Copy my text when I finish dictating"
+                }
+            }
+            div(class = "flex flex-row items-center mt-4 text-xl") {
+                "Export"
+                select(
+                    class = "ml-2 p-2 border border-black rounded-md",
+                    bind:value = export_source,
+                    // Make sure any recent dictations are in the list
+                    on:focus = move |_| {
+                        #[cfg(client)]
+                        state.load_history(cx);
+                    }
+                ) {
+                    option(value = "document") { "My current text" }
+                    (*history_options.get())
+                }
+                "as"
+                select(
+                    class = "mx-2 p-2 border border-black rounded-md",
+                    bind:value = export_format
+                ) {
+                    option(value = "txt") { "Plain text" }
+                    option(value = "markdown") { "Markdown" }
+                    option(value = "docx") { "Word document" }
+                    option(value = "srt") { "SRT subtitles" }
+                    option(value = "vtt") { "WebVTT subtitles" }
+                }
+                button(
+                    on:click = move |_| {
+                        #[cfg(client)]
+                        state.export(cx, &export_source.get_untracked(), &export_format.get_untracked());
+                    },
+                    class = "p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                ) { "Export..." }
+                p(class = "ml-2 text-red-800") { (state.export_message.get().to_string()) }
+            }
             button(
                 on:click = |_| {
                     help_shown.set(!*help_shown.get_untracked());
                 },
                 class = "mt-8 text-xl p-2 bg-red-400 text-white rounded-md hover:bg-red-700 transition-colors"
             ) { "Help!" }
+            a(
+                href = "settings",
+                class = "mt-4 text-xl p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors"
+            ) { "Settings" }
+            div(class = "flex flex-row items-center mt-4 text-xl") {
+                label(class = "flex items-center") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().retain_recordings,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_retain_recordings(cx);
+                        }
+                    ) {}
+                    "Keep my recordings so I can listen back to them"
+                }
+                label(class = "flex items-center ml-4") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().autosave,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_autosave(cx);
+                        }
+                    ) {}
+                    "Autosave my work"
+                }
+                label(class = "flex items-center ml-4") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().profile().spoken_commands,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_spoken_commands(cx);
+                        }
+                    ) {}
+                    "Understand spoken punctuation"
+                }
+                button(
+                    on:click = move |_| {
+                        #[cfg(client)]
+                        state.play_selection(cx);
+                    },
+                    class = format!(
+                        "ml-4 p-2 bg-emerald-400 text-white rounded-md hover:bg-emerald-700 transition-colors {}",
+                        if state.settings.get().retain_recordings {
+                            "block"
+                        } else {
+                            "hidden"
+                        }
+                    )
+                ) { "Listen to selection" }
+                (*vault_view.get())
+            }
+            div(class = "flex flex-row items-center mt-4 text-xl") {
+                label(class = "flex items-center") {
+                    "Dictation shortcut:"
+                    input(
+                        class = "ml-2 p-2 border border-black rounded-md",
+                        placeholder = "None",
+                        bind:value = hotkey,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.set_hotkey(cx, (*hotkey.get_untracked()).clone(), &hotkey_mode.get_untracked());
+                        }
+                    ) {}
+                }
+                select(
+                    class = "ml-4 p-2 border border-black rounded-md",
+                    bind:value = hotkey_mode,
+                    on:change = move |_| {
+                        #[cfg(client)]
+                        state.set_hotkey(cx, (*hotkey.get_untracked()).clone(), &hotkey_mode.get_untracked());
+                    }
+                ) {
+                    option(value = "toggle") { "Press to start and stop" }
+                    option(value = "hold") { "Hold to talk" }
+                }
+                select(
+                    class = "ml-4 p-2 border border-black rounded-md",
+                    bind:value = output_target,
+                    on:change = move |_| {
+                        #[cfg(client)]
+                        state.set_output_target(cx, &output_target.get_untracked());
+                    }
+                ) {
+                    option(value = "sotto") { "Only put my text here" }
+                    option(value = "type") { "Also type my text into other apps" }
+                    option(value = "paste") { "Also paste my text into other apps" }
+                }
+                select(
+                    class = "ml-4 p-2 border border-black rounded-md",
+                    bind:value = insertion_mode,
+                    on:change = move |_| {
+                        #[cfg(client)]
+                        state.set_insertion_mode(cx, &insertion_mode.get_untracked());
+                    }
+                ) {
+                    option(value = "append") { "Add new text at the end" }
+                    option(value = "cursor") { "Add new text where my cursor is" }
+                }
+            }
+            div(class = "flex flex-row items-center mt-4 text-xl") {
+                label(class = "flex items-center") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().profile().normalization.numbers,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_normalize_numbers(cx);
+                        }
+                    ) {}
+                    "Write numbers, dates and units as figures"
+                }
+                label(class = "flex items-center ml-4") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().profile().normalization.mask_profanity,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_mask_profanity(cx);
+                        }
+                    ) {}
+                    "Hide swearing"
+                }
+                label(class = "flex items-center ml-4") {
+                    input(
+                        type = "checkbox",
+                        class = "mr-2 h-6 w-6",
+                        checked = state.settings.get().suggest_alternatives,
+                        on:change = move |_| {
+                            #[cfg(client)]
+                            state.toggle_suggest_alternatives(cx);
+                        }
+                    ) {}
+                    "Suggest alternatives for words Sotto isn't sure about (slower)"
+                }
+                select(
+                    class = "ml-4 p-2 border border-black rounded-md",
+                    bind:value = spelling,
+                    on:change = move |_| {
+                        #[cfg(client)]
+                        state.set_spelling(cx, &spelling.get_untracked());
+                    }
+                ) {
+                    option(value = "unchanged") { "Don't change my spelling" }
+                    option(value = "british") { "Use British spelling" }
+                    option(value = "american") { "Use American spelling" }
+                }
+            }
             div(class = format!(
                 "text-2xl mx-4 {}",
                 if *help_shown.get() {
@@ -114,7 +833,31 @@ fn index_page<G: Html>(cx: Scope, state: &IndexStateRx) -> View<G> {
                     li { "Press the big green button to transcribe, and wait until the red button appears again." }
                     li { "Edit your text manually in the text-area to the right." }
                 }
+                p { "Sometimes the speech recognition imagines words that weren't said (especially in silence), or gets stuck repeating itself. Sotto leaves anything like that out, but it'll show you what it left out, and you can put it back if it was right after all." }
+                p { "If you've turned on spoken punctuation, you can say things like 'comma', 'full stop', 'question mark', 'open quote' and 'close quote', and they'll be typed as punctuation. You can also say 'new line', 'new paragraph', or 'bullet point' to lay out your text." }
+                p { "Normally, each dictation is added to the end of your text as a new paragraph. If you'd rather, Sotto can put it wherever your cursor is in the text instead, or replace whatever you've selected. It'll take care of spaces and capital letters for you." }
+                p { "Sotto can also tidy up your text as it goes: writing numbers, amounts of money, dates, times and measurements with figures (so 'twenty five milligrams' becomes '25 mg'), hiding swearing, and sticking to British or American spelling." }
+                p { "Sotto highlights any words it isn't sure it heard right. Press 'Proofread' to see them (the redder, the less sure it is), and click one to correct it, pick one of the alternatives Sotto suggests, or tell it the word was right after all. Looking for alternatives takes a little longer, so you can turn it off if you'd rather." }
+                p { "You can also edit your text by voice. Say 'scratch that' to remove what you last dictated, 'undo' to undo your last voice edit, 'capitalize that' to capitalize every word you last dictated, or 'replace' followed by some words, 'with', and some other words to swap them." }
+                p { "If you've chosen to keep your recordings, you can select some text and press 'Listen to selection' to hear what you actually said. Kept recordings are encrypted with a passphrase you choose, and you'll need to unlock them with it each time you open Sotto. If you forget it, your old recordings can't be recovered!" }
+                p { "You can keep several documents on the go: use the buttons along the top to switch between them, or to start a new one, and give the one you're working on a name so you can find it again. Dictations always go into the document you're looking at." }
+                p { "If you've turned on autosaving, all your documents will be saved (encrypted with the same passphrase) as you go, and they'll come back when you next open Sotto and unlock it. If Sotto closes while you're recording, that recording will be transcribed too." }
+                p { "You can also dictate from any app with the dictation shortcut: either press it once to start and again to stop, or hold it down while you talk, depending on what you've chosen. Your text will appear here, and Sotto's icon in your system tray will show you what's happening. If you like, Sotto can also type or paste your text straight into whatever app you're using." }
+                p { "You can also export your text as a file, either as plain text, Markdown, or a Word document. If you're keeping your recordings, you can export any past dictation as well, including as subtitles that line up with the recording." }
+                p { "Press 'Settings' to choose which microphone to record from and how many of your computer's cores to use, and to set up your profiles. Each profile has its own speech recognition model (bigger ones are more accurate but slower), language, glossary of words to expect, and what happens to your text, so you can switch between them for different kinds of dictation with 'Profile' above, or with a shortcut you give each profile. The options on this screen (like spoken punctuation) change the profile you're using." }
+                p { "To get your text out without selecting it, use 'Copy all' or 'Copy last dictation', and then paste it wherever you like. You can also have Sotto copy each dictation for you as soon as it's finished." }
                 p { "If you encounter a dark red circle with an excalamation mark, it means there's been an error. That page will tell you what to do." }
+                p { "If something isn't working, you can create a diagnostic report to attach to an issue. It has Sotto's logs, the versions of Sotto and your operating system, the audio devices Sotto can see, and your settings, but never your recordings or anything you've said." }
+                div(class = "flex flex-row items-center mt-2") {
+                    button(
+                        on:click = move |_| {
+                            #[cfg(client)]
+                            state.create_report(cx);
+                        },
+                        class = "p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                    ) { "Create diagnostic report..." }
+                    p(class = "ml-2") { (state.report_message.get().to_string()) }
+                }
             }
         }
     }
@@ -130,11 +873,44 @@ fn head(cx: Scope) -> View<SsrNode> {
 #[derive(Serialize, Deserialize, Clone, ReactiveState)]
 #[rx(alias = "IndexStateRx")]
 struct IndexState {
-    /// The text that has been transcribed. This will be accumulated as the user transcribes
+    /// The text of the active document. This will be accumulated as the user transcribes
     /// more and more speech.
     text: String,
+    /// The names and IDs of all the user's documents.
+    documents: Vec<DocumentSummary>,
+    /// The ID of the document the user is working on.
+    active_document: u64,
+    /// Whether or not we've fetched the workspace from the backend yet. Until we have, we
+    /// mustn't send it anything we think is in the active document.
+    workspace_loaded: bool,
     /// The system state.
     state: SottoState,
+    /// Whether or not the error being shown (if there is one) came from a dictation, in which case
+    /// the user can retry it.
+    retry_dictation: bool,
+    /// The user's settings, which are fetched from the backend when the page loads.
+    settings: Settings,
+    /// The status of the vault that kept recordings are encrypted in.
+    vault: VaultStatus,
+    /// A message about the last attempt to unlock the vault (e.g. if the passphrase was wrong).
+    vault_message: String,
+    /// Whether or not we've tried to restore the user's autosaved work yet.
+    restored: bool,
+    /// The text of the most recent dictation, so the user can copy just that.
+    last_dictation: String,
+    /// Anything that was left out of the most recent dictation because we think Whisper made it
+    /// up, so the user can put it back if we were wrong.
+    flagged: Vec<FlaggedSegment>,
+    /// The words in the active document Whisper wasn't sure about, which the user should check.
+    uncertain: Vec<UncertainWord>,
+    /// The user's past dictations, if they're keeping their recordings and the vault is unlocked.
+    history: Vec<HistoryEntry>,
+    /// A message about the last attempt to export something (e.g. if it couldn't be done).
+    export_message: String,
+    /// A message about the last attempt to create a diagnostic report.
+    report_message: String,
+    /// A message about the profile being switched to, while its model's being got ready.
+    profile_message: String,
 }
 
 /// The state of the transcription and recording system.
@@ -149,7 +925,7 @@ enum SottoState {
     Transcribing,
     /// An error occurred somewhere in our interactions with Tauri, which should be displayed
     /// to the user.
-    Err(String),
+    Err(CommandError),
     /// We're ready to record some new text.
     ///
     /// This is both the initial state and the state after transcription has been completed.
@@ -157,13 +933,42 @@ enum SottoState {
 }
 #[cfg(client)]
 impl IndexStateRx {
+    /// Updates the document with the result of a dictation, which has already been applied to
+    /// the backend's copy of it (which might have involved voice commands editing what was
+    /// already there).
+    fn apply_update(&self, update: DocumentUpdate) {
+        self.flagged.set(update.flagged);
+        self.uncertain.set(update.uncertain);
+        if !update.inserted.is_empty() {
+            self.last_dictation.set(update.inserted);
+        }
+        // The cursor should end up after whatever was just dictated, so the next dictation
+        // follows on from it
+        let cursor = update.text[..update.cursor].encode_utf16().count() as u32;
+        self.text.set(update.text);
+        if let Some(textarea) = transcript_textarea() {
+            let _ = textarea.set_selection_range(cursor, cursor);
+        }
+    }
+    /// Shows the result of a finished dictation. If its text couldn't be delivered to another app,
+    /// the user's told, but it's still shown here, since it's in the document regardless.
+    fn finish_dictation(&self, mut update: DocumentUpdate) {
+        let output_error = update.output_error.take();
+        self.apply_update(update);
+        match output_error {
+            // Trying the dictation again would mean saying it all again, so there's no retrying
+            Some(err) => self.fail(err),
+            None => self.state.set(SottoState::Ready),
+        }
+    }
     /// Extends the currently known transcription with further text.
     ///
     /// This will extend the existing text with a new paragraph, to make delimiting clearer. It's also
     /// much easier for a human to remove paragraphing than to add it!
-    fn extend_transcription<'a>(&'a self, cx: Scope<'a>, new_text: String) {
+    fn extend_transcription(&self, new_text: String) {
         // Whisper sometimes adds some padding
         let new_text = new_text.trim();
+        self.last_dictation.set(new_text.to_string());
         let old_text = self.text.get();
         let updated_text = if old_text.is_empty() {
             // No new paragraph if this is the first thing
@@ -173,6 +978,30 @@ impl IndexStateRx {
         };
         self.text.set(updated_text);
     }
+    /// Shows the given error.
+    fn fail(&self, err: CommandError) {
+        self.retry_dictation.set(false);
+        self.state.set(SottoState::Err(err));
+    }
+    /// Shows the given error from a dictation, which the user can retry.
+    fn fail_dictation(&self, err: CommandError) {
+        self.retry_dictation.set(true);
+        self.state.set(SottoState::Err(err));
+    }
+    /// Dismisses the error being shown and tries the dictation that caused it again.
+    fn retry<'a>(&'a self, cx: Scope<'a>) {
+        self.state.set(SottoState::Ready);
+        self.dictate(cx);
+    }
+    /// Instructs Tauri to restart Sotto, after an error it can't recover from.
+    fn restart<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            // If this worked, we won't be around to see it
+            if let Err(err) = crate::tauri::restart().await {
+                self.fail(err);
+            }
+        });
+    }
     /// Instructs Tauri to begin the recording process.
     fn dictate<'a>(&'a self, cx: Scope<'a>) {
         self.state.set(SottoState::Recording);
@@ -186,16 +1015,499 @@ impl IndexStateRx {
             // This is a future which will return the transcribed text when it's done
             let res = crate::tauri::dictate().await;
             match res {
-                Ok(new_text) => {
-                    let new_text = new_text.as_string().unwrap();
-                    perseus::web_log!("{}", &new_text);
-                    self.extend_transcription(cx, new_text);
+                Ok(update) => self.finish_dictation(update),
+                // Dictations can be cancelled from outside Sotto (e.g. over D-Bus), which isn't
+                // an error as far as the user's concerned
+                Err(err) if err.code == ErrorCode::DictationCancelled => {
+                    self.state.set(SottoState::Ready)
+                }
+                Err(err) => self.fail_dictation(err),
+            };
+        });
+    }
+    /// Fetches the user's settings from Tauri.
+    fn load_settings<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::get_settings().await;
+            match res {
+                Ok(settings) => self.settings.set(settings),
+                Err(err) => return self.fail(err),
+            };
+            // If the user's settings couldn't be loaded, those were the defaults, so explain why
+            match crate::tauri::settings_error().await {
+                Ok(Some(err)) | Err(err) => self.fail(err),
+                Ok(None) => {}
+            };
+        });
+    }
+    /// Toggles whether or not recordings are kept, saving the change through Tauri.
+    fn toggle_retain_recordings<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.retain_recordings = !settings.retain_recordings;
+        self.update_settings(cx, settings);
+    }
+    /// Toggles whether or not the user's work is autosaved, saving the change through Tauri.
+    fn toggle_autosave<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.autosave = !settings.autosave;
+        spawn_local_scoped(cx, async move {
+            // If the vault's already unlocked, Tauri will have restored any saved documents when
+            // autosaving was turned on, so we need to show them
+            if self.save_settings(settings).await {
+                self.load_workspace(cx);
+            }
+        });
+    }
+    /// Toggles whether or not spoken punctuation and formatting commands are understood, saving
+    /// the change through Tauri.
+    fn toggle_spoken_commands<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        let profile = settings.profile_mut();
+        profile.spoken_commands = !profile.spoken_commands;
+        self.update_settings(cx, settings);
+    }
+    /// Toggles whether or not finished transcripts are copied to the clipboard automatically,
+    /// saving the change through Tauri.
+    fn toggle_copy_on_finish<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.copy_on_finish = !settings.copy_on_finish;
+        self.update_settings(cx, settings);
+    }
+    /// Toggles whether or not alternatives are suggested for words Whisper wasn't sure about,
+    /// saving the change through Tauri.
+    fn toggle_suggest_alternatives<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.suggest_alternatives = !settings.suggest_alternatives;
+        self.update_settings(cx, settings);
+    }
+    /// Updates the global dictation shortcut, saving the change through Tauri.
+    fn set_hotkey<'a>(&'a self, cx: Scope<'a>, hotkey: String, mode: &str) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.hotkey = hotkey;
+        settings.hotkey_mode = match mode {
+            "hold" => HotkeyMode::Hold,
+            _ => HotkeyMode::Toggle,
+        };
+        self.update_settings(cx, settings);
+    }
+    /// Updates where finished transcripts go, saving the change through Tauri.
+    fn set_output_target<'a>(&'a self, cx: Scope<'a>, target: &str) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.profile_mut().output_target = match target {
+            "type" => OutputTarget::Type,
+            "paste" => OutputTarget::Paste,
+            _ => OutputTarget::Sotto,
+        };
+        self.update_settings(cx, settings);
+    }
+    /// Updates where new dictations go in the document, saving the change through Tauri.
+    fn set_insertion_mode<'a>(&'a self, cx: Scope<'a>, mode: &str) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.insertion_mode = match mode {
+            "cursor" => InsertionMode::Cursor,
+            _ => InsertionMode::Append,
+        };
+        self.update_settings(cx, settings);
+    }
+    /// Toggles whether or not numbers, dates, times and units are written with figures, saving the
+    /// change through Tauri.
+    fn toggle_normalize_numbers<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        let normalization = &mut settings.profile_mut().normalization;
+        normalization.numbers = !normalization.numbers;
+        self.update_settings(cx, settings);
+    }
+    /// Toggles whether or not profanity is masked, saving the change through Tauri.
+    fn toggle_mask_profanity<'a>(&'a self, cx: Scope<'a>) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        let normalization = &mut settings.profile_mut().normalization;
+        normalization.mask_profanity = !normalization.mask_profanity;
+        self.update_settings(cx, settings);
+    }
+    /// Sets which variety of English spelling transcripts use, saving the change through Tauri.
+    fn set_spelling<'a>(&'a self, cx: Scope<'a>, spelling: &str) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.profile_mut().normalization.spelling = match spelling {
+            "british" => Spelling::British,
+            "american" => Spelling::American,
+            _ => Spelling::Unchanged,
+        };
+        self.update_settings(cx, settings);
+    }
+    /// Switches to the profile with the given name for future dictations. This can take a while if
+    /// its model has to be downloaded, so we say so in the meantime.
+    fn select_profile<'a>(&'a self, cx: Scope<'a>, name: String) {
+        self.profile_message
+            .set(format!("Getting the '{name}' profile ready..."));
+        spawn_local_scoped(cx, async move {
+            match crate::tauri::select_profile(&name).await {
+                Ok(_) => {
+                    self.profile_message.set(String::new());
+                    self.set_active_profile(name);
+                }
+                Err(err) => {
+                    self.profile_message.set(String::new());
+                    self.fail(err);
+                }
+            }
+        });
+    }
+    /// Records that the profile with the given name is now the active one, however it was
+    /// switched to.
+    fn set_active_profile(&self, name: String) {
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.active_profile = name;
+        self.settings.set(settings);
+    }
+    /// Instructs Tauri to save the given settings, updating them here if that succeeds.
+    fn update_settings<'a>(&'a self, cx: Scope<'a>, settings: Settings) {
+        spawn_local_scoped(cx, async move {
+            self.save_settings(settings).await;
+        });
+    }
+    /// Saves the given settings through Tauri, returning whether or not that worked.
+    async fn save_settings(&self, settings: Settings) -> bool {
+        let res = crate::tauri::update_settings(&settings).await;
+        match res {
+            Ok(_) => {
+                self.settings.set(settings);
+                true
+            }
+            Err(err) => {
+                self.fail(err);
+                false
+            }
+        }
+    }
+    /// Listens for the active profile being switched from outside the frontend (e.g. by one of the
+    /// profile shortcuts), so the main screen shows the right one.
+    fn listen_for_profiles<'a>(&'a self, cx: Scope<'a>) {
+        // As with dictation, this closure outlives the page
+        let profile_state = self.clone();
+        let on_changed =
+            Closure::<dyn FnMut(JsValue)>::new(
+                move |name: JsValue| match crate::tauri::decode_event::<String>(name) {
+                    Ok(name) => profile_state.set_active_profile(name),
+                    Err(err) => profile_state.fail(err),
+                },
+            );
+
+        spawn_local_scoped(cx, async move {
+            if let Err(err) = crate::tauri::listen_profiles(on_changed.into_js_value()).await {
+                self.fail(err);
+            }
+        });
+    }
+    /// Listens for changes to the dictation status from Tauri. This keeps us in sync when
+    /// dictation is being controlled by the global shortcut, which also means we need to listen
+    /// for the results of those dictations.
+    fn listen_for_dictation<'a>(&'a self, cx: Scope<'a>) {
+        // These closures outlive the page, so they need their own handles to the state
+        let status_state = self.clone();
+        let on_status = Closure::<dyn FnMut(JsValue)>::new(move |status: JsValue| {
+            // Errors need to stay on screen until the user's seen them
+            if let SottoState::Err(_) = *status_state.state.get_untracked() {
+                return;
+            }
+            match crate::tauri::decode_event(status) {
+                Ok(DictationStatus::Recording) => status_state.state.set(SottoState::Recording),
+                Ok(DictationStatus::Transcribing) => {
+                    status_state.state.set(SottoState::Transcribing)
+                }
+                Ok(DictationStatus::Idle) => status_state.state.set(SottoState::Ready),
+                Err(err) => status_state.fail(err),
+            }
+        });
+        let finished_state = self.clone();
+        let on_finished = Closure::<dyn FnMut(JsValue)>::new(move |res: JsValue| {
+            let res = crate::tauri::decode_event::<Result<DocumentUpdate, CommandError>>(res);
+            match res.and_then(|res| res) {
+                Ok(update) => finished_state.finish_dictation(update),
+                Err(err) if err.code == ErrorCode::DictationCancelled => {
+                    finished_state.state.set(SottoState::Ready)
+                }
+                Err(err) => finished_state.fail_dictation(err),
+            }
+        });
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::listen_dictation(
+                on_status.into_js_value(),
+                on_finished.into_js_value(),
+            )
+            .await;
+            if let Err(err) = res {
+                self.fail(err);
+            }
+        });
+    }
+    /// Fetches the status of the vault from Tauri.
+    fn load_vault_status<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::vault_status().await;
+            match res {
+                Ok(status) => {
+                    self.vault.set(status);
+                    // We can only get at the user's autosaved work once the vault's unlocked
+                    if status.unlocked && !*self.restored.get_untracked() {
+                        self.restore_work(cx);
+                    }
+                }
+                Err(err) => self.fail(err),
+            };
+        });
+    }
+    /// Instructs Tauri to unlock the vault with the given passphrase (or to set it up with that
+    /// passphrase, if this is the first time). The passphrase will be cleared either way.
+    fn unlock_vault<'a>(&'a self, cx: Scope<'a>, passphrase: &'a Signal<String>) {
+        let passphrase_str = (*passphrase.get_untracked()).clone();
+        passphrase.set(String::new());
+        if passphrase_str.is_empty() {
+            return;
+        }
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::unlock_vault(passphrase_str).await;
+            match res {
+                Ok(_) => {
+                    self.vault_message.set(String::new());
+                    self.load_vault_status(cx);
+                }
+                // Getting the passphrase wrong is hardly worth crashing over
+                Err(err) if err.code == ErrorCode::IncorrectPassphrase => {
+                    self.vault_message
+                        .set("That passphrase isn't right, please try again.".to_string());
+                }
+                Err(err) => self.fail(err),
+            };
+        });
+    }
+    /// Instructs Tauri to lock the vault.
+    fn lock_vault<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::lock_vault().await;
+            match res {
+                Ok(_) => self.load_vault_status(cx),
+                Err(err) => self.fail(err),
+            };
+        });
+    }
+    /// Restores the user's autosaved work from Tauri, including transcribing any recording
+    /// that was in progress when Sotto last closed.
+    fn restore_work<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            // Anything the user's already started on will be kept alongside what was saved
+            let res = crate::tauri::restore_workspace().await;
+            if !self.handle_workspace_result(res) {
+                return;
+            }
+            self.restored.set(true);
+
+            self.state.set(SottoState::Transcribing);
+            let res = crate::tauri::recover_recording().await;
+            match res {
+                Ok(text) => {
+                    if let Some(text) = text {
+                        self.extend_transcription(text);
+                    }
                     self.state.set(SottoState::Ready);
                 }
-                Err(err) => self.state.set(SottoState::Err(err.as_string().unwrap())),
+                Err(err) => self.fail(err),
+            };
+        });
+    }
+    /// Sends the given text to Tauri as the current state of the document, along with the user's
+    /// selection in it (as byte offsets), if it's known.
+    fn sync_document<'a>(&'a self, cx: Scope<'a>, text: String, selection: Option<(usize, usize)>) {
+        spawn_local_scoped(cx, async move {
+            let id = *self.active_document.get_untracked();
+            let res = crate::tauri::set_document(id, text, selection).await;
+            match res {
+                // The user's edits might have moved the words they need to check (or removed them),
+                // unless they switched documents and this edit was ignored
+                Ok(Some(uncertain)) => self.uncertain.set(uncertain),
+                Ok(None) => {}
+                Err(err) => self.fail(err),
+            };
+        });
+    }
+    /// Instructs Tauri to mark the uncertain word starting at the given byte offset as checked,
+    /// replacing it with the given correction if there is one.
+    fn resolve_word<'a>(&'a self, cx: Scope<'a>, start: usize, correction: Option<String>) {
+        spawn_local_scoped(cx, async move {
+            let id = *self.active_document.get_untracked();
+            let res = crate::tauri::resolve_word(id, start, correction).await;
+            match res {
+                Ok(update) => {
+                    self.uncertain.set(update.uncertain);
+                    self.text.set(update.text);
+                }
+                Err(err) => self.fail(err),
             };
         });
     }
+    /// Fetches the user's documents from Tauri, showing the active one.
+    fn load_workspace<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::get_workspace().await;
+            self.handle_workspace_result(res);
+        });
+    }
+    /// Instructs Tauri to create a new document, which becomes active.
+    fn create_document<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::create_document("Untitled".to_string()).await;
+            self.handle_workspace_result(res);
+        });
+    }
+    /// Instructs Tauri to rename the active document.
+    fn rename_document<'a>(&'a self, cx: Scope<'a>, name: String) {
+        spawn_local_scoped(cx, async move {
+            let id = *self.active_document.get_untracked();
+            let res = crate::tauri::rename_document(id, name).await;
+            self.handle_workspace_result(res);
+        });
+    }
+    /// Instructs Tauri to switch to the given document.
+    fn switch_document<'a>(&'a self, cx: Scope<'a>, id: u64) {
+        if id == *self.active_document.get_untracked() {
+            return;
+        }
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::switch_document(id).await;
+            self.handle_workspace_result(res);
+        });
+    }
+    /// Instructs Tauri to delete the active document.
+    fn delete_document<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            let id = *self.active_document.get_untracked();
+            let res = crate::tauri::delete_document(id).await;
+            self.handle_workspace_result(res);
+        });
+    }
+    /// Shows the workspace Tauri has sent us, or the error it gave instead. This returns whether
+    /// or not we got a workspace.
+    fn handle_workspace_result(&self, res: Result<WorkspaceSummary, CommandError>) -> bool {
+        match res {
+            Ok(workspace) => {
+                // The active document has to be set first, so the text is synced to the right
+                // document
+                self.active_document.set(workspace.active);
+                self.documents.set(workspace.documents);
+                self.workspace_loaded.set(true);
+                self.uncertain.set(workspace.uncertain);
+                self.text.set(workspace.text);
+                true
+            }
+            Err(err) => {
+                self.fail(err);
+                false
+            }
+        }
+    }
+    /// Instructs Tauri to copy the given text to the clipboard.
+    fn copy_text<'a>(&'a self, cx: Scope<'a>, text: String) {
+        if text.is_empty() {
+            return;
+        }
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::copy_text(text).await;
+            if let Err(err) = res {
+                self.fail(err);
+            }
+        });
+    }
+    /// Fetches the user's history from Tauri, if there's any to fetch.
+    fn load_history<'a>(&'a self, cx: Scope<'a>) {
+        if !self.settings.get_untracked().retain_recordings || !self.vault.get_untracked().unlocked
+        {
+            return;
+        }
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::list_history().await;
+            match res {
+                Ok(history) => self.history.set(history),
+                Err(err) => self.fail(err),
+            };
+        });
+    }
+    /// Instructs Tauri to export the given source (`document` or the ID of a history entry) in
+    /// the given format, which will ask the user where to save it.
+    fn export<'a>(&'a self, cx: Scope<'a>, source: &str, format: &str) {
+        let source = match source.parse() {
+            Ok(id) => ExportSource::History(id),
+            Err(_) => ExportSource::Document((*self.text.get_untracked()).clone()),
+        };
+        let format = match format {
+            "markdown" => ExportFormat::Markdown,
+            "docx" => ExportFormat::Docx,
+            "srt" => ExportFormat::Srt,
+            "vtt" => ExportFormat::Vtt,
+            _ => ExportFormat::Txt,
+        };
+        self.export_message.set(String::new());
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::export_text(&source, format).await;
+            match res {
+                Ok(_) => {}
+                // This just means the user picked something that can't be done
+                Err(err) if err.code == ErrorCode::Export => self
+                    .export_message
+                    .set(format!("Couldn't export that: {}.", err.message)),
+                Err(err) => self.fail(err),
+            }
+        });
+    }
+    /// Instructs Tauri to create a diagnostic report, which will ask the user where to save it.
+    /// Failing to create one isn't worth interrupting the user over (they might already be
+    /// looking at an error), so this just says what happened.
+    fn create_report<'a>(&'a self, cx: Scope<'a>) {
+        self.report_message.set(String::new());
+
+        spawn_local_scoped(cx, async move {
+            match crate::tauri::create_diagnostic_report().await {
+                Ok(true) => self
+                    .report_message
+                    .set("Saved! You can attach it to an issue on GitHub.".to_string()),
+                Ok(false) => {}
+                Err(err) => self
+                    .report_message
+                    .set(format!("Couldn't create a report: {}.", err.message)),
+            }
+        });
+    }
+    /// Sends the contents of the transcript textarea and the user's selection in it to Tauri.
+    fn sync_selection<'a>(&'a self, cx: Scope<'a>) {
+        if let Some((text, selection)) = textarea_selection() {
+            self.sync_document(cx, text, Some(selection));
+        }
+    }
+    /// Gets the text the user currently has selected in the transcript textarea.
+    fn selected_text(&self) -> String {
+        match textarea_selection() {
+            Some((text, (start, end))) => text[start..end].to_string(),
+            None => String::new(),
+        }
+    }
+    /// Instructs Tauri to play back the recording behind the currently selected text.
+    fn play_selection<'a>(&'a self, cx: Scope<'a>) {
+        let selection = self.selected_text();
+        if selection.trim().is_empty() {
+            return;
+        }
+
+        spawn_local_scoped(cx, async move {
+            let res = crate::tauri::play_recording_of(selection).await;
+            if let Err(err) = res {
+                self.fail(err);
+            }
+        });
+    }
     /// Instructs Tauri to stop recording audio.
     fn end_recording<'a>(&'a self, cx: Scope<'a>) {
         // This will resolve instantly, but Tauri does everything asynchronously, so we still need it
@@ -203,17 +1515,66 @@ impl IndexStateRx {
             let res = crate::tauri::end_recording().await;
             match res {
                 Ok(_) => self.state.set(SottoState::Transcribing),
-                Err(err) => self.state.set(SottoState::Err(err.as_string().unwrap())),
+                Err(err) => self.fail_dictation(err),
             };
         });
     }
 }
 
+/// Gets the transcript textarea, if it's been rendered yet.
+#[cfg(client)]
+fn transcript_textarea() -> Option<web_sys::HtmlTextAreaElement> {
+    web_sys::window()?
+        .document()?
+        .get_element_by_id("transcript")
+        .map(|elem| elem.unchecked_into())
+}
+
+/// Gets the contents of the transcript textarea, along with the user's selection in it, as
+/// start and end byte offsets into those contents.
+#[cfg(client)]
+fn textarea_selection() -> Option<(String, (usize, usize))> {
+    let textarea = transcript_textarea()?;
+    let text = textarea.value();
+    // These are in UTF-16 code units (thanks JS...), so we need to convert them
+    let start = textarea.selection_start().ok().flatten().unwrap_or(0) as usize;
+    let end = textarea.selection_end().ok().flatten().unwrap_or(0) as usize;
+    let to_byte_offset = |utf16_offset: usize| {
+        let mut units = 0;
+        for (idx, c) in text.char_indices() {
+            if units >= utf16_offset {
+                return idx;
+            }
+            units += c.len_utf16();
+        }
+        text.len()
+    };
+    let start = to_byte_offset(start);
+    let end = to_byte_offset(end).max(start);
+
+    Some((text, (start, end)))
+}
+
 #[engine_only_fn]
 async fn get_build_state(_: StateGeneratorInfo<()>) -> IndexState {
     IndexState {
         text: String::new(),
+        documents: Vec::new(),
+        active_document: 0,
+        workspace_loaded: false,
         state: SottoState::Ready,
+        retry_dictation: false,
+        settings: Settings::default(),
+        vault: VaultStatus::default(),
+        vault_message: String::new(),
+        restored: false,
+        last_dictation: String::new(),
+        flagged: Vec::new(),
+        uncertain: Vec::new(),
+        history: Vec::new(),
+        export_message: String::new(),
+        report_message: String::new(),
+        profile_message: String::new(),
     }
 }
 
@@ -0,0 +1,503 @@
+use perseus::prelude::*;
+use serde::{Deserialize, Serialize};
+use sotto_types::{InsertionMode, Model, OutputTarget, Profile, Settings};
+use sycamore::prelude::*;
+
+/// The languages offered on the settings page, as the codes Whisper knows them by and their
+/// names. Whisper understands plenty more, but these are the ones it's reliable in (and the first
+/// four have built-in spoken commands).
+const LANGUAGES: [(&str, &str); 7] = [
+    ("en", "English"),
+    ("fr", "Français"),
+    ("de", "Deutsch"),
+    ("es", "Español"),
+    ("it", "Italiano"),
+    ("nl", "Nederlands"),
+    ("pt", "Português"),
+];
+
+#[auto_scope]
+fn settings_page<G: Html>(cx: Scope, state: &SettingsStateRx) -> View<G> {
+    // These are edited locally, and only saved when the user presses 'Save'
+    // Empty for the system default
+    let input_device = create_signal(cx, String::new());
+    // Empty for automatic
+    let threads = create_signal(cx, String::new());
+    let insertion_mode = create_signal(cx, String::new());
+    let copy_on_finish = create_signal(cx, false);
+    // The user's profiles as they're being edited, and which one's in the form below
+    let profiles = create_signal(cx, Vec::<Profile>::new());
+    let editing = create_signal(cx, 0usize);
+    let name = create_signal(cx, String::new());
+    let profile_hotkey = create_signal(cx, String::new());
+    let model = create_signal(cx, String::new());
+    let language = create_signal(cx, String::new());
+    let beam_size = create_signal(cx, String::new());
+    let temperature = create_signal(cx, String::new());
+    // One word or phrase per line
+    let glossary = create_signal(cx, String::new());
+    let output_target = create_signal(cx, String::new());
+    let spoken_commands = create_signal(cx, false);
+    let numbers = create_signal(cx, false);
+    let mask_profanity = create_signal(cx, false);
+    let sentence_case = create_signal(cx, false);
+    // One term per line, written how it should always be written
+    let casing = create_signal(cx, String::new());
+
+    // Fills the profile form with the profile being edited
+    let load_profile = move || {
+        let profiles = profiles.get_untracked();
+        let profile = match profiles.get(*editing.get_untracked()) {
+            Some(profile) => profile,
+            None => return,
+        };
+        name.set(profile.name.clone());
+        profile_hotkey.set(profile.hotkey.clone());
+        model.set(model_value(profile.model).to_string());
+        language.set(profile.language.clone());
+        beam_size.set(profile.decoding.beam_size.to_string());
+        temperature.set(profile.decoding.temperature.to_string());
+        glossary.set(profile.glossary.join("\n"));
+        output_target.set(output_target_value(profile.output_target).to_string());
+        spoken_commands.set(profile.spoken_commands);
+        numbers.set(profile.normalization.numbers);
+        mask_profanity.set(profile.normalization.mask_profanity);
+        sentence_case.set(profile.normalization.sentence_case);
+        casing.set(profile.normalization.casing.join("\n"));
+    };
+    // Writes the profile form back into the profile being edited
+    let store_profile = move || {
+        let mut edited = (*profiles.get_untracked()).clone();
+        let profile = match edited.get_mut(*editing.get_untracked()) {
+            Some(profile) => profile,
+            None => return,
+        };
+        profile.name = name.get_untracked().trim().to_string();
+        profile.hotkey = profile_hotkey.get_untracked().trim().to_string();
+        profile.model = parse_model(&model.get_untracked());
+        profile.language = (*language.get_untracked()).clone();
+        // The inputs won't let these be anything silly, but they can be left empty
+        profile.decoding.beam_size = beam_size.get_untracked().trim().parse().unwrap_or(1);
+        profile.decoding.temperature = temperature.get_untracked().trim().parse().unwrap_or(0.0);
+        profile.glossary = glossary
+            .get_untracked()
+            .lines()
+            .map(|term| term.trim().to_string())
+            .filter(|term| !term.is_empty())
+            .collect();
+        profile.output_target = parse_output_target(&output_target.get_untracked());
+        profile.spoken_commands = *spoken_commands.get_untracked();
+        profile.normalization.numbers = *numbers.get_untracked();
+        profile.normalization.mask_profanity = *mask_profanity.get_untracked();
+        profile.normalization.sentence_case = *sentence_case.get_untracked();
+        profile.normalization.casing = casing
+            .get_untracked()
+            .lines()
+            .map(|term| term.trim().to_string())
+            .filter(|term| !term.is_empty())
+            .collect();
+        profiles.set(edited);
+    };
+
+    create_effect(cx, move || {
+        let settings = state.settings.get();
+        input_device.set(settings.input_device.clone().unwrap_or_default());
+        threads.set(
+            settings
+                .threads
+                .map(|threads| threads.to_string())
+                .unwrap_or_default(),
+        );
+        insertion_mode.set(match settings.insertion_mode {
+            InsertionMode::Append => "append".to_string(),
+            InsertionMode::Cursor => "cursor".to_string(),
+        });
+        copy_on_finish.set(settings.copy_on_finish);
+        // Start with whichever profile's active, since that's probably the one the user wants
+        editing.set(
+            settings
+                .profiles
+                .iter()
+                .position(|profile| profile.name == settings.active_profile)
+                .unwrap_or(0),
+        );
+        profiles.set(settings.profiles.clone());
+        load_profile();
+    });
+
+    // The settings in the build state are just the defaults, so fetch the user's real ones
+    #[cfg(client)]
+    state.load(cx);
+
+    let device_options = create_memo(cx, move || {
+        View::new_fragment(
+            state
+                .input_devices
+                .get()
+                .iter()
+                .map(|name| {
+                    let name = name.clone();
+                    view! { cx, option(value = name.clone()) { (name.clone()) } }
+                })
+                .collect(),
+        )
+    });
+    let profile_tabs = create_memo(cx, move || {
+        let current = *editing.get();
+        View::new_fragment(
+            profiles
+                .get()
+                .iter()
+                .enumerate()
+                .map(|(idx, profile)| {
+                    let name = profile.name.clone();
+                    let class = if idx == current {
+                        "mr-2 mb-2 p-2 bg-neutral-700 text-white rounded-md"
+                    } else {
+                        "mr-2 mb-2 p-2 bg-neutral-400 text-white rounded-md hover:bg-neutral-700 transition-colors"
+                    };
+                    view! { cx,
+                        button(
+                            class = class,
+                            on:click = move |_| {
+                                store_profile();
+                                editing.set(idx);
+                                load_profile();
+                            }
+                        ) { (name) }
+                    }
+                })
+                .collect(),
+        )
+    });
+    let language_options = create_memo(cx, move || {
+        let mut options = LANGUAGES
+            .iter()
+            .map(|(code, name)| view! { cx, option(value = *code) { (*name) } })
+            .collect::<Vec<_>>();
+        // Whatever the profile uses should always be there, even if we don't list it
+        let current = profiles
+            .get()
+            .get(*editing.get())
+            .map(|profile| profile.language.clone())
+            .unwrap_or_default();
+        if !current.is_empty() && !LANGUAGES.iter().any(|(code, _)| *code == current) {
+            options.push(view! { cx, option(value = current.clone()) { (current.clone()) } });
+        }
+        View::new_fragment(options)
+    });
+
+    view! { cx,
+        div(class = "w-full min-h-screen flex flex-col items-center p-8 text-xl") {
+            div(class = "flex flex-row items-center w-full max-w-2xl mb-4") {
+                a(href = "", class = "p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors") { "Back" }
+                h1(class = "text-3xl ml-4") { "Settings" }
+            }
+            div(class = "flex flex-col w-full max-w-2xl") {
+                h2(class = "text-2xl mt-4") { "Recording" }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Microphone"
+                    select(class = "ml-4 p-2 border border-black rounded-md", bind:value = input_device) {
+                        option(value = "") { "System default" }
+                        (*device_options.get())
+                    }
+                }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Threads"
+                    input(
+                        type = "number",
+                        min = "1",
+                        class = "ml-4 p-2 border border-black rounded-md",
+                        placeholder = "Automatic",
+                        bind:value = threads
+                    ) {}
+                }
+                p(class = "text-base text-neutral-600 mt-1") { "How many of your computer's cores transcription can use. Leave this empty to use all of them." }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Where new text is added"
+                    select(class = "ml-4 p-2 border border-black rounded-md", bind:value = insertion_mode) {
+                        option(value = "append") { "At the end" }
+                        option(value = "cursor") { "Where my cursor is" }
+                    }
+                }
+                label(class = "flex items-center mt-4") {
+                    input(type = "checkbox", class = "mr-2 h-6 w-6", bind:checked = copy_on_finish) {}
+                    "Copy my text when I finish dictating"
+                }
+
+                h2(class = "text-2xl mt-8") { "Profiles" }
+                p(class = "text-base text-neutral-600 mt-1") { "Each profile bundles up how Sotto transcribes and what it does with your text, so you can switch between them for different kinds of dictation." }
+                div(class = "flex flex-row flex-wrap items-center mt-4") {
+                    (*profile_tabs.get())
+                    button(
+                        on:click = move |_| {
+                            store_profile();
+                            let mut edited = (*profiles.get_untracked()).clone();
+                            // Names have to be unique, so find one that isn't taken
+                            let mut number = edited.len() + 1;
+                            while edited.iter().any(|profile| profile.name == format!("Profile {number}")) {
+                                number += 1;
+                            }
+                            edited.push(Profile {
+                                name: format!("Profile {number}"),
+                                ..Profile::default()
+                            });
+                            editing.set(edited.len() - 1);
+                            profiles.set(edited);
+                            load_profile();
+                        },
+                        class = "mb-2 p-2 border border-black rounded-md hover:bg-neutral-200 transition-colors"
+                    ) { "New profile" }
+                }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Name"
+                    input(class = "ml-4 p-2 border border-black rounded-md", bind:value = name) {}
+                }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Shortcut to switch to it"
+                    input(class = "ml-4 p-2 border border-black rounded-md", placeholder = "None", bind:value = profile_hotkey) {}
+                }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Model"
+                    select(class = "ml-4 p-2 border border-black rounded-md", bind:value = model) {
+                        option(value = "tiny") { "Tiny (fastest)" }
+                        option(value = "base") { "Base" }
+                        option(value = "small") { "Small" }
+                        option(value = "medium") { "Medium" }
+                        option(value = "large") { "Large (most accurate)" }
+                    }
+                }
+                p(class = "text-base text-neutral-600 mt-1") { "Bigger models are more accurate, but slower, and need to be downloaded the first time they're used." }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Language"
+                    select(class = "ml-4 p-2 border border-black rounded-md", bind:value = language) {
+                        (*language_options.get())
+                    }
+                }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Beam size"
+                    input(type = "number", min = "1", max = "10", class = "ml-4 p-2 border border-black rounded-md", bind:value = beam_size) {}
+                }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Temperature"
+                    input(type = "number", min = "0", max = "1", step = "0.1", class = "ml-4 p-2 border border-black rounded-md", bind:value = temperature) {}
+                }
+                p(class = "text-base text-neutral-600 mt-1") { "A beam size above 1 makes Sotto weigh up several transcriptions at once, which is more accurate but slower. A temperature above 0 adds some randomness, which can help if Sotto keeps getting stuck." }
+                label(class = "flex flex-col mt-4") {
+                    "Glossary"
+                    textarea(class = "mt-2 p-2 border border-black rounded-md h-32", placeholder = "One word or phrase per line", bind:value = glossary) {}
+                }
+                p(class = "text-base text-neutral-600 mt-1") { "Words Sotto should expect to hear, like names or technical terms, so it's more likely to get them right." }
+                label(class = "flex items-center justify-between mt-4") {
+                    "Where your text goes"
+                    select(class = "ml-4 p-2 border border-black rounded-md", bind:value = output_target) {
+                        option(value = "sotto") { "Only put my text here" }
+                        option(value = "type") { "Also type my text into other apps" }
+                        option(value = "paste") { "Also paste my text into other apps" }
+                    }
+                }
+                label(class = "flex items-center mt-4") {
+                    input(type = "checkbox", class = "mr-2 h-6 w-6", bind:checked = spoken_commands) {}
+                    "Understand spoken punctuation"
+                }
+                label(class = "flex items-center mt-4") {
+                    input(type = "checkbox", class = "mr-2 h-6 w-6", bind:checked = numbers) {}
+                    "Write numbers, dates and units as figures"
+                }
+                label(class = "flex items-center mt-4") {
+                    input(type = "checkbox", class = "mr-2 h-6 w-6", bind:checked = mask_profanity) {}
+                    "Hide swearing"
+                }
+                label(class = "flex items-center mt-4") {
+                    input(type = "checkbox", class = "mr-2 h-6 w-6", bind:checked = sentence_case) {}
+                    "Start every sentence with a capital"
+                }
+                label(class = "flex flex-col mt-4") {
+                    "Capitalization"
+                    textarea(class = "mt-2 p-2 border border-black rounded-md h-32", placeholder = "One word or phrase per line", bind:value = casing) {}
+                }
+                p(class = "text-base text-neutral-600 mt-1") { "Words Sotto should always write exactly like this, like iPhone or GitHub, however they come out." }
+                button(
+                    on:click = move |_| {
+                        let mut edited = (*profiles.get_untracked()).clone();
+                        edited.remove(*editing.get_untracked());
+                        editing.set(0);
+                        profiles.set(edited);
+                        load_profile();
+                    },
+                    // There always has to be at least one profile
+                    disabled = profiles.get().len() < 2,
+                    class = "mt-4 self-start p-2 bg-neutral-400 text-white rounded-md hover:bg-red-700 disabled:opacity-50 transition-colors"
+                ) { "Delete this profile" }
+
+                div(class = "flex flex-row items-center mt-8") {
+                    button(
+                        on:click = move |_| {
+                            store_profile();
+                            #[cfg(client)]
+                            state.save(
+                                cx,
+                                &input_device.get_untracked(),
+                                &threads.get_untracked(),
+                                &insertion_mode.get_untracked(),
+                                *copy_on_finish.get_untracked(),
+                                (*profiles.get_untracked()).clone(),
+                            );
+                        },
+                        disabled = !*state.loaded.get(),
+                        class = "p-2 bg-emerald-400 text-white rounded-md hover:bg-emerald-700 transition-colors"
+                    ) { "Save" }
+                    p(class = "ml-4") { (state.message.get().to_string()) }
+                }
+            }
+        }
+    }
+}
+
+/// Gets the value the given model has in the model selector.
+fn model_value(model: Model) -> &'static str {
+    match model {
+        Model::WhisperTiny => "tiny",
+        Model::WhisperBase => "base",
+        Model::WhisperSmall => "small",
+        Model::WhisperMedium => "medium",
+        Model::WhisperLarge => "large",
+    }
+}
+/// Gets the model with the given value in the model selector.
+fn parse_model(value: &str) -> Model {
+    match value {
+        "tiny" => Model::WhisperTiny,
+        "small" => Model::WhisperSmall,
+        "medium" => Model::WhisperMedium,
+        "large" => Model::WhisperLarge,
+        _ => Model::WhisperBase,
+    }
+}
+/// Gets the value the given output target has in the output selector.
+fn output_target_value(target: OutputTarget) -> &'static str {
+    match target {
+        OutputTarget::Sotto => "sotto",
+        OutputTarget::Type => "type",
+        OutputTarget::Paste => "paste",
+    }
+}
+/// Gets the output target with the given value in the output selector.
+fn parse_output_target(value: &str) -> OutputTarget {
+    match value {
+        "type" => OutputTarget::Type,
+        "paste" => OutputTarget::Paste,
+        _ => OutputTarget::Sotto,
+    }
+}
+
+#[engine_only_fn]
+fn head(cx: Scope) -> View<SsrNode> {
+    view! { cx,
+        title { "Sotto settings" }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, ReactiveState)]
+#[rx(alias = "SettingsStateRx")]
+struct SettingsState {
+    /// The user's settings, which are fetched from the backend when the page loads.
+    settings: Settings,
+    /// Whether or not we've fetched the settings yet. Until we have, we mustn't save anything, or
+    /// we'd overwrite the user's settings with the defaults.
+    loaded: bool,
+    /// The names of the microphones the user can pick from.
+    input_devices: Vec<String>,
+    /// A message about the last attempt to load or save the settings.
+    message: String,
+}
+
+#[cfg(client)]
+impl SettingsStateRx {
+    /// Fetches the user's settings and the microphones they can choose from from Tauri.
+    fn load<'a>(&'a self, cx: Scope<'a>) {
+        spawn_local_scoped(cx, async move {
+            match crate::tauri::get_settings().await {
+                Ok(settings) => {
+                    self.settings.set(settings);
+                    self.loaded.set(true);
+                }
+                Err(err) => self
+                    .message
+                    .set(format!("Couldn't load your settings: {}.", err.message)),
+            }
+            // Without these, the user can still use the default microphone
+            if let Ok(devices) = crate::tauri::list_input_devices().await {
+                self.input_devices.set(devices);
+            }
+        });
+    }
+    /// Instructs Tauri to save the settings and profiles from the form, on top of the rest of the
+    /// user's settings.
+    fn save<'a>(
+        &'a self,
+        cx: Scope<'a>,
+        input_device: &str,
+        threads: &str,
+        insertion_mode: &str,
+        copy_on_finish: bool,
+        profiles: Vec<Profile>,
+    ) {
+        let threads = match threads.trim() {
+            "" => None,
+            threads => match threads.parse::<usize>() {
+                Ok(threads) if threads > 0 => Some(threads),
+                _ => {
+                    self.message
+                        .set("Threads should be a whole number, or left empty.".to_string());
+                    return;
+                }
+            },
+        };
+        let mut settings = (*self.settings.get_untracked()).clone();
+        settings.input_device = Some(input_device.to_string()).filter(|name| !name.is_empty());
+        settings.threads = threads;
+        settings.insertion_mode = match insertion_mode {
+            "cursor" => InsertionMode::Cursor,
+            _ => InsertionMode::Append,
+        };
+        settings.copy_on_finish = copy_on_finish;
+        settings.profiles = profiles;
+        // A new model has to be loaded (and maybe downloaded) before this finishes
+        self.message.set("Saving...".to_string());
+
+        spawn_local_scoped(cx, async move {
+            match crate::tauri::update_settings(&settings).await {
+                Ok(_) => {
+                    // If the active profile's been deleted or renamed, Tauri will have fallen back
+                    // to the first one, so get what it actually saved
+                    if let Ok(saved) = crate::tauri::get_settings().await {
+                        self.settings.set(saved);
+                    }
+                    self.message.set("Saved!".to_string());
+                }
+                Err(err) => self
+                    .message
+                    .set(format!("Couldn't save your settings: {}.", err.message)),
+            }
+        });
+    }
+}
+
+#[engine_only_fn]
+async fn get_build_state(_: StateGeneratorInfo<()>) -> SettingsState {
+    SettingsState {
+        settings: Settings::default(),
+        loaded: false,
+        input_devices: Vec::new(),
+        message: String::new(),
+    }
+}
+
+pub fn get_template<G: Html>() -> Template<G> {
+    Template::build("settings")
+        .view_with_state(settings_page)
+        .head(head)
+        .build_state_fn(get_build_state)
+        .build()
+}